@@ -0,0 +1,504 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use cosmic::{iced, prelude::*, widget};
+use udisks2::{zbus::zvariant::OwnedObjectPath, Client};
+
+use super::{data_path, error::Error, message::AppMessage, progress::Reporter};
+use crate::widget::{graph::Series, ring::section_color, Graph};
+
+/// Direct IO needs buffers and offsets aligned to the logical block size, 4 KiB covers all drives.
+pub const ALIGN: u64 = 4096;
+const ACCESS_SAMPLES: u64 = 100;
+
+/// Sample counts and sizes offered in the benchmark dialog.
+pub const SAMPLE_COUNTS: [u64; 4] = [10, 25, 50, 100];
+pub const SAMPLE_SIZES: [u64; 4] = [1 << 20, 10 << 20, 50 << 20, 100 << 20];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BenchmarkOptions {
+    pub samples: u64,
+    pub sample_size: u64,
+    /// Writes back the data just read, only offered for drives without mounted file systems
+    pub write: bool,
+}
+
+/// Result of a single benchmark run.
+#[derive(Clone, Debug, Default)]
+pub struct Benchmark {
+    /// Seconds since the Unix epoch
+    pub time: u64,
+    pub sample_size: u64,
+    /// Rates in bytes per second, evenly spaced from the start to the end of the device
+    pub read: Vec<u64>,
+    pub write: Vec<u64>,
+    /// Access times in microseconds, at random offsets
+    pub access: Vec<u64>,
+}
+
+fn average(values: &[u64]) -> Option<u64> {
+    match values.len() {
+        0 => None,
+        len => Some(values.iter().sum::<u64>() / len as u64),
+    }
+}
+
+fn list(values: &[u64]) -> String {
+    values
+        .iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_list(list: &str) -> Option<Vec<u64>> {
+    list.split(',')
+        .filter(|value| !value.is_empty())
+        .map(|value| value.parse().ok())
+        .collect()
+}
+
+/// Rates use decimal units like the sizes udisks formats.
+pub fn rate_for_display(rate: u64) -> String {
+    match rate {
+        0..1_000_000 => format!("{:.1} kB/s", rate as f64 / 1e3),
+        1_000_000..1_000_000_000 => format!("{:.1} MB/s", rate as f64 / 1e6),
+        _ => format!("{:.2} GB/s", rate as f64 / 1e9),
+    }
+}
+
+/// Dates in UTC, there is no time zone database to convert them to local time.
+pub fn date_for_display(time: u64) -> String {
+    // Days to civil date, after Howard Hinnant's `civil_from_days`
+    let days = time / 86400 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year}-{month:02}-{day:02} {:02}:{:02} UTC",
+        time % 86400 / 3600,
+        time % 3600 / 60
+    )
+}
+
+impl Benchmark {
+    pub fn read_average(&self) -> Option<u64> {
+        average(&self.read)
+    }
+
+    pub fn write_average(&self) -> Option<u64> {
+        average(&self.write)
+    }
+
+    pub fn access_average(&self) -> Option<u64> {
+        average(&self.access)
+    }
+
+    /// One line per run: time, sample size, then the read, write and access samples.
+    fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\n",
+            self.time,
+            self.sample_size,
+            list(&self.read),
+            list(&self.write),
+            list(&self.access)
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        Some(Self {
+            time: fields.next()?.parse().ok()?,
+            sample_size: fields.next()?.parse().ok()?,
+            read: parse_list(fields.next()?)?,
+            write: parse_list(fields.next()?)?,
+            access: parse_list(fields.next()?)?,
+        })
+    }
+
+    fn graph(&self) -> Graph {
+        let rate_max = self
+            .read
+            .iter()
+            .chain(&self.write)
+            .copied()
+            .max()
+            .unwrap_or(0) as f32;
+        let access_max = self.access.iter().copied().max().unwrap_or(0) as f32;
+        let values = |values: &[u64]| values.iter().map(|value| *value as f32).collect();
+        Graph {
+            series: vec![
+                Series {
+                    color: section_color(0),
+                    values: values(&self.read),
+                    max: rate_max * 1.1,
+                    points: false,
+                },
+                Series {
+                    color: section_color(1),
+                    values: values(&self.write),
+                    max: rate_max * 1.1,
+                    points: false,
+                },
+                Series {
+                    color: section_color(2),
+                    values: values(&self.access),
+                    max: access_max * 1.1,
+                    points: true,
+                },
+            ],
+        }
+    }
+}
+
+/// Past benchmark runs of a drive, kept in the user's data directory.
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    pub runs: Vec<Benchmark>,
+    graph: Graph,
+}
+
+impl History {
+    pub fn load(key: &str) -> Self {
+        let runs: Vec<_> = data_path("benchmarks", key)
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map(|history| history.lines().filter_map(Benchmark::from_line).collect())
+            .unwrap_or_default();
+        Self {
+            graph: runs.last().map(Benchmark::graph).unwrap_or_default(),
+            runs,
+        }
+    }
+
+    /// Adds a run that was just measured, whether or not it could be saved.
+    pub fn push(&mut self, benchmark: Benchmark) {
+        self.graph = benchmark.graph();
+        self.runs.push(benchmark);
+    }
+
+    /// Takes over the runs of `previous` that are newer than the loaded ones, those could not
+    /// be saved and would be lost with a reload.
+    pub fn keep_unsaved(&mut self, previous: &History) {
+        let last = self.runs.last().map_or(0, |run| run.time);
+        for run in previous.runs.iter().filter(|run| run.time > last) {
+            self.push(run.clone());
+        }
+    }
+
+    pub fn save(key: &str, benchmark: &Benchmark) -> Result<(), Error> {
+        let Some(path) = data_path("benchmarks", key) else {
+            return Err(Error::new(
+                "The drive has no serial number to store the results under",
+                true,
+            ));
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(benchmark.to_line().as_bytes())?;
+        Ok(())
+    }
+
+    pub fn view(&self) -> Option<Element<Result<AppMessage, Error>>> {
+        let last = self.runs.last()?;
+        let mut section =
+            widget::settings::section()
+                .title("Benchmark")
+                .add(widget::settings::item(
+                    "Last Run",
+                    widget::text::body(date_for_display(last.time)),
+                ));
+        if let Some(rate) = last.read_average() {
+            section = section.add(widget::settings::item(
+                "Average Read Rate",
+                widget::text::body(rate_for_display(rate)),
+            ));
+        }
+        if let Some(rate) = last.write_average() {
+            section = section.add(widget::settings::item(
+                "Average Write Rate",
+                widget::text::body(rate_for_display(rate)),
+            ));
+        }
+        if let Some(access) = last.access_average() {
+            section = section.add(widget::settings::item(
+                "Average Access Time",
+                widget::text::body(format!("{:.2} ms", access as f64 / 1000.0)),
+            ));
+        }
+        section = section.add(widget::settings::flex_item(
+            "Read, Write and Access Time",
+            widget::canvas(&self.graph)
+                .width(iced::Length::Fill)
+                .height(iced::Length::Fixed(160.0)),
+        ));
+        // Earlier runs, newest first, to spot a drive getting slower
+        for run in self.runs.iter().rev().skip(1).take(5) {
+            section = section.add(widget::settings::item(
+                date_for_display(run.time),
+                widget::text::body(format!(
+                    "{} read, {} write, {} access",
+                    run.read_average().map_or("-".to_string(), rate_for_display),
+                    run.write_average()
+                        .map_or("-".to_string(), rate_for_display),
+                    run.access_average()
+                        .map_or("-".to_string(), |access| format!(
+                            "{:.2} ms",
+                            access as f64 / 1000.0
+                        )),
+                )),
+            ));
+        }
+        Some(section.into())
+    }
+}
+
+/// Whether a file system on the device or one of its partitions is mounted.
+pub async fn is_mounted(client: &Client, block_path: &OwnedObjectPath) -> Result<bool, Error> {
+    let object = client.object(block_path.clone()).unwrap();
+    let mut paths = vec![block_path.clone()];
+    if let Ok(ptable) = object.partition_table().await {
+        paths.extend(ptable.partitions().await?);
+    }
+    for path in paths {
+        if let Ok(fs) = client.object(path).unwrap().filesystem().await {
+            if !fs.mount_points().await?.is_empty() {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Runs the benchmark on a device or image file opened for it.
+///
+/// The write test writes every sample back unchanged right after reading it, a sample that was
+/// started is always completed even when the benchmark is cancelled.
+pub fn run(
+    file: &mut File,
+    capacity: u64,
+    options: BenchmarkOptions,
+    reporter: &mut Reporter,
+) -> Result<Benchmark, Error> {
+    let sample_size = options.sample_size.min(capacity / ALIGN * ALIGN);
+    if sample_size == 0 {
+        return Err(Error::new("The device is too small to benchmark", true));
+    }
+    let mut storage = vec![0u8; (sample_size + ALIGN) as usize];
+    let start = storage.as_ptr().align_offset(ALIGN as usize);
+    let buffer = &mut storage[start..start + sample_size as usize];
+
+    let samples = options.samples.max(1);
+    let offsets: Vec<u64> = (0..samples)
+        .map(|sample| match samples {
+            1 => 0,
+            _ => (capacity - sample_size) * sample / (samples - 1) / ALIGN * ALIGN,
+        })
+        .collect();
+    let rate = |started: Instant| (sample_size as f64 / started.elapsed().as_secs_f64()) as u64;
+
+    let mut benchmark = Benchmark {
+        time: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs()),
+        sample_size,
+        ..Default::default()
+    };
+
+    reporter.stage("Measuring Read Rate", samples * sample_size);
+    for (sample, offset) in offsets.iter().enumerate() {
+        reporter.check_cancelled()?;
+        file.seek(SeekFrom::Start(*offset))?;
+        let started = Instant::now();
+        file.read_exact(buffer)?;
+        benchmark.read.push(rate(started));
+        reporter.update((sample as u64 + 1) * sample_size);
+    }
+
+    if options.write {
+        reporter.stage("Measuring Write Rate", samples * sample_size);
+        for (sample, offset) in offsets.iter().enumerate() {
+            reporter.check_cancelled()?;
+            file.seek(SeekFrom::Start(*offset))?;
+            file.read_exact(buffer)?;
+            file.seek(SeekFrom::Start(*offset))?;
+            let started = Instant::now();
+            file.write_all(buffer)?;
+            file.sync_data()?;
+            benchmark.write.push(rate(started));
+            reporter.update((sample as u64 + 1) * sample_size);
+        }
+    }
+
+    reporter.stage("Measuring Access Time", ACCESS_SAMPLES * ALIGN);
+    let blocks = capacity / ALIGN;
+    // xorshift, seeded from the clock, is random enough to defeat read-ahead
+    let mut state = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos() as u64)
+        | 1;
+    for sample in 0..ACCESS_SAMPLES {
+        reporter.check_cancelled()?;
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        file.seek(SeekFrom::Start(state % blocks * ALIGN))?;
+        let started = Instant::now();
+        file.read_exact(&mut buffer[..ALIGN as usize])?;
+        benchmark.access.push(started.elapsed().as_micros() as u64);
+        reporter.update((sample + 1) * ALIGN);
+    }
+
+    Ok(benchmark)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    /// A temporary file filled with a pattern, removed again when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, size: u64) -> (Self, File) {
+            let path = std::env::temp_dir()
+                .join(format!("andromeda-benchmark-{}-{name}", std::process::id()));
+            let data: Vec<u8> = (0..size).map(|index| (index % 251) as u8).collect();
+            std::fs::write(&path, &data).unwrap();
+            let file = std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&path)
+                .unwrap();
+            (Self(path), file)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn options(samples: u64, sample_size: u64, write: bool) -> BenchmarkOptions {
+        BenchmarkOptions {
+            samples,
+            sample_size,
+            write,
+        }
+    }
+
+    #[test]
+    fn read_only() {
+        let (_temp, mut file) = TempFile::new("read", 8 * MIB);
+        let result = run(
+            &mut file,
+            8 * MIB,
+            options(4, MIB, false),
+            &mut Reporter::detached(),
+        )
+        .unwrap();
+        assert_eq!(result.sample_size, MIB);
+        assert_eq!(result.read.len(), 4);
+        assert!(result.read.iter().all(|rate| *rate > 0));
+        assert!(result.write.is_empty());
+        assert_eq!(result.access.len(), ACCESS_SAMPLES as usize);
+        assert!(result.time > 0);
+    }
+
+    #[test]
+    fn write_keeps_data() {
+        let (temp, mut file) = TempFile::new("write", 4 * MIB);
+        let before = std::fs::read(&temp.0).unwrap();
+        let result = run(
+            &mut file,
+            4 * MIB,
+            options(3, MIB, true),
+            &mut Reporter::detached(),
+        )
+        .unwrap();
+        assert_eq!(result.read.len(), 3);
+        assert_eq!(result.write.len(), 3);
+        assert_eq!(std::fs::read(&temp.0).unwrap(), before);
+    }
+
+    #[test]
+    fn small_devices() {
+        // Samples shrink to the device, down to whole blocks
+        let (_temp, mut file) = TempFile::new("small", 64 * 1024 + 100);
+        let result = run(
+            &mut file,
+            64 * 1024 + 100,
+            options(1, MIB, false),
+            &mut Reporter::detached(),
+        )
+        .unwrap();
+        assert_eq!(result.sample_size, 64 * 1024);
+        assert_eq!(result.read.len(), 1);
+
+        let (_temp, mut file) = TempFile::new("tiny", 1000);
+        assert!(run(
+            &mut file,
+            1000,
+            options(1, MIB, false),
+            &mut Reporter::detached()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn history_lines() {
+        let benchmark = Benchmark {
+            time: 1_700_000_000,
+            sample_size: MIB,
+            read: vec![100, 200],
+            write: Vec::new(),
+            access: vec![5, 7, 9],
+        };
+        let parsed = Benchmark::from_line(benchmark.to_line().trim_end()).unwrap();
+        assert_eq!(parsed.time, benchmark.time);
+        assert_eq!(parsed.read, benchmark.read);
+        assert!(parsed.write.is_empty());
+        assert_eq!(parsed.access_average(), Some(7));
+        assert_eq!(parsed.write_average(), None);
+    }
+
+    #[test]
+    fn unsaved_runs_survive_reloads() {
+        let run = |time| Benchmark {
+            time,
+            read: vec![100],
+            ..Default::default()
+        };
+        let mut previous = History::default();
+        for time in [10, 20, 30] {
+            previous.push(run(time));
+        }
+        // Only the first run made it to disk
+        let mut loaded = History::default();
+        loaded.push(run(10));
+        loaded.keep_unsaved(&previous);
+        let times: Vec<_> = loaded.runs.iter().map(|run| run.time).collect();
+        assert_eq!(times, [10, 20, 30]);
+
+        let mut loaded = History::default();
+        loaded.keep_unsaved(&History::default());
+        assert!(loaded.runs.is_empty());
+    }
+}
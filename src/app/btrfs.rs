@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+
+use cosmic::{prelude::*, theme, widget};
+
+use udisks2::{
+    filesystem::FilesystemProxy,
+    zbus::zvariant::{ObjectPath, OwnedObjectPath, Value},
+    Client,
+};
+
+use super::device::{self, Device};
+use super::{error::Error, message::AppMessage};
+
+#[zbus::proxy(
+    interface = "org.freedesktop.UDisks2.Filesystem.BTRFS",
+    default_service = "org.freedesktop.UDisks2"
+)]
+pub trait FilesystemBtrfs {
+    fn create_subvolume(&self, name: &str, options: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+
+    fn remove_subvolume(&self, name: &str, options: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+
+    fn get_subvolumes(
+        &self,
+        snapshots_only: bool,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<(Vec<(u64, u64, String)>, i32)>;
+
+    fn create_snapshot(
+        &self,
+        source: &str,
+        dest: &str,
+        ro: bool,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<()>;
+
+    fn add_device(
+        &self,
+        device: &ObjectPath<'_>,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<()>;
+
+    fn remove_device(
+        &self,
+        device: &ObjectPath<'_>,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<()>;
+}
+
+/// Minimum number of devices each block group profile can live on.
+fn profile_min_devices(profile: &str) -> Option<usize> {
+    match profile {
+        "single" | "dup" | "raid0" => Some(1),
+        "raid1" | "raid10" | "raid5" => Some(2),
+        "raid1c3" | "raid6" => Some(3),
+        "raid1c4" => Some(4),
+        _ => None,
+    }
+}
+
+/// Reads the block group profiles in use from sysfs, only available while mounted.
+fn read_profiles(uuid: &str, kind: &str) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(format!("/sys/fs/btrfs/{uuid}/allocation/{kind}")) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| profile_min_devices(name).is_some())
+        .collect()
+}
+
+/// The path inside the file system that is mounted at `mount_point`, `/` for the top level.
+///
+/// Read from the root field of `/proc/self/mountinfo`, where spaces and other special
+/// characters are escaped as octal.
+fn mount_root(mountinfo: &str, mount_point: &str) -> Option<String> {
+    let unescape = |field: &str| {
+        let mut bytes = Vec::new();
+        let mut rest = field.as_bytes();
+        while let Some((&byte, tail)) = rest.split_first() {
+            match (byte, tail.get(..3)) {
+                (b'\\', Some(octal)) if octal.iter().all(|digit| (b'0'..=b'7').contains(digit)) => {
+                    bytes.push(
+                        octal
+                            .iter()
+                            .fold(0u8, |value, digit| value * 8 + (digit - b'0')),
+                    );
+                    rest = &tail[3..];
+                }
+                _ => {
+                    bytes.push(byte);
+                    rest = tail;
+                }
+            }
+        }
+        String::from_utf8_lossy(&bytes).into_owned()
+    };
+    mountinfo
+        .lines()
+        .map(|line| line.split(' ').collect::<Vec<_>>())
+        .filter(|fields| fields.len() > 4 && unescape(fields[4]) == mount_point)
+        // The last mount on a path hides the earlier ones
+        .last()
+        .map(|fields| unescape(fields[3]))
+}
+
+/// A subvolume path relative to the file system root, as `GetSubvolumes` reports it, relative
+/// to the mounted subvolume instead. `None` when it lies outside of it.
+fn relative_to_mount(path: &str, mount_root: &str) -> Option<String> {
+    let root = mount_root.trim_matches('/');
+    if root.is_empty() {
+        return Some(path.to_string());
+    }
+    path.strip_prefix(root)?
+        .strip_prefix('/')
+        .filter(|relative| !relative.is_empty())
+        .map(str::to_string)
+}
+
+#[derive(Clone, Debug)]
+pub struct Subvolume {
+    pub id: u64,
+    /// Path from the top level of the file system
+    pub path: String,
+    /// Path from the mount point, which udisks expects, `None` when the subvolume can not be
+    /// reached through the mounted subvolume
+    pub mounted_path: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Btrfs {
+    pub proxy: FilesystemBtrfsProxy<'static>,
+    /// Subvolumes can only be listed and changed while the file system is mounted
+    pub mount_point: Option<String>,
+    pub subvolumes: Vec<Subvolume>,
+    pub snapshots: Vec<u64>,
+
+    /// Member devices across all drives, grouped by the file system UUID
+    pub members: Vec<Device>,
+    /// Unused block devices the file system can be extended onto
+    pub candidates: Vec<Device>,
+    pub data_profiles: Vec<String>,
+    pub metadata_profiles: Vec<String>,
+    pub system_profiles: Vec<String>,
+}
+
+impl Btrfs {
+    pub async fn load(
+        client: &Client,
+        path: OwnedObjectPath,
+        fs: &FilesystemProxy<'static>,
+        uuid: &str,
+    ) -> Result<Self, Error> {
+        let proxy = FilesystemBtrfsProxy::builder(client.manager().inner().connection())
+            .path(path)?
+            .build()
+            .await?;
+
+        let mount_point = fs.mount_points().await?.first().map(|mount_point| {
+            String::from_utf8_lossy(mount_point)
+                .trim_end_matches('\0')
+                .to_string()
+        });
+
+        let (subvolumes, snapshots) = match &mount_point {
+            Some(mount_point) => {
+                let root = std::fs::read_to_string("/proc/self/mountinfo")
+                    .ok()
+                    .and_then(|mountinfo| mount_root(&mountinfo, mount_point))
+                    .unwrap_or_else(|| "/".to_string());
+                let (subvolumes, _) = proxy
+                    .get_subvolumes(false, udisks2::standard_options(false))
+                    .await?;
+                let (snapshots, _) = proxy
+                    .get_subvolumes(true, udisks2::standard_options(false))
+                    .await?;
+                (
+                    subvolumes
+                        .into_iter()
+                        .map(|(id, _, path)| Subvolume {
+                            id,
+                            mounted_path: relative_to_mount(&path, &root),
+                            path,
+                        })
+                        .collect(),
+                    snapshots.into_iter().map(|(id, _, _)| id).collect(),
+                )
+            }
+            None => (Vec::new(), Vec::new()),
+        };
+
+        let mut members = Vec::new();
+        for block_path in client
+            .manager()
+            .get_block_devices(udisks2::standard_options(false))
+            .await?
+        {
+            let block = client.object(block_path.clone()).unwrap().block().await?;
+            if block.id_type().await? == "btrfs" && block.id_uuid().await? == uuid {
+                members.push(Device::load(client, block_path, &block).await?);
+            }
+        }
+        members.sort_by(|a, b| a.device.cmp(&b.device));
+
+        Ok(Self {
+            proxy,
+            mount_point,
+            subvolumes,
+            snapshots,
+            members,
+            candidates: device::unused(client).await?,
+            data_profiles: read_profiles(uuid, "data"),
+            metadata_profiles: read_profiles(uuid, "metadata"),
+            system_profiles: read_profiles(uuid, "system"),
+        })
+    }
+
+    /// Whether removing a member leaves fewer devices than any profile in use needs.
+    pub fn removal_degrades(&self) -> bool {
+        let required = self
+            .data_profiles
+            .iter()
+            .chain(self.metadata_profiles.iter())
+            .chain(self.system_profiles.iter())
+            .filter_map(|profile| profile_min_devices(profile))
+            .max()
+            .unwrap_or(1);
+        self.members.len().saturating_sub(1) < required
+    }
+
+    pub fn subvolume(&self, id: u64) -> Option<&Subvolume> {
+        self.subvolumes.iter().find(|subvolume| subvolume.id == id)
+    }
+
+    pub fn view(&self) -> Element<Result<AppMessage, Error>> {
+        let theme = theme::active();
+        let cosmic = theme.cosmic();
+
+        widget::column()
+            .spacing(cosmic.space_xs())
+            .push(self.devices_view())
+            .push(self.subvolumes_view())
+            .into()
+    }
+
+    fn devices_view(&self) -> Element<Result<AppMessage, Error>> {
+        let profiles = |profiles: &[String]| match profiles.is_empty() {
+            true => "Unknown".to_string(),
+            false => profiles.join(", ").to_uppercase(),
+        };
+        let section = widget::settings::section()
+            .title("Devices")
+            .add(widget::settings::item(
+                "Data Profile",
+                widget::text::body(profiles(&self.data_profiles)),
+            ))
+            .add(widget::settings::item(
+                "Metadata Profile",
+                widget::text::body(profiles(&self.metadata_profiles)),
+            ));
+        self.members
+            .iter()
+            .fold(section, |section, member| {
+                section.add(widget::settings::item(
+                    member.device.clone(),
+                    widget::text::body(&member.size),
+                ))
+            })
+            .into()
+    }
+
+    fn subvolumes_view(&self) -> Element<Result<AppMessage, Error>> {
+        let section = widget::settings::section().title("Subvolumes");
+        if self.mount_point.is_none() {
+            return section
+                .add(widget::text::body(
+                    "Mount the file system to manage subvolumes",
+                ))
+                .into();
+        }
+        if self.subvolumes.is_empty() {
+            return section
+                .add(widget::text::body("Only the top level subvolume exists"))
+                .into();
+        }
+        self.subvolumes
+            .iter()
+            .fold(section, |section, subvolume| {
+                let kind = match self.snapshots.contains(&subvolume.id) {
+                    true => "Snapshot, ",
+                    false => "",
+                };
+                let reachable = match subvolume.mounted_path {
+                    Some(_) => "",
+                    None => ", outside the mounted subvolume",
+                };
+                section.add(widget::settings::item(
+                    subvolume.path.clone(),
+                    widget::text::caption(format!("{kind}ID {}{reachable}", subvolume.id)),
+                ))
+            })
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOUNTINFO: &str = "\
+22 1 0:21 / / rw,relatime shared:1 - btrfs /dev/sda2 rw,subvol=/
+36 22 0:31 /@home /home rw,relatime shared:2 - btrfs /dev/sdb1 rw,subvol=/@home
+40 22 0:33 /my\\040data /mnt/my\\040disk rw,relatime shared:3 - btrfs /dev/sdc1 rw
+41 40 0:34 /@new /mnt/my\\040disk rw,relatime shared:4 - btrfs /dev/sdd1 rw
+";
+
+    #[test]
+    fn mount_roots() {
+        assert_eq!(mount_root(MOUNTINFO, "/").as_deref(), Some("/"));
+        assert_eq!(mount_root(MOUNTINFO, "/home").as_deref(), Some("/@home"));
+        assert_eq!(
+            mount_root(MOUNTINFO, "/mnt/my disk").as_deref(),
+            Some("/@new")
+        );
+        assert_eq!(mount_root(MOUNTINFO, "/srv"), None);
+    }
+
+    #[test]
+    fn paths_relative_to_mount() {
+        assert_eq!(relative_to_mount("@home", "/").as_deref(), Some("@home"));
+        assert_eq!(
+            relative_to_mount("@home/.snapshots/1", "/@home").as_deref(),
+            Some(".snapshots/1")
+        );
+        assert_eq!(relative_to_mount("@home", "/@home"), None);
+        assert_eq!(relative_to_mount("@homework/a", "/@home"), None);
+        assert_eq!(relative_to_mount("@", "/@home"), None);
+    }
+}
@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use udisks2::{
+    block::BlockProxy,
+    drive::DriveProxy,
+    filesystem::FilesystemProxy,
+    zbus::zvariant::{OwnedObjectPath, Value},
+    Client,
+};
+
+use super::error::Error;
+
+/// A block device listed by path, e.g. as a member of a btrfs file system or RAID array.
+#[derive(Clone, Debug)]
+pub struct Device {
+    pub path: OwnedObjectPath,
+    pub device: String,
+    pub capacity: u64,
+    pub size: String,
+}
+
+impl Device {
+    pub async fn load(
+        client: &Client,
+        path: OwnedObjectPath,
+        block: &BlockProxy<'_>,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            device: String::from_utf8_lossy(&block.preferred_device().await?)
+                .trim_end_matches('\0')
+                .to_string(),
+            capacity: block.size().await?,
+            size: client.size_for_display(block.size().await?, true, false),
+            path,
+        })
+    }
+}
+
+/// Block devices without any content that can be handed to a multi-device file system or array.
+pub async fn unused(client: &Client) -> Result<Vec<Device>, Error> {
+    let mut devices = Vec::new();
+    for block_path in client
+        .manager()
+        .get_block_devices(udisks2::standard_options(false))
+        .await?
+    {
+        let object = client.object(block_path.clone()).unwrap();
+        let block = object.block().await?;
+        if !block.id_usage().await?.is_empty()
+            || block.read_only().await?
+            || block.size().await? == 0
+            || object.partition_table().await.is_ok()
+            || object.r#loop().await.is_ok()
+        {
+            continue;
+        }
+        // Extended partitions only hold other partitions
+        if let Ok(partition) = object.partition().await {
+            if partition.is_container().await? {
+                continue;
+            }
+        }
+        devices.push(Device::load(client, block_path, &block).await?);
+    }
+    devices.sort_by(|a, b| a.device.cmp(&b.device));
+    Ok(devices)
+}
+
+/// Finds the block object of a device node, or of the drive holding it for partitions.
+pub async fn resolve(client: &Client, path: &Path) -> Result<OwnedObjectPath, Error> {
+    let mut devspec = HashMap::new();
+    devspec.insert("path", Value::from(path.to_string_lossy().to_string()));
+    let block_path = client
+        .manager()
+        .resolve_device(devspec, udisks2::standard_options(false))
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| {
+            Error::new(
+                format!("{} is not a known block device", path.display()),
+                true,
+            )
+        })?;
+    Ok(
+        match client.object(block_path.clone()).unwrap().partition().await {
+            Ok(partition) => partition.table().await?,
+            Err(_) => block_path,
+        },
+    )
+}
+
+/// A whole drive that can be overwritten as a unit, e.g. by an installer image.
+#[derive(Clone, Debug)]
+pub struct DriveTarget {
+    pub block: BlockProxy<'static>,
+    pub block_path: OwnedObjectPath,
+    pub device_number: u64,
+    pub drive: DriveProxy<'static>,
+    pub drive_path: OwnedObjectPath,
+    pub name: String,
+    pub capacity: u64,
+}
+
+/// Whole drives that may be overwritten, never the ones udisks marks as system devices.
+pub async fn drive_targets(
+    client: &Client,
+    removable_only: bool,
+) -> Result<Vec<DriveTarget>, Error> {
+    let mut targets = Vec::new();
+    for block_path in client
+        .manager()
+        .get_block_devices(udisks2::standard_options(false))
+        .await?
+    {
+        let object = client.object(block_path.clone()).unwrap();
+        let block = object.block().await?;
+        let drive_path = block.drive().await?;
+        if drive_path.as_str() == "/"
+            || object.partition().await.is_ok()
+            || block.hint_system().await?
+            || block.read_only().await?
+            || block.size().await? == 0
+        {
+            continue;
+        }
+        let drive = client.object(drive_path.clone()).unwrap().drive().await?;
+        if removable_only && !(drive.removable().await? || drive.media_removable().await?) {
+            continue;
+        }
+        let capacity = block.size().await?;
+        let device_number = block.device_number().await?;
+        let device = Device::load(client, block_path.clone(), &block).await?;
+        targets.push(DriveTarget {
+            name: format!(
+                "{} {} ({}, {})",
+                drive.vendor().await?,
+                drive.model().await?,
+                device.device,
+                device.size
+            )
+            .trim()
+            .to_string(),
+            block,
+            block_path,
+            device_number,
+            drive,
+            drive_path,
+            capacity,
+        });
+    }
+    targets.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(targets)
+}
+
+/// Partitions are placed on 1 MiB boundaries, like udisks aligns them.
+const PARTITION_ALIGNMENT: u64 = 1024 * 1024;
+
+/// Unallocated regions of a partitioned device as offset and size, aligned for new partitions.
+pub async fn free_space(
+    client: &Client,
+    block_path: &OwnedObjectPath,
+) -> Result<Vec<(u64, u64)>, Error> {
+    let object = client.object(block_path.clone()).unwrap();
+    let Ok(ptable) = object.partition_table().await else {
+        return Ok(Vec::new());
+    };
+    let size = object.block().await?.size().await?;
+    // The backup GPT takes up the end, a master boot record addresses no more than 2 TiB
+    let end = match ptable.type_().await?.as_str() {
+        "dos" => size.min(u32::MAX as u64 * 512),
+        _ => size.saturating_sub(PARTITION_ALIGNMENT),
+    };
+    let mut used = Vec::new();
+    for path in ptable.partitions().await? {
+        let partition = client.object(path).unwrap().partition().await?;
+        used.push((partition.offset().await?, partition.size().await?));
+    }
+    used.sort();
+    // Sentinel for the space after the last partition
+    used.push((end, 0));
+
+    let mut free = Vec::new();
+    let mut start = PARTITION_ALIGNMENT;
+    for (offset, size) in used {
+        let gap_end = offset.min(end);
+        if gap_end > start {
+            free.push((start, gap_end - start));
+        }
+        start = start.max((offset + size).next_multiple_of(PARTITION_ALIGNMENT));
+    }
+    Ok(free)
+}
+
+/// File systems on any block device of a drive, including its partitions.
+pub async fn drive_filesystems(
+    client: &Client,
+    drive_path: &OwnedObjectPath,
+) -> Result<Vec<FilesystemProxy<'static>>, Error> {
+    let mut filesystems = Vec::new();
+    for block_path in client
+        .manager()
+        .get_block_devices(udisks2::standard_options(false))
+        .await?
+    {
+        let object = client.object(block_path).unwrap();
+        if object.block().await?.drive().await? != *drive_path {
+            continue;
+        }
+        if let Ok(fs) = object.filesystem().await {
+            filesystems.push(fs);
+        }
+    }
+    Ok(filesystems)
+}
+
+/// Unmounts a file system, naming the mount point if it is still in use.
+pub async fn unmount(fs: &FilesystemProxy<'_>) -> Result<(), Error> {
+    let Some(mount_point) = fs.mount_points().await?.into_iter().next() else {
+        return Ok(());
+    };
+    if let Err(err) = fs.unmount(udisks2::standard_options(false)).await {
+        return Err(Error::new(
+            format!(
+                "{} could not be unmounted, close all files and programs using it and try again: {err}",
+                String::from_utf8_lossy(&mount_point).trim_end_matches('\0')
+            ),
+            true,
+        ));
+    }
+    Ok(())
+}
+
+/// Unmounts every file system of a drive and locks its encrypted devices, so it can be removed.
+pub async fn release_drive(client: &Client, drive_path: &OwnedObjectPath) -> Result<(), Error> {
+    for block_path in client
+        .manager()
+        .get_block_devices(udisks2::standard_options(false))
+        .await?
+    {
+        let object = client.object(block_path).unwrap();
+        if object.block().await?.drive().await? != *drive_path {
+            continue;
+        }
+        if let Ok(fs) = object.filesystem().await {
+            unmount(&fs).await?;
+        }
+        // Unlocked devices have no drive of their own, their file systems go first
+        if let Ok(encrypted) = object.encrypted().await {
+            let cleartext = encrypted.cleartext_device().await?;
+            if cleartext.as_str() == "/" {
+                continue;
+            }
+            if let Ok(fs) = client.object(cleartext).unwrap().filesystem().await {
+                unmount(&fs).await?;
+            }
+            encrypted.lock(udisks2::standard_options(false)).await?;
+        }
+    }
+    Ok(())
+}
@@ -1,28 +1,70 @@
 use std::collections::HashMap;
+use std::fs::File;
+use std::path::PathBuf;
 
 use cosmic::prelude::*;
 use cosmic::widget::nav_bar::Id;
 use cosmic::{iced, theme, widget};
 
 use udisks2::{
-    block::BlockProxy, drive::DriveProxy, filesystem::FilesystemProxy, partition::PartitionProxy,
-    partitiontable::PartitionTableProxy, zbus::zvariant::OwnedObjectPath, Client,
+    ata::AtaProxy, block::BlockProxy, drive::DriveProxy, filesystem::FilesystemProxy,
+    partition::PartitionProxy, partitiontable::PartitionTableProxy, r#loop::LoopProxy,
+    zbus::zvariant::OwnedObjectPath, Client,
 };
 
-use super::operation::Operation;
+use super::benchmark::{self, History};
+use super::btrfs::Btrfs;
+use super::erase::EraseSupport;
+use super::filesystem::{Availability, FsTools};
+use super::image_table;
+use super::nvme::Nvme;
+use super::operation::{clone::CloneSource, Operation};
+use super::power::{power_state_for_display, PowerSettings};
+use super::raid::level_for_display;
+use super::smart::{Assessment, SelftestKind, Smart};
+use super::surface::SurfaceCheck;
 use super::{error::Error, message::AppMessage};
-use crate::widget::Ring;
+use crate::widget::{
+    ring::{section_color, RingSection},
+    Ring,
+};
 
 #[derive(Clone, Debug)]
 pub struct Drive {
-    pub block: BlockProxy<'static>,
-    pub block_path: OwnedObjectPath,
-    pub _drive: DriveProxy<'static>,
+    /// Image files edited offline have no block device
+    pub block: Option<BlockProxy<'static>>,
+    pub block_path: Option<OwnedObjectPath>,
+    pub image_file: Option<PathBuf>,
+    /// Attached images have no drive behind them
+    pub drive: Option<DriveProxy<'static>>,
+    pub ata: Option<AtaProxy<'static>>,
+    pub power: PowerSettings,
+    /// Result of `Drive.Ata.PmGetState`, for drives supporting power management
+    pub power_state: Option<u8>,
+    pub ejectable: bool,
+    pub can_power_off: bool,
+    /// Removable and not marked as a system device, so ISO images may be written to it
+    pub removable: bool,
     pub ptable: Option<PartitionTableProxy<'static>>,
+    pub r#loop: Option<LoopProxy<'static>>,
+    pub backing_file: Option<String>,
+    pub autoclear: bool,
+    /// SMART data, only for ATA drives that support it
+    pub health: Option<Smart>,
+    /// Controller and health information of NVMe drives
+    pub nvme: Option<Nvme>,
+    pub erase: EraseSupport,
+    /// No file system is mounted and the device is not read-only, so it may be written directly
+    pub writable: bool,
+    pub benchmarks: History,
+    /// Latest surface check, kept like the benchmarks
+    pub surface: Option<SurfaceCheck>,
 
     pub ring: Ring,
 
     pub model: String,
+    pub device_number: u64,
+    pub capacity: u64,
     pub size: String,
     pub serial: String,
     pub revision: String,
@@ -33,9 +75,27 @@ pub struct Drive {
 
 #[derive(Eq, PartialEq, Clone, Copy)]
 enum DriveAction {
-    Format,
+    Format(EraseSupport),
+    CreateRaid,
     MakeImg,
-    RestoreImg,
+    RestoreImg(u64),
+    WriteIso,
+    Clone(u64, u64),
+    Autoclear(bool),
+    Detach,
+    Close,
+    Selftest(SelftestKind),
+    AbortSelftest,
+    LoadSmartData,
+    FormatNamespace(u64),
+    Sanitize,
+    Settings(PowerSettings),
+    Standby,
+    Wakeup,
+    Eject,
+    PowerOff,
+    Benchmark(bool),
+    CheckSurface,
 }
 
 impl widget::menu::Action for DriveAction {
@@ -43,11 +103,143 @@ impl widget::menu::Action for DriveAction {
 
     fn message(&self) -> Self::Message {
         match self {
-            Self::Format => Ok(AppMessage::OpenOperationDialog(Operation::DriveFormat)),
-            Self::MakeImg => Ok(AppMessage::NoOp),
-            Self::RestoreImg => Ok(AppMessage::NoOp),
+            Self::Format(erase) => Ok(AppMessage::OpenOperationDialog(Operation::DriveFormat(
+                *erase,
+            ))),
+            Self::CreateRaid => Ok(AppMessage::OpenOperationDialog(Operation::RaidCreate)),
+            Self::MakeImg => Ok(AppMessage::OpenOperationDialog(Operation::ImageCreate(
+                None,
+            ))),
+            Self::RestoreImg(capacity) => Ok(AppMessage::OpenOperationDialog(
+                Operation::ImageRestore(None, *capacity),
+            )),
+            Self::WriteIso => Ok(AppMessage::OpenOperationDialog(Operation::IsoWrite)),
+            Self::Clone(device_number, capacity) => Ok(AppMessage::OpenOperationDialog(
+                Operation::Clone(CloneSource::Drive(*device_number), *capacity),
+            )),
+            Self::Autoclear(autoclear) => Ok(AppMessage::LoopSetAutoclear(*autoclear)),
+            Self::Detach => Ok(AppMessage::OpenOperationDialog(Operation::ImageDetach)),
+            Self::Close => Ok(AppMessage::CloseImageFile),
+            Self::Selftest(kind) => Ok(AppMessage::SmartSelftestStart(*kind)),
+            Self::AbortSelftest => Ok(AppMessage::SmartSelftestAbort),
+            Self::LoadSmartData => Ok(AppMessage::SmartChooseBlob),
+            Self::FormatNamespace(device_number) => Ok(AppMessage::OpenOperationDialog(
+                Operation::NamespaceFormat(*device_number),
+            )),
+            Self::Sanitize => Ok(AppMessage::OpenOperationDialog(Operation::Sanitize)),
+            Self::Settings(settings) => Ok(AppMessage::OpenOperationDialog(
+                Operation::DriveSettings(*settings),
+            )),
+            Self::Standby => Ok(AppMessage::DriveStandby),
+            Self::Wakeup => Ok(AppMessage::DriveWakeup),
+            Self::Eject => Ok(AppMessage::DriveEject(None)),
+            Self::PowerOff => Ok(AppMessage::DrivePowerOff(None)),
+            Self::Benchmark(writable) => Ok(AppMessage::OpenOperationDialog(Operation::Benchmark(
+                *writable,
+            ))),
+            Self::CheckSurface => Ok(AppMessage::OpenOperationDialog(Operation::SurfaceCheck)),
+        }
+    }
+}
+
+fn partitioning_for_display(table_type: Option<&str>) -> String {
+    match table_type {
+        Some("gpt") => "GUID Partition Table",
+        Some("dos") => "Master Boot Record",
+        Some(_) => "Unknown",
+        None => "Empty",
+    }
+    .to_string()
+}
+
+fn partition_type_for_display(client: &Client, table_type: &str, type_id: &str) -> String {
+    client
+        .partition_type_for_display(table_type, type_id)
+        .unwrap_or("part-type\u{004}None".to_string())
+        .split('\u{004}')
+        .last()
+        .unwrap_or(type_id)
+        .to_string()
+}
+
+fn ring(partitions: &[Block]) -> Ring {
+    Ring {
+        sections: partitions
+            .iter()
+            .enumerate()
+            .map(|(index, block)| match &block.partition {
+                Some(_) => RingSection {
+                    color: section_color(index),
+                    size: block.size as usize,
+                    index,
+                },
+                None => RingSection::free(block.size as usize),
+            })
+            .collect(),
+        line_width: 12.0,
+        selected_par: None,
+    }
+}
+
+/// Sorts the partitions of a table and fills the gaps between them with empty space.
+fn with_free_space(client: &Client, mut partitions: Vec<Block>, size: u64) -> Vec<Block> {
+    partitions.sort_by(|a, b| a.offset.cmp(&b.offset));
+    let mut partitions_result = Vec::new();
+    if partitions.len() > 0 {
+        // Leading empty space
+        let start = 0;
+        let end = partitions[0].offset;
+        // TODO: Block size checking for drives
+        if end - start > 512 {
+            partitions_result.push(Block {
+                size: end - start,
+                offset: start,
+                size_for_display: client.size_for_display(end - start, true, false),
+                offset_for_display: client.size_for_display(start, true, false),
+                partition: None,
+            })
         }
+        // Partition pairs
+        for (par_a, par_b) in partitions.iter().zip(partitions.iter().skip(1)) {
+            let start = par_a.offset + par_a.size;
+            let end = par_b.offset;
+
+            partitions_result.push(par_a.clone());
+            if end - start > 512 {
+                partitions_result.push(Block {
+                    size: end - start,
+                    offset: start,
+                    size_for_display: client.size_for_display(end - start, true, false),
+                    offset_for_display: client.size_for_display(start, true, false),
+                    partition: None,
+                })
+            }
+        }
+        // Push last partition
+        let last_par = partitions.last().unwrap();
+        partitions_result.push(last_par.clone());
+        // Trailing empty space
+        let start = last_par.offset + last_par.size;
+        let end = size;
+        if end - start > 512 {
+            partitions_result.push(Block {
+                size: end - start,
+                offset: start,
+                size_for_display: client.size_for_display(end - start, true, false),
+                offset_for_display: client.size_for_display(start, true, false),
+                partition: None,
+            })
+        }
+    } else {
+        partitions_result.push(Block {
+            size,
+            offset: 0,
+            size_for_display: client.size_for_display(size, true, false),
+            offset_for_display: client.size_for_display(0, true, false),
+            partition: None,
+        });
     }
+    partitions_result
 }
 
 impl Drive {
@@ -60,8 +252,75 @@ impl Drive {
         let block = block_device.block().await?;
 
         let drive_path = block.drive().await?;
-        let drive_device = client.object(drive_path).unwrap();
-        let drive = drive_device.drive().await?;
+        let (drive, health, nvme) = match drive_path.as_str() {
+            "/" => (None, None, None),
+            _ => {
+                let drive = client.object(drive_path.clone()).unwrap().drive().await?;
+                // Health data is optional, a drive that fails to report it is still shown
+                let nvme = Nvme::load(
+                    &client,
+                    drive_path.clone(),
+                    drive.revision().await.unwrap_or_default(),
+                )
+                .await
+                .unwrap_or(None);
+                (
+                    Some(drive),
+                    Smart::load(&client, drive_path.clone())
+                        .await
+                        .unwrap_or(None),
+                    nvme,
+                )
+            }
+        };
+        let ata = match &drive {
+            Some(_) => client
+                .object(drive_path.clone())
+                .unwrap()
+                .drive_ata()
+                .await
+                .ok(),
+            None => None,
+        };
+        let (power, power_state) = match (&drive, &ata) {
+            (Some(drive), Some(ata)) => {
+                let power = PowerSettings::load(drive, ata).await.unwrap_or_default();
+                let power_state = match power.standby_supported {
+                    true => ata
+                        .pm_get_state(udisks2::standard_options(false))
+                        .await
+                        .ok(),
+                    false => None,
+                };
+                (power, power_state)
+            }
+            _ => (PowerSettings::default(), None),
+        };
+        let erase = EraseSupport::load(
+            &client,
+            drive.as_ref().map(|_| drive_path),
+            block.device_number().await?,
+            nvme.is_some(),
+        )
+        .await
+        .unwrap_or_default();
+        // Writing is only offered when it is known that nothing is mounted
+        let writable = !block.read_only().await?
+            && !benchmark::is_mounted(&client, &block_path)
+                .await
+                .unwrap_or(true);
+        let r#loop = block_device.r#loop().await.ok();
+        let (backing_file, autoclear) = match &r#loop {
+            Some(r#loop) => (
+                Some(
+                    String::from_utf8_lossy(&r#loop.backing_file().await?)
+                        .trim_end_matches('\0')
+                        .to_string(),
+                ),
+                r#loop.autoclear().await?,
+            ),
+            None => (None, false),
+        };
 
         let ptable = block_device.partition_table().await;
 
@@ -83,6 +342,48 @@ impl Drive {
                     .unwrap()
                     .filesystem()
                     .await;
+                let (btrfs, btrfs_error) = match &fs {
+                    Ok(fs) if block.id_type().await? == "btrfs" => {
+                        match Btrfs::load(
+                            &client,
+                            partition_path.clone(),
+                            fs,
+                            &block.id_uuid().await?,
+                        )
+                        .await
+                        {
+                            Ok(btrfs) => (Some(btrfs), None),
+                            Err(err) => (None, Some(err.description)),
+                        }
+                    }
+                    _ => (None, None),
+                };
+                let tools = match &fs {
+                    Ok(_) => FsTools::load(&client, &block.id_type().await?).await.ok(),
+                    Err(_) => None,
+                };
+                let raid_member = block.mdraid_member().await?;
+                let partition_id = match raid_member.as_str() {
+                    "/" => client
+                        .id_for_display(
+                            block.id_usage().await?.as_str(),
+                            block.id_type().await?.as_str(),
+                            block.id_version().await?.as_str(),
+                            false,
+                        )
+                        .split('\u{004}')
+                        .last()
+                        .unwrap_or(block.id_type().await?.as_str())
+                        .to_string(),
+                    _ => {
+                        let raid = client.object(raid_member.clone()).unwrap().mdraid().await?;
+                        format!(
+                            "{} Member ({})",
+                            level_for_display(&raid.level().await?),
+                            raid.name().await?
+                        )
+                    }
+                };
                 let partition = Partition {
                     name: std::path::Path::new(&partition_path.to_string())
                         .file_name()
@@ -93,33 +394,21 @@ impl Drive {
                     block_size: client.size_for_display(block.size().await?, true, false),
                     size: client.size_for_display(part.size().await?, true, false),
                     offset: client.size_for_display(part.offset().await?, true, false),
-                    r#type: client
-                        .partition_type_for_display(
-                            ptable.type_().await?.as_str(),
-                            part.type_().await?.as_str(),
-                        )
-                        .unwrap_or("part-type\u{004}None".to_string())
-                        .split('\u{004}')
-                        .last()
-                        .unwrap_or(part.type_().await?.as_str())
-                        .to_string(),
+                    r#type: partition_type_for_display(
+                        &client,
+                        &ptable.type_().await?,
+                        &part.type_().await?,
+                    ),
                     uuid: block.id_uuid().await?,
 
-                    partition_id: client
-                        .id_for_display(
-                            block.id_usage().await?.as_str(),
-                            block.id_type().await?.as_str(),
-                            block.id_version().await?.as_str(),
-                            false,
-                        )
-                        .split('\u{004}')
-                        .last()
-                        .unwrap_or(block.id_type().await?.as_str())
-                        .to_string(),
+                    partition_id,
 
-                    block,
-                    _part: part.clone(),
+                    block: Some(block),
+                    part: Some(part.clone()),
                     fs: fs.ok(),
+                    tools,
+                    btrfs,
+                    btrfs_error,
                 };
 
                 let block = Block {
@@ -133,123 +422,462 @@ impl Drive {
                 partitions.push(block);
             }
 
-            partitions.sort_by(|a, b| a.offset.cmp(&b.offset));
-            let mut partitions_result = Vec::new();
-            if partitions.len() > 0 {
-                // Leading empty space
-                let start = 0;
-                let end = partitions[0].offset;
-                // TODO: Block size checking for drives
-                if end - start > 512 {
-                    partitions_result.push(Block {
-                        size: end - start,
-                        offset: start,
-                        size_for_display: client.size_for_display(end - start, true, false),
-                        offset_for_display: client.size_for_display(start, true, false),
-                        partition: None,
-                    })
-                }
-                // Partition pairs
-                for (par_a, par_b) in partitions.iter().zip(partitions.iter().skip(1)) {
-                    let start = par_a.offset + par_a.size;
-                    let end = par_b.offset;
-
-                    partitions_result.push(par_a.clone());
-                    if end - start > 512 {
-                        partitions_result.push(Block {
-                            size: end - start,
-                            offset: start,
-                            size_for_display: client.size_for_display(end - start, true, false),
-                            offset_for_display: client.size_for_display(start, true, false),
-                            partition: None,
-                        })
-                    }
-                }
-                // Push last partition
-                let last_par = partitions.last().unwrap();
-                partitions_result.push(last_par.clone());
-                // Trailing empty space
-                let start = last_par.offset + last_par.size;
-                let end = block.size().await?;
-                if end - start > 512 {
-                    partitions_result.push(Block {
-                        size: end - start,
-                        offset: start,
-                        size_for_display: client.size_for_display(end - start, true, false),
-                        offset_for_display: client.size_for_display(start, true, false),
-                        partition: None,
-                    })
+            partitions = with_free_space(&client, partitions, block.size().await?);
+        }
+
+        let removable = match &drive {
+            Some(drive) => {
+                (drive.removable().await? || drive.media_removable().await?)
+                    && !block.hint_system().await?
+            }
+            None => false,
+        };
+        let mut loaded = Drive {
+            model: match (&drive, &backing_file) {
+                (Some(drive), _) => drive.model().await?,
+                (None, Some(backing_file)) => std::path::Path::new(backing_file)
+                    .file_name()
+                    .map_or(backing_file.clone(), |name| {
+                        name.to_string_lossy().to_string()
+                    }),
+                (None, None) => String::from_utf8_lossy(&block.preferred_device().await?)
+                    .trim_end_matches('\0')
+                    .to_string(),
+            },
+            device_number: block.device_number().await?,
+            capacity: block.size().await?,
+            size: client.size_for_display(block.size().await?, true, false),
+            serial: match &drive {
+                Some(drive) => drive.serial().await?,
+                None => String::new(),
+            },
+            revision: match &drive {
+                Some(drive) => drive.revision().await?,
+                None => String::new(),
+            },
+            partitioning: partitioning_for_display(
+                match &ptable {
+                    Ok(ptable) => Some(ptable.type_().await?),
+                    Err(_) => None,
                 }
-            } else {
-                partitions_result.push(Block {
-                    size: block.size().await?,
-                    offset: 0,
-                    size_for_display: client.size_for_display(block.size().await?, true, false),
-                    offset_for_display: client.size_for_display(0, true, false),
-                    partition: None,
+                .as_deref(),
+            ),
+            ring: ring(&partitions),
+
+            partitions,
+
+            block: Some(block),
+            block_path: Some(block_path),
+            image_file: None,
+            ejectable: match &drive {
+                Some(drive) => drive.ejectable().await?,
+                None => false,
+            },
+            can_power_off: match &drive {
+                Some(drive) => drive.can_power_off().await?,
+                None => false,
+            },
+            removable,
+            drive,
+            ata,
+            power,
+            power_state,
+            ptable: ptable.ok(),
+            r#loop,
+            backing_file,
+            autoclear,
+            health,
+            nvme,
+            erase,
+            writable,
+            benchmarks: History::default(),
+            surface: None,
+        };
+        let key = loaded.results_key();
+        loaded.benchmarks = History::load(&key);
+        loaded.surface = SurfaceCheck::load(&key);
+        Ok(AppMessage::DriveRead(id, loaded))
+    }
+
+    /// Reads the partition table of an image file directly, without attaching it.
+    pub async fn load_image_file(
+        client: Client,
+        id: Id,
+        path: PathBuf,
+    ) -> Result<AppMessage, Error> {
+        let mut file = File::open(&path)?;
+        let capacity = file.metadata()?.len();
+        let table = image_table::read(&mut file)?;
+
+        let mut partitions = Vec::new();
+        if let Some(table) = &table {
+            let table_type = table.kind.udisks_type();
+            for entry in &table.entries {
+                partitions.push(Block {
+                    size: entry.size,
+                    offset: entry.offset,
+                    size_for_display: client.size_for_display(entry.size, true, false),
+                    offset_for_display: client.size_for_display(entry.offset, true, false),
+                    partition: Some(Partition {
+                        block: None,
+                        part: None,
+                        fs: None,
+                        btrfs: None,
+                        btrfs_error: None,
+                        tools: None,
+                        name: match entry.name.is_empty() {
+                            true => format!("Partition {}", entry.number),
+                            false => entry.name.clone(),
+                        },
+                        // File systems are only probed once the image is attached
+                        partition_id: "Unknown".to_string(),
+                        size: client.size_for_display(entry.size, true, false),
+                        offset: client.size_for_display(entry.offset, true, false),
+                        r#type: partition_type_for_display(&client, table_type, &entry.type_id),
+                        block_size: client.size_for_display(entry.size, true, false),
+                        uuid: entry.uuid.clone(),
+                    }),
                 });
             }
-            partitions = partitions_result;
+            partitions = with_free_space(&client, partitions, capacity);
         }
 
-        Ok(AppMessage::DriveRead(
-            id,
-            Drive {
-                model: drive.model().await?,
-                size: client.size_for_display(block.size().await?, true, false),
-                serial: drive.serial().await?,
-                revision: drive.revision().await?,
-                partitioning: match &ptable {
-                    Ok(ptable) => match ptable.type_().await?.as_str() {
-                        "gpt" => "GUID Partition Table",
-                        "mbr" => "Master Boot Record",
-                        _ => "Unknown",
-                    }
-                    .to_string(),
-                    Err(_) => "Empty".to_string(),
-                },
-                ring: Ring {
-                    sections: Vec::new(),
-                    line_width: 12.0,
-                    selected_par: None,
-                },
+        let mut loaded = Drive {
+            model: path
+                .file_name()
+                .map_or(path.to_string_lossy(), |name| name.to_string_lossy())
+                .to_string(),
+            device_number: 0,
+            capacity,
+            size: client.size_for_display(capacity, true, false),
+            serial: String::new(),
+            revision: String::new(),
+            partitioning: partitioning_for_display(
+                table.as_ref().map(|table| table.kind.udisks_type()),
+            ),
+            ring: ring(&partitions),
 
-                partitions,
+            partitions,
 
-                block,
-                block_path,
-                _drive: drive,
-                ptable: ptable.ok(),
-            },
-        ))
+            block: None,
+            block_path: None,
+            image_file: Some(path),
+            drive: None,
+            ata: None,
+            power: PowerSettings::default(),
+            power_state: None,
+            ejectable: false,
+            can_power_off: false,
+            removable: false,
+            ptable: None,
+            r#loop: None,
+            backing_file: None,
+            autoclear: false,
+            health: None,
+            nvme: None,
+            erase: EraseSupport::default(),
+            writable: true,
+            benchmarks: History::default(),
+            surface: None,
+        };
+        let key = loaded.results_key();
+        loaded.benchmarks = History::load(&key);
+        loaded.surface = SurfaceCheck::load(&key);
+        Ok(AppMessage::DriveRead(id, loaded))
+    }
+
+    /// Takes over results of the previous load that could not be saved, so a reload keeps them.
+    pub fn keep_unsaved(&mut self, previous: &Drive) {
+        self.benchmarks.keep_unsaved(&previous.benchmarks);
+        let newer = |check: &SurfaceCheck| {
+            self.surface
+                .as_ref()
+                .map_or(true, |surface| check.time > surface.time)
+        };
+        if let Some(check) = previous.surface.as_ref().filter(|check| newer(check)) {
+            self.surface = Some(check.clone());
+        }
+    }
+
+    /// Benchmark and surface check results are kept by serial and size, so they follow the drive
+    /// from port to port, and by canonical path for images. Drives with neither have no results.
+    pub fn results_key(&self) -> String {
+        let image = self.image_file.clone().or_else(|| {
+            self.backing_file
+                .as_ref()
+                .filter(|backing_file| !backing_file.is_empty())
+                .map(PathBuf::from)
+        });
+        match image {
+            Some(path) => std::fs::canonicalize(&path)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .into_owned(),
+            None if self.serial.is_empty() => String::new(),
+            None => format!("{}-{}", self.serial, self.capacity),
+        }
+    }
+
+    pub fn drive_path(&self) -> Option<OwnedObjectPath> {
+        self.drive
+            .as_ref()
+            .map(|drive| drive.inner().path().to_owned().into())
+    }
+
+    /// Overall health of drives that report SMART data, ATA or NVMe.
+    pub fn assessment(&self) -> Option<Assessment> {
+        match (&self.health, &self.nvme) {
+            (Some(health), _) => Some(health.assessment()),
+            (None, Some(nvme)) => Some(nvme.assessment()),
+            (None, None) => None,
+        }
+    }
+
+    pub fn selftest_running(&self) -> bool {
+        self.health
+            .as_ref()
+            .is_some_and(|health| health.selftest_running())
+            || self
+                .nvme
+                .as_ref()
+                .is_some_and(|nvme| nvme.selftest_running())
+    }
+
+    /// Whether the drive runs an operation on its own that the view should follow.
+    pub fn in_progress(&self) -> bool {
+        self.selftest_running() || self.nvme.as_ref().is_some_and(|nvme| nvme.in_progress())
+    }
+
+    /// Follows a running NVMe operation without loading the whole drive again, which only
+    /// happens once the operation is done.
+    pub async fn refresh_progress(mut self, id: Id) -> Result<AppMessage, Error> {
+        if let Some(nvme) = &mut self.nvme {
+            nvme.refresh_progress().await?;
+            if nvme.in_progress() {
+                return Ok(AppMessage::DriveRead(id, self));
+            }
+        }
+        match self.block_path {
+            Some(block_path) => Ok(AppMessage::LoadDrive(id, block_path)),
+            None => Ok(AppMessage::NoOp),
+        }
+    }
+
+    pub async fn start_selftest(&self, kind: SelftestKind) -> Result<(), Error> {
+        match (&self.health, &self.nvme) {
+            (Some(health), _) => health.start_selftest(kind).await,
+            (None, Some(nvme)) => nvme.start_selftest(kind).await,
+            (None, None) => Ok(()),
+        }
+    }
+
+    pub async fn abort_selftest(&self) -> Result<(), Error> {
+        match (&self.health, &self.nvme) {
+            (Some(health), _) => health.abort_selftest().await,
+            (None, Some(nvme)) => nvme.abort_selftest().await,
+            (None, None) => Ok(()),
+        }
     }
 
     pub fn menu_bar(&self) -> Element<Result<AppMessage, Error>> {
         use widget::menu;
-        menu::bar(vec![
-            menu::Tree::with_children(
-                menu::root("Drive"),
-                menu::items(
-                    &HashMap::new(),
-                    vec![
-                        menu::Item::Button("Format", None, DriveAction::Format),
-                        menu::Item::Divider,
-                        menu::Item::ButtonDisabled("Create Disk Image", None, DriveAction::MakeImg),
-                        menu::Item::ButtonDisabled(
-                            "Restore Disk Image",
-                            None,
-                            DriveAction::RestoreImg,
-                        ),
-                    ],
+        let offline = self.image_file.is_some();
+        if offline {
+            return menu::bar(vec![
+                menu::Tree::with_children(
+                    menu::root("Image"),
+                    menu::items(
+                        &HashMap::new(),
+                        vec![
+                            menu::Item::Button("Format", None, DriveAction::Format(self.erase)),
+                            menu::Item::Button(
+                                "Benchmark",
+                                None,
+                                DriveAction::Benchmark(self.writable),
+                            ),
+                            menu::Item::Button("Check Surface", None, DriveAction::CheckSurface),
+                            menu::Item::Divider,
+                            menu::Item::Button("Close", None, DriveAction::Close),
+                        ],
+                    ),
+                ),
+                menu::Tree::with_children(
+                    menu::root("Partitions"),
+                    menu::items(
+                        &HashMap::new(),
+                        self.partitions
+                            .iter()
+                            .map(|partition| partition.menu_folder(offline, self.erase))
+                            .collect(),
+                    ),
                 ),
+            ])
+            .apply(Element::from);
+        }
+
+        let mut items = vec![
+            menu::Item::Button("Format".to_string(), None, DriveAction::Format(self.erase)),
+            menu::Item::Button(
+                "Create RAID Array".to_string(),
+                None,
+                DriveAction::CreateRaid,
             ),
+            menu::Item::Divider,
+            menu::Item::Button("Create Disk Image".to_string(), None, DriveAction::MakeImg),
+            menu::Item::Button(
+                "Restore Disk Image".to_string(),
+                None,
+                DriveAction::RestoreImg(self.capacity),
+            ),
+        ];
+        // Writing an ISO is meant for USB sticks and SD cards, not internal drives
+        if self.removable {
+            items.push(menu::Item::Button(
+                "Write ISO to Drive".to_string(),
+                None,
+                DriveAction::WriteIso,
+            ));
+        }
+        items.extend([
+            menu::Item::Button(
+                "Clone Drive".to_string(),
+                None,
+                DriveAction::Clone(self.device_number, self.capacity),
+            ),
+            menu::Item::Divider,
+            menu::Item::Button(
+                "Benchmark".to_string(),
+                None,
+                DriveAction::Benchmark(self.writable),
+            ),
+            menu::Item::Button("Check Surface".to_string(), None, DriveAction::CheckSurface),
+        ]);
+        if self.assessment().is_some() {
+            let mut selftest = match self.selftest_running() {
+                true => vec![menu::Item::Button(
+                    "Abort Self-Test".to_string(),
+                    None,
+                    DriveAction::AbortSelftest,
+                )],
+                false => vec![
+                    menu::Item::Button(
+                        "Short Self-Test".to_string(),
+                        None,
+                        DriveAction::Selftest(SelftestKind::Short),
+                    ),
+                    menu::Item::Button(
+                        "Extended Self-Test".to_string(),
+                        None,
+                        DriveAction::Selftest(SelftestKind::Extended),
+                    ),
+                ],
+            };
+            // Conveyance tests and libatasmart blobs only exist for ATA drives
+            if self.health.is_some() {
+                if !self.selftest_running() {
+                    selftest.push(menu::Item::Button(
+                        "Conveyance Self-Test".to_string(),
+                        None,
+                        DriveAction::Selftest(SelftestKind::Conveyance),
+                    ));
+                }
+                selftest.push(menu::Item::Divider);
+                selftest.push(menu::Item::Button(
+                    "Load SMART Data from File...".to_string(),
+                    None,
+                    DriveAction::LoadSmartData,
+                ));
+            }
+            items.push(menu::Item::Divider);
+            items.push(menu::Item::Folder("SMART".to_string(), selftest));
+        }
+        if let Some(nvme) = &self.nvme {
+            let mut namespace_items: Vec<_> = nvme
+                .namespaces
+                .iter()
+                .map(|namespace| {
+                    menu::Item::Button(
+                        format!("Format Namespace {}", namespace.nsid),
+                        None,
+                        DriveAction::FormatNamespace(namespace.device_number),
+                    )
+                })
+                .collect();
+            namespace_items.push(menu::Item::Divider);
+            namespace_items.push(menu::Item::Button(
+                "Sanitize Drive".to_string(),
+                None,
+                DriveAction::Sanitize,
+            ));
+            items.push(menu::Item::Folder("NVMe".to_string(), namespace_items));
+        }
+        if self.power.any_supported() {
+            items.push(menu::Item::Divider);
+            items.push(menu::Item::Button(
+                "Drive Settings...".to_string(),
+                None,
+                DriveAction::Settings(self.power),
+            ));
+        }
+        if self.power.standby_supported {
+            items.push(menu::Item::Button(
+                "Standby Now".to_string(),
+                None,
+                DriveAction::Standby,
+            ));
+            items.push(menu::Item::Button(
+                "Wake Up".to_string(),
+                None,
+                DriveAction::Wakeup,
+            ));
+        }
+        if self.ejectable || self.can_power_off {
+            items.push(menu::Item::Divider);
+        }
+        if self.ejectable {
+            items.push(menu::Item::Button(
+                "Eject".to_string(),
+                None,
+                DriveAction::Eject,
+            ));
+        }
+        if self.can_power_off {
+            items.push(menu::Item::Button(
+                "Power Off".to_string(),
+                None,
+                DriveAction::PowerOff,
+            ));
+        }
+        if self.r#loop.is_some() {
+            items.push(menu::Item::Divider);
+            items.push(match self.autoclear {
+                true => menu::Item::Button(
+                    "Keep Attached When Unused".to_string(),
+                    None,
+                    DriveAction::Autoclear(false),
+                ),
+                false => menu::Item::Button(
+                    "Detach When Unused".to_string(),
+                    None,
+                    DriveAction::Autoclear(true),
+                ),
+            });
+            items.push(menu::Item::Button(
+                "Detach".to_string(),
+                None,
+                DriveAction::Detach,
+            ));
+        }
+
+        menu::bar(vec![
+            menu::Tree::with_children(menu::root("Drive"), menu::items(&HashMap::new(), items)),
             menu::Tree::with_children(
                 menu::root("Partitions"),
                 menu::items(
                     &HashMap::new(),
                     self.partitions
                         .iter()
-                        .map(|partition| partition.menu_folder())
+                        .map(|partition| partition.menu_folder(offline, self.erase))
                         .collect(),
                 ),
             ),
@@ -257,6 +885,49 @@ impl Drive {
         .apply(Element::from)
     }
 
+    fn info(&self) -> Element<Result<AppMessage, Error>> {
+        let mut section = widget::settings::section().add(widget::settings::item(
+            "Size",
+            widget::text::heading(&self.size),
+        ));
+        section = match (&self.backing_file, &self.image_file) {
+            (_, Some(image_file)) => section.add(widget::settings::item(
+                "Image File",
+                widget::text::body(image_file.to_string_lossy()),
+            )),
+            (Some(backing_file), _) => section
+                .add(widget::settings::item(
+                    "Backing File",
+                    widget::text::body(backing_file),
+                ))
+                .add(widget::settings::item(
+                    "Autoclear",
+                    widget::text::body(if self.autoclear { "On" } else { "Off" }),
+                )),
+            (None, None) => section
+                .add(widget::settings::item(
+                    "Serial",
+                    widget::text::body(&self.serial),
+                ))
+                .add(widget::settings::item(
+                    "Revision",
+                    widget::text::body(&self.revision),
+                )),
+        };
+        if let Some(state) = self.power_state {
+            section = section.add(widget::settings::item(
+                "Power State",
+                widget::text::body(power_state_for_display(state)),
+            ));
+        }
+        section
+            .add(widget::settings::item(
+                "Partition Table",
+                widget::text::body(&self.partitioning),
+            ))
+            .into()
+    }
+
     pub fn view(&self) -> Element<Result<AppMessage, Error>> {
         let theme = theme::active();
         let cosmic = theme.cosmic();
@@ -276,44 +947,29 @@ impl Drive {
                                 .width(iced::Length::Fill)
                                 .height(iced::Length::Fixed(250.0))
                                 .apply(Element::from),
-                            widget::container(
-                                widget::settings::section()
-                                    .add(widget::settings::item(
-                                        "Size",
-                                        widget::text::heading(&self.size),
-                                    ))
-                                    .add(widget::settings::item(
-                                        "Serial",
-                                        widget::text::body(&self.serial),
-                                    ))
-                                    .add(widget::settings::item(
-                                        "Revision",
-                                        widget::text::body(&self.revision),
-                                    ))
-                                    .add(widget::settings::item(
-                                        "Partition Table",
-                                        widget::text::body(&self.partitioning),
-                                    )),
-                            )
-                            .apply(Element::from),
+                            widget::container(self.info()).apply(Element::from),
                         ])
                         .align_items(iced::Alignment::Center),
                     ),
             )
-            .push(
-                widget::column()
-                    .push(widget::text::title3("Partitions"))
-                    .push(iced::widget::horizontal_rule(1)),
-            )
             .push(widget::scrollable(
-                widget::column::with_children(
-                    self.partitions
-                        .iter()
-                        .map(|partition| partition.view())
-                        .collect(),
-                )
-                .padding([0, cosmic.space_xs(), 0, 0])
-                .spacing(cosmic.space_m()),
+                widget::column()
+                    .push_maybe(self.health.as_ref().map(|health| health.view()))
+                    .push_maybe(self.nvme.as_ref().map(|nvme| nvme.view()))
+                    .push_maybe(self.benchmarks.view())
+                    .push_maybe(
+                        self.surface
+                            .as_ref()
+                            .map(|surface| surface.view(&self.partitions)),
+                    )
+                    .push(
+                        widget::column()
+                            .push(widget::text::title3("Partitions"))
+                            .push(iced::widget::horizontal_rule(1)),
+                    )
+                    .extend(self.partitions.iter().map(|partition| partition.view()))
+                    .padding([0, cosmic.space_xs(), 0, 0])
+                    .spacing(cosmic.space_m()),
             ))
             .into()
     }
@@ -322,7 +978,18 @@ impl Drive {
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub enum BlockAction {
     AddPartition(u64, u64),
-    FormatPartition(u64),
+    FormatPartition(u64, EraseSupport),
+    CreateSubvolume(u64),
+    SnapshotSubvolume(u64, u64),
+    DeleteSubvolume(u64, u64),
+    AddBtrfsDevice(u64, usize),
+    RemoveBtrfsDevice(u64, usize, bool),
+    MakeImage(u64),
+    RestoreImage(u64, u64),
+    ClonePartition(u64, u64),
+    DeletePartition(u64),
+    CheckFilesystem(u64),
+    RepairFilesystem(u64),
 }
 
 impl widget::menu::Action for BlockAction {
@@ -333,9 +1000,44 @@ impl widget::menu::Action for BlockAction {
             Self::AddPartition(offset, max_size) => Ok(AppMessage::OpenOperationDialog(
                 Operation::AddPartition(*offset, *max_size),
             )),
-            Self::FormatPartition(offset) => Ok(AppMessage::OpenOperationDialog(
-                Operation::PartitionFormat(*offset),
+            Self::FormatPartition(offset, erase) => Ok(AppMessage::OpenOperationDialog(
+                Operation::PartitionFormat(*offset, *erase),
+            )),
+            Self::CreateSubvolume(offset) => Ok(AppMessage::OpenOperationDialog(
+                Operation::BtrfsSubvolumeCreate(*offset),
+            )),
+            Self::SnapshotSubvolume(offset, id) => Ok(AppMessage::OpenOperationDialog(
+                Operation::BtrfsSnapshot(*offset, *id),
+            )),
+            Self::DeleteSubvolume(offset, id) => Ok(AppMessage::OpenOperationDialog(
+                Operation::BtrfsSubvolumeDelete(*offset, *id),
+            )),
+            Self::AddBtrfsDevice(offset, index) => Ok(AppMessage::OpenOperationDialog(
+                Operation::BtrfsAddDevice(*offset, *index),
+            )),
+            Self::MakeImage(offset) => Ok(AppMessage::OpenOperationDialog(Operation::ImageCreate(
+                Some(*offset),
+            ))),
+            Self::RestoreImage(offset, capacity) => Ok(AppMessage::OpenOperationDialog(
+                Operation::ImageRestore(Some(*offset), *capacity),
             )),
+            Self::ClonePartition(offset, size) => Ok(AppMessage::OpenOperationDialog(
+                Operation::Clone(CloneSource::Partition(*offset), *size),
+            )),
+            Self::DeletePartition(offset) => Ok(AppMessage::OpenOperationDialog(
+                Operation::PartitionDelete(*offset),
+            )),
+            Self::CheckFilesystem(offset) => Ok(AppMessage::OpenOperationDialog(
+                Operation::FilesystemCheck(*offset, false),
+            )),
+            Self::RepairFilesystem(offset) => Ok(AppMessage::OpenOperationDialog(
+                Operation::FilesystemCheck(*offset, true),
+            )),
+            Self::RemoveBtrfsDevice(offset, index, degrades) => {
+                Ok(AppMessage::OpenOperationDialog(
+                    Operation::BtrfsRemoveDevice(*offset, *index, *degrades),
+                ))
+            }
         }
     }
 }
@@ -349,18 +1051,154 @@ pub struct Block {
     pub partition: Option<Partition>,
 }
 
+/// Menu item for a file system tool, disabled and naming the missing program if unavailable.
+fn tool_item(
+    label: &str,
+    availability: &Availability,
+    action: BlockAction,
+) -> widget::menu::Item<BlockAction, String> {
+    match availability {
+        Availability::Available => widget::menu::Item::Button(label.to_string(), None, action),
+        Availability::Missing(tool) => {
+            widget::menu::Item::ButtonDisabled(format!("{label} (install {tool})"), None, action)
+        }
+    }
+}
+
 impl Block {
-    fn menu_folder(&self) -> widget::menu::Item<BlockAction, String> {
+    /// Menu of the block, limited to editing the table for image files edited offline.
+    fn menu_folder(
+        &self,
+        offline: bool,
+        erase: EraseSupport,
+    ) -> widget::menu::Item<BlockAction, String> {
         use widget::menu;
         match &self.partition {
-            Some(partition) => menu::Item::Folder(
+            Some(partition) if offline => menu::Item::Folder(
                 partition.name.to_string(),
                 vec![menu::Item::Button(
-                    "Format".to_string(),
+                    "Delete".to_string(),
                     None,
-                    BlockAction::FormatPartition(self.offset),
+                    BlockAction::DeletePartition(self.offset),
                 )],
             ),
+            Some(partition) => {
+                let mut items = vec![
+                    menu::Item::Button(
+                        "Format".to_string(),
+                        None,
+                        BlockAction::FormatPartition(self.offset, erase),
+                    ),
+                    menu::Item::Button(
+                        "Create Partition Image".to_string(),
+                        None,
+                        BlockAction::MakeImage(self.offset),
+                    ),
+                    menu::Item::Button(
+                        "Restore Partition Image".to_string(),
+                        None,
+                        BlockAction::RestoreImage(self.offset, self.size),
+                    ),
+                    menu::Item::Button(
+                        "Clone Partition".to_string(),
+                        None,
+                        BlockAction::ClonePartition(self.offset, self.size),
+                    ),
+                ];
+                if let Some(tools) = &partition.tools {
+                    items.push(menu::Item::Divider);
+                    items.push(tool_item(
+                        "Check Filesystem",
+                        &tools.check,
+                        BlockAction::CheckFilesystem(self.offset),
+                    ));
+                    items.push(tool_item(
+                        "Repair Filesystem",
+                        &tools.repair,
+                        BlockAction::RepairFilesystem(self.offset),
+                    ));
+                }
+                if let Some(btrfs) = partition
+                    .btrfs
+                    .as_ref()
+                    .filter(|btrfs| btrfs.mount_point.is_some())
+                {
+                    items.push(menu::Item::Divider);
+                    items.push(menu::Item::Button(
+                        "Create Subvolume".to_string(),
+                        None,
+                        BlockAction::CreateSubvolume(self.offset),
+                    ));
+                    items.push(menu::Item::Folder(
+                        "Subvolumes".to_string(),
+                        btrfs
+                            .subvolumes
+                            .iter()
+                            // udisks addresses subvolumes through the mount point
+                            .filter(|subvolume| subvolume.mounted_path.is_some())
+                            .map(|subvolume| {
+                                menu::Item::Folder(
+                                    subvolume.path.clone(),
+                                    vec![
+                                        menu::Item::Button(
+                                            "Create Snapshot".to_string(),
+                                            None,
+                                            BlockAction::SnapshotSubvolume(
+                                                self.offset,
+                                                subvolume.id,
+                                            ),
+                                        ),
+                                        menu::Item::Button(
+                                            "Delete".to_string(),
+                                            None,
+                                            BlockAction::DeleteSubvolume(self.offset, subvolume.id),
+                                        ),
+                                    ],
+                                )
+                            })
+                            .collect(),
+                    ));
+                    items.push(menu::Item::Folder(
+                        "Add Device to this Btrfs".to_string(),
+                        btrfs
+                            .candidates
+                            .iter()
+                            .enumerate()
+                            .map(|(index, candidate)| {
+                                menu::Item::Button(
+                                    format!("{} ({})", candidate.device, candidate.size),
+                                    None,
+                                    BlockAction::AddBtrfsDevice(self.offset, index),
+                                )
+                            })
+                            .collect(),
+                    ));
+                    // The last device can not be removed
+                    if btrfs.members.len() > 1 {
+                        let degrades = btrfs.removal_degrades();
+                        items.push(menu::Item::Folder(
+                            "Remove Device".to_string(),
+                            btrfs
+                                .members
+                                .iter()
+                                .enumerate()
+                                .map(|(index, member)| {
+                                    menu::Item::Button(
+                                        member.device.clone(),
+                                        None,
+                                        BlockAction::RemoveBtrfsDevice(
+                                            self.offset,
+                                            index,
+                                            degrades,
+                                        ),
+                                    )
+                                })
+                                .collect(),
+                        ));
+                    }
+                }
+                menu::Item::Folder(partition.name.to_string(), items)
+            }
             None => menu::Item::Folder(
                 "Empty Space".to_string(),
                 vec![menu::Item::Button(
@@ -393,9 +1231,15 @@ impl Block {
 
 #[derive(Clone, Debug)]
 pub struct Partition {
-    pub block: BlockProxy<'static>,
-    pub _part: PartitionProxy<'static>,
+    /// Both are missing for partitions of image files edited offline
+    pub block: Option<BlockProxy<'static>>,
+    pub part: Option<PartitionProxy<'static>>,
     pub fs: Option<FilesystemProxy<'static>>,
+    pub btrfs: Option<Btrfs>,
+    /// Why the btrfs details could not be read, the partition is shown without them
+    pub btrfs_error: Option<String>,
+    /// Check and repair support of the file system type, for partitions with a file system
+    pub tools: Option<FsTools>,
 
     pub name: String,
     pub partition_id: String,
@@ -410,6 +1254,24 @@ pub struct Partition {
 
 impl Partition {
     pub fn view(&self) -> Element<Result<AppMessage, Error>> {
+        let theme = theme::active();
+        let cosmic = theme.cosmic();
+
+        let mut column = widget::column()
+            .spacing(cosmic.space_xs())
+            .push(self.details());
+        if let Some(btrfs) = &self.btrfs {
+            column = column.push(btrfs.view());
+        }
+        if let Some(err) = &self.btrfs_error {
+            column = column.push(widget::settings::section().title("Btrfs").add(
+                widget::text::body(format!("The btrfs details could not be read: {err}")),
+            ));
+        }
+        column.into()
+    }
+
+    fn details(&self) -> Element<Result<AppMessage, Error>> {
         widget::settings::section()
             .title(&self.name)
             .add(widget::settings::item(
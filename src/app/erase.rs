@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use udisks2::{
+    zbus::zvariant::{OwnedObjectPath, Value},
+    Client,
+};
+
+use super::error::Error;
+use super::nvme::{Nvme, SanitizeAction};
+use super::progress::Reporter;
+use super::smart::duration_for_display;
+
+/// Erase methods a drive supports, read once when the drive is loaded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EraseSupport {
+    pub discard: bool,
+    /// Minutes the drive estimates for an ATA Security Erase, `None` if unsupported or frozen
+    pub ata_minutes: Option<i32>,
+    pub ata_enhanced_minutes: Option<i32>,
+    pub sanitize: bool,
+}
+
+impl EraseSupport {
+    pub async fn load(
+        client: &Client,
+        drive_path: Option<OwnedObjectPath>,
+        device_number: u64,
+        sanitize: bool,
+    ) -> Result<Self, Error> {
+        // Partitions have no queue of their own, whole devices report what the hardware allows
+        let major = ((device_number >> 8) & 0xfff) | ((device_number >> 32) & !0xfff);
+        let minor = (device_number & 0xff) | ((device_number >> 12) & !0xff);
+        let discard = std::fs::read_to_string(format!(
+            "/sys/dev/block/{major}:{minor}/queue/discard_max_bytes"
+        ))
+        .ok()
+        .and_then(|bytes| bytes.trim().parse::<u64>().ok())
+        .is_some_and(|bytes| bytes > 0);
+
+        let mut support = Self {
+            discard,
+            sanitize,
+            ..Default::default()
+        };
+        let Some(drive_path) = drive_path else {
+            return Ok(support);
+        };
+        let Ok(ata) = client.object(drive_path).unwrap().drive_ata().await else {
+            return Ok(support);
+        };
+        // A frozen drive rejects the erase until it was power cycled
+        if ata.security_frozen().await.unwrap_or(true) {
+            return Ok(support);
+        }
+        let minutes = |minutes: i32| (minutes > 0).then_some(minutes);
+        support.ata_minutes = minutes(ata.security_erase_unit_minutes().await?);
+        support.ata_enhanced_minutes = minutes(ata.security_enhanced_erase_unit_minutes().await?);
+        Ok(support)
+    }
+}
+
+/// How a drive or partition is erased before it is formatted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EraseMethod {
+    /// Keep the old data, only the new structures are written
+    None,
+    /// Tell the device the blocks are unused, fast but not guaranteed to erase
+    Discard,
+    Zero,
+    AtaSecurityErase,
+    AtaEnhancedErase,
+    Sanitize(SanitizeAction),
+}
+
+impl EraseMethod {
+    /// Methods available for a device, hardware methods always erase the whole drive.
+    ///
+    /// udisks only discards while creating a file system, so formatting a whole drive with a
+    /// partition table does not offer it.
+    pub fn available(support: EraseSupport, whole_drive: bool) -> Vec<Self> {
+        let mut methods = vec![Self::None];
+        if support.discard && !whole_drive {
+            methods.push(Self::Discard);
+        }
+        methods.push(Self::Zero);
+        if !whole_drive {
+            return methods;
+        }
+        if support.ata_minutes.is_some() {
+            methods.push(Self::AtaSecurityErase);
+        }
+        if support.ata_enhanced_minutes.is_some() {
+            methods.push(Self::AtaEnhancedErase);
+        }
+        if support.sanitize {
+            methods.extend(SanitizeAction::ALL.map(Self::Sanitize));
+        }
+        methods
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::None => "None (Fast)".to_string(),
+            Self::Discard => "Discard (Fast, for SSDs)".to_string(),
+            Self::Zero => "Overwrite with Zeros (Slow)".to_string(),
+            Self::AtaSecurityErase => "ATA Security Erase".to_string(),
+            Self::AtaEnhancedErase => "ATA Enhanced Security Erase".to_string(),
+            Self::Sanitize(action) => format!("NVMe Sanitize, {}", action.name()),
+        }
+    }
+
+    /// The time the drive itself estimates, only ATA drives report one.
+    pub fn estimated_time(&self, support: EraseSupport) -> Option<String> {
+        let minutes = match self {
+            Self::AtaSecurityErase => support.ata_minutes?,
+            Self::AtaEnhancedErase => support.ata_enhanced_minutes?,
+            _ => return None,
+        };
+        Some(duration_for_display(minutes as u64 * 60))
+    }
+
+    /// Adds the options for `Block.Format` that perform the erase, if udisks does it.
+    ///
+    /// Discarding is left to `mkfs`, which `no-discard` controls.
+    pub fn format_options(&self, options: &mut HashMap<&str, Value<'_>>) {
+        match self {
+            Self::Discard => options.insert("no-discard", false.into()),
+            Self::Zero => options.insert("erase", "zero".into()),
+            // Everything else is erased before formatting, or not at all
+            _ => options.insert("no-discard", true.into()),
+        };
+    }
+
+    /// Runs the hardware erase of the whole drive ahead of formatting it.
+    pub async fn erase_drive(
+        &self,
+        client: &Client,
+        drive_path: &OwnedObjectPath,
+        nvme: Option<&Nvme>,
+        reporter: &mut Reporter,
+        capacity: u64,
+    ) -> Result<(), Error> {
+        match self {
+            Self::AtaSecurityErase | Self::AtaEnhancedErase => {
+                let ata = client
+                    .object(drive_path.clone())
+                    .unwrap()
+                    .drive_ata()
+                    .await?;
+                let mut options = udisks2::standard_options(false);
+                options.insert("enhanced", (*self == Self::AtaEnhancedErase).into());
+                reporter.stage("Erasing Drive", capacity);
+                ata.security_erase_unit(options).await?;
+            }
+            Self::Sanitize(action) => {
+                if let Some(nvme) = nvme {
+                    reporter.stage(format!("Sanitizing, {}", action.name()), capacity);
+                    nvme.sanitize(*action, reporter, capacity).await?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
@@ -18,3 +18,23 @@ impl From<udisks2::Error> for Error {
         Self::new(error.to_string(), false)
     }
 }
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Self::new(error.to_string(), true)
+    }
+}
+
+/// D-Bus calls fail for reasons the user can act on, e.g. a cancelled authorization or a busy
+/// device, so they do not end the app.
+impl From<zbus::Error> for Error {
+    fn from(error: zbus::Error) -> Self {
+        Self::new(error.to_string(), true)
+    }
+}
+
+impl From<zbus::fdo::Error> for Error {
+    fn from(error: zbus::fdo::Error) -> Self {
+        Self::new(error.to_string(), true)
+    }
+}
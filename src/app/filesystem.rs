@@ -0,0 +1,312 @@
+use udisks2::{filesystem::FilesystemProxy, Client};
+
+use super::{device, error::Error};
+
+/// Whether udisks can run an operation on a file system type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Availability {
+    Available,
+    /// The userspace tool udisks needs for it is not installed
+    Missing(String),
+}
+
+impl Availability {
+    fn new((available, tool): (bool, String)) -> Self {
+        match available {
+            true => Self::Available,
+            false => Self::Missing(tool),
+        }
+    }
+}
+
+/// Maintenance udisks supports for the file system type of a partition.
+#[derive(Clone, Debug)]
+pub struct FsTools {
+    pub check: Availability,
+    pub repair: Availability,
+}
+
+impl FsTools {
+    pub async fn load(client: &Client, fs_type: &str) -> Result<Self, Error> {
+        let manager = client.manager();
+        Ok(Self {
+            check: Availability::new(manager.can_check(fs_type).await?),
+            repair: Availability::new(manager.can_repair(fs_type).await?),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckResult {
+    Clean,
+    /// Only a check was run, a repair is needed
+    Damaged,
+    Repaired,
+    NotRepaired,
+}
+
+impl CheckResult {
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Clean => "File System is Clean",
+            Self::Damaged => "File System is Damaged",
+            Self::Repaired => "File System Repaired",
+            Self::NotRepaired => "File System Not Repaired",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Clean => "No errors were found.",
+            Self::Damaged => "Errors were found, repair the file system to fix them.",
+            Self::Repaired => "Errors were found and fixed.",
+            Self::NotRepaired => {
+                "Errors were found that could not be fixed. Back up the data you can still read, then format the partition."
+            }
+        }
+    }
+}
+
+/// Checks a file system and repairs it if asked to and needed.
+///
+/// Both need the file system unmounted, it is mounted again afterwards if it was mounted before,
+/// also when the check failed. The result comes with the error of mounting it again, so it is
+/// shown even then.
+pub async fn check(
+    fs: &FilesystemProxy<'static>,
+    repair: bool,
+) -> Result<(CheckResult, Option<Error>), Error> {
+    let mounted = !fs.mount_points().await?.is_empty();
+    if mounted {
+        device::unmount(fs).await?;
+    }
+
+    let result = async {
+        let clean = fs.check(udisks2::standard_options(false)).await?;
+        Ok(match (clean, repair) {
+            (true, _) => CheckResult::Clean,
+            (false, false) => CheckResult::Damaged,
+            (false, true) => match fs.repair(udisks2::standard_options(false)).await? {
+                true => CheckResult::Repaired,
+                false => CheckResult::NotRepaired,
+            },
+        })
+    }
+    .await
+    .map_err(|err: zbus::Error| {
+        Error::new(format!("The file system could not be checked: {err}"), true)
+    });
+
+    let remount = match mounted {
+        true => fs
+            .mount(udisks2::standard_options(false))
+            .await
+            .err()
+            .map(|err| {
+                Error::new(
+                    format!("The file system could not be mounted again: {err}"),
+                    true,
+                )
+            }),
+        false => None,
+    };
+    match (result, remount) {
+        (Ok(result), remount) => Ok((result, remount)),
+        (Err(err), None) => Err(err),
+        (Err(err), Some(remount)) => Err(Error::new(
+            format!("{} {}", err.description, remount.description),
+            true,
+        )),
+    }
+}
+
+/// File systems offered for formatting, in the order they are listed.
+const FORMAT_TYPES: [(&str, &str); 9] = [
+    ("ext4", "Linux (Ext4)"),
+    ("btrfs", "Linux (Btrfs)"),
+    ("xfs", "Linux (XFS)"),
+    ("f2fs", "Flash Storage (F2FS)"),
+    ("ntfs", "Windows (NTFS)"),
+    ("exfat", "Universal (exFAT)"),
+    ("vfat", "Universal (FAT)"),
+    ("udf", "Optical and Universal (UDF)"),
+    ("swap", "Linux Swap"),
+];
+
+/// A file system type that can be chosen when formatting.
+#[derive(Clone, Debug)]
+pub struct FormatType {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub availability: Availability,
+}
+
+impl FormatType {
+    /// Name for the dropdown, unavailable types stay listed so the hint can explain why.
+    pub fn name_for_display(&self) -> String {
+        match self.availability {
+            Availability::Available => self.name.to_string(),
+            Availability::Missing(_) => format!("{} (Unavailable)", self.name),
+        }
+    }
+}
+
+/// File system types udisks was built with, with whether their mkfs tool is installed.
+pub async fn format_types(client: &Client) -> Result<Vec<FormatType>, Error> {
+    let manager = client.manager();
+    let supported = manager.supported_filesystems().await?;
+    let mut types = Vec::new();
+    for (id, name) in FORMAT_TYPES {
+        if !supported.iter().any(|supported| supported == id) {
+            continue;
+        }
+        types.push(FormatType {
+            id,
+            name,
+            availability: Availability::new(manager.can_format(id).await?),
+        });
+    }
+    Ok(types)
+}
+
+/// How the length of a label is counted on disk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LabelUnit {
+    Bytes,
+    /// UTF-16 code units, for file systems from Windows
+    Utf16,
+}
+
+/// Limits a file system puts on its label.
+struct LabelRules {
+    max: usize,
+    unit: LabelUnit,
+    /// FAT stores labels in upper case only
+    uppercase: bool,
+    ascii_only: bool,
+    forbidden: &'static str,
+}
+
+fn label_rules(fs_type: &str) -> Option<LabelRules> {
+    let rules = |max, unit| LabelRules {
+        max,
+        unit,
+        uppercase: false,
+        ascii_only: false,
+        forbidden: "",
+    };
+    Some(match fs_type {
+        "vfat" => LabelRules {
+            uppercase: true,
+            ascii_only: true,
+            forbidden: "\"*+,./:;<=>?[\\]|",
+            ..rules(11, LabelUnit::Bytes)
+        },
+        "exfat" => rules(15, LabelUnit::Utf16),
+        "ntfs" => rules(128, LabelUnit::Utf16),
+        "ext2" | "ext3" | "ext4" | "swap" => rules(16, LabelUnit::Bytes),
+        "xfs" => rules(12, LabelUnit::Bytes),
+        "btrfs" => rules(255, LabelUnit::Bytes),
+        "f2fs" => rules(512, LabelUnit::Utf16),
+        _ => return None,
+    })
+}
+
+/// Adapts a label to what the file system stores, e.g. upper case for FAT.
+pub fn normalize_label(fs_type: &str, label: &str) -> String {
+    match label_rules(fs_type) {
+        Some(rules) if rules.uppercase => label.to_uppercase(),
+        _ => label.to_string(),
+    }
+}
+
+/// Checks a label against the rules of the file system, the error is shown next to the input.
+pub fn validate_label(fs_type: &str, label: &str) -> Result<(), String> {
+    let Some(rules) = label_rules(fs_type) else {
+        return Ok(());
+    };
+    if rules.ascii_only && !label.is_ascii() {
+        return Err("Only letters and digits without accents are allowed".to_string());
+    }
+    if let Some(c) = label.chars().find(|c| rules.forbidden.contains(*c)) {
+        return Err(format!("The character {c} is not allowed"));
+    }
+    let (length, unit) = match rules.unit {
+        LabelUnit::Bytes => (label.len(), "bytes"),
+        LabelUnit::Utf16 => (label.encode_utf16().count(), "characters"),
+    };
+    if length > rules.max {
+        return Err(format!(
+            "The label is {length} {unit} long, at most {} are allowed",
+            rules.max
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vfat_labels() {
+        assert_eq!(normalize_label("vfat", "Backup 2024"), "BACKUP 2024");
+        assert_eq!(validate_label("vfat", "BACKUP 2024"), Ok(()));
+        assert_eq!(
+            validate_label("vfat", "BACKUP 20245"),
+            Err("The label is 12 bytes long, at most 11 are allowed".to_string())
+        );
+        for c in "\"*+,./:;<=>?[\\]|".chars() {
+            assert_eq!(
+                validate_label("vfat", &format!("A{c}B")),
+                Err(format!("The character {c} is not allowed"))
+            );
+        }
+        assert_eq!(
+            validate_label("vfat", "MÜLL"),
+            Err("Only letters and digits without accents are allowed".to_string())
+        );
+    }
+
+    #[test]
+    fn windows_labels_count_utf16() {
+        // Each of these takes two UTF-16 units, an emoji takes four bytes in UTF-8
+        assert_eq!(validate_label("exfat", &"😀".repeat(7)), Ok(()));
+        assert_eq!(
+            validate_label("exfat", &format!("{}a", "😀".repeat(7))),
+            Ok(())
+        );
+        assert_eq!(
+            validate_label("exfat", &"😀".repeat(8)),
+            Err("The label is 16 characters long, at most 15 are allowed".to_string())
+        );
+        assert_eq!(validate_label("exfat", &"é".repeat(15)), Ok(()));
+        assert_eq!(validate_label("ntfs", &"é".repeat(128)), Ok(()));
+        assert_eq!(
+            validate_label("ntfs", &"é".repeat(129)),
+            Err("The label is 129 characters long, at most 128 are allowed".to_string())
+        );
+        // Neither changes case nor restricts characters like FAT
+        assert_eq!(normalize_label("ntfs", "Daten"), "Daten");
+        assert_eq!(validate_label("exfat", "a.b"), Ok(()));
+    }
+
+    #[test]
+    fn linux_labels_count_bytes() {
+        assert_eq!(validate_label("ext4", &"a".repeat(16)), Ok(()));
+        assert_eq!(validate_label("ext4", &"ä".repeat(8)), Ok(()));
+        assert_eq!(
+            validate_label("ext4", &"ä".repeat(9)),
+            Err("The label is 18 bytes long, at most 16 are allowed".to_string())
+        );
+        assert_eq!(validate_label("xfs", &"a".repeat(12)), Ok(()));
+        assert_eq!(validate_label("xfs", &"ä".repeat(6)), Ok(()));
+        assert_eq!(
+            validate_label("xfs", "äääääa€"),
+            Err("The label is 14 bytes long, at most 12 are allowed".to_string())
+        );
+        assert_eq!(normalize_label("ext4", "Home"), "Home");
+        // Unknown types are left to udisks
+        assert_eq!(validate_label("unknown", &"a".repeat(1000)), Ok(()));
+    }
+}
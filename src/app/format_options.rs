@@ -0,0 +1,312 @@
+/// Kinds of values an advanced format option takes.
+pub enum OptionKind {
+    /// Values passed to mkfs with their names, the first one leaves the choice to mkfs
+    Choice {
+        values: &'static [&'static str],
+        names: &'static [&'static str],
+    },
+    /// A whole number in a range, left to mkfs when empty
+    Number { min: u32, max: u32 },
+    /// Passes the arguments when enabled
+    Flag,
+}
+
+/// An advanced option of a file system, passed through to its mkfs tool.
+pub struct FormatOption {
+    pub name: &'static str,
+    pub kind: OptionKind,
+    /// Arguments for mkfs, `{}` is replaced by the value
+    args: &'static str,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OptionValue {
+    Choice(usize),
+    Number(String),
+    Flag(bool),
+}
+
+const VFAT: &[FormatOption] = &[
+    FormatOption {
+        name: "Cluster Size",
+        kind: OptionKind::Choice {
+            values: &["", "1", "2", "4", "8", "16", "32", "64", "128"],
+            names: &[
+                "Default", "512 B", "1 KiB", "2 KiB", "4 KiB", "8 KiB", "16 KiB", "32 KiB",
+                "64 KiB",
+            ],
+        },
+        args: "-s {}",
+    },
+    FormatOption {
+        name: "FAT Size",
+        kind: OptionKind::Choice {
+            values: &["", "12", "16", "32"],
+            names: &["Default", "FAT12", "FAT16", "FAT32"],
+        },
+        args: "-F {}",
+    },
+];
+
+const EXFAT: &[FormatOption] = &[FormatOption {
+    name: "Cluster Size",
+    kind: OptionKind::Choice {
+        values: &["", "4K", "16K", "32K", "64K", "128K", "256K", "1M"],
+        names: &[
+            "Default", "4 KiB", "16 KiB", "32 KiB", "64 KiB", "128 KiB", "256 KiB", "1 MiB",
+        ],
+    },
+    args: "-c {}",
+}];
+
+const EXT4: &[FormatOption] = &[
+    FormatOption {
+        name: "Reserved Blocks (%)",
+        kind: OptionKind::Number { min: 0, max: 50 },
+        args: "-m {}",
+    },
+    FormatOption {
+        name: "Inode Size",
+        kind: OptionKind::Choice {
+            values: &["", "128", "256", "512", "1024"],
+            names: &["Default", "128 B", "256 B", "512 B", "1 KiB"],
+        },
+        args: "-I {}",
+    },
+    FormatOption {
+        name: "Initialize Inode Tables Now",
+        kind: OptionKind::Flag,
+        args: "-E lazy_itable_init=0,lazy_journal_init=0",
+    },
+];
+
+const XFS: &[FormatOption] = &[
+    FormatOption {
+        name: "Block Size",
+        kind: OptionKind::Choice {
+            values: &["", "1024", "2048", "4096"],
+            names: &["Default", "1 KiB", "2 KiB", "4 KiB"],
+        },
+        args: "-b size={}",
+    },
+    FormatOption {
+        name: "Disable Reflink",
+        kind: OptionKind::Flag,
+        args: "-m reflink=0",
+    },
+    FormatOption {
+        name: "Timestamps Beyond 2038",
+        kind: OptionKind::Flag,
+        args: "-m bigtime=1",
+    },
+];
+
+const BTRFS: &[FormatOption] = &[
+    FormatOption {
+        name: "Checksum",
+        kind: OptionKind::Choice {
+            values: &["", "crc32c", "xxhash", "sha256", "blake2"],
+            names: &["Default", "CRC32C", "xxHash", "SHA-256", "BLAKE2"],
+        },
+        args: "--csum {}",
+    },
+    FormatOption {
+        name: "Metadata Profile",
+        kind: OptionKind::Choice {
+            values: &["", "single", "dup"],
+            names: &["Default", "Single", "Duplicated"],
+        },
+        args: "-m {}",
+    },
+    FormatOption {
+        name: "Block Group Tree",
+        kind: OptionKind::Flag,
+        args: "-O block-group-tree",
+    },
+];
+
+const F2FS: &[FormatOption] = &[FormatOption {
+    name: "Compression Support",
+    kind: OptionKind::Flag,
+    args: "-O extra_attr,compression",
+}];
+
+const NTFS: &[FormatOption] = &[FormatOption {
+    name: "Cluster Size",
+    kind: OptionKind::Choice {
+        values: &[
+            "", "512", "1024", "2048", "4096", "8192", "16384", "32768", "65536",
+        ],
+        names: &[
+            "Default", "512 B", "1 KiB", "2 KiB", "4 KiB", "8 KiB", "16 KiB", "32 KiB", "64 KiB",
+        ],
+    },
+    args: "-c {}",
+}];
+
+/// Advanced options of a file system type, empty for types without any.
+pub fn options(fs_type: &str) -> &'static [FormatOption] {
+    match fs_type {
+        "vfat" => VFAT,
+        "exfat" => EXFAT,
+        "ext4" => EXT4,
+        "xfs" => XFS,
+        "btrfs" => BTRFS,
+        "f2fs" => F2FS,
+        "ntfs" => NTFS,
+        _ => &[],
+    }
+}
+
+/// udisks passes arguments on to mkfs with the `mkfs-args` option since 2.10.
+pub fn supported(udisks_version: &str) -> bool {
+    let mut parts = udisks_version
+        .split('.')
+        .map(|part| part.parse::<u32>().unwrap_or(0));
+    let (major, minor) = (parts.next().unwrap_or(0), parts.next().unwrap_or(0));
+    (major, minor) >= (2, 10)
+}
+
+impl FormatOption {
+    pub fn default_value(&self) -> OptionValue {
+        match self.kind {
+            OptionKind::Choice { .. } => OptionValue::Choice(0),
+            OptionKind::Number { .. } => OptionValue::Number(String::new()),
+            OptionKind::Flag => OptionValue::Flag(false),
+        }
+    }
+
+    /// Checks a value, returning a message to show next to the option if it is invalid.
+    pub fn validate(&self, value: &OptionValue) -> Result<(), String> {
+        match (&self.kind, value) {
+            (OptionKind::Number { min, max }, OptionValue::Number(number))
+                if !number.is_empty() =>
+            {
+                match number.parse::<u32>() {
+                    Ok(number) if (*min..=*max).contains(&number) => Ok(()),
+                    _ => Err(format!("Enter a number from {min} to {max}")),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn args(&self, value: &OptionValue) -> Vec<String> {
+        let value = match (&self.kind, value) {
+            (OptionKind::Choice { values, .. }, OptionValue::Choice(index)) => {
+                values.get(*index).copied().unwrap_or_default()
+            }
+            (OptionKind::Number { .. }, OptionValue::Number(number)) => number.as_str(),
+            (OptionKind::Flag, OptionValue::Flag(true)) => "",
+            _ => return Vec::new(),
+        };
+        if value.is_empty() && self.args.contains("{}") {
+            return Vec::new();
+        }
+        self.args
+            .replace("{}", value)
+            .split_whitespace()
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// Arguments for mkfs from the values of the options, in the same order.
+pub fn mkfs_args(options: &[FormatOption], values: &[OptionValue]) -> Vec<String> {
+    options
+        .iter()
+        .zip(values)
+        .flat_map(|(option, value)| option.args(value))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn option(fs_type: &str, name: &str) -> &'static FormatOption {
+        options(fs_type)
+            .iter()
+            .find(|option| option.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn defaults_leave_everything_to_mkfs() {
+        for fs_type in ["vfat", "exfat", "ext4", "xfs", "btrfs", "f2fs", "ntfs"] {
+            let options = options(fs_type);
+            assert!(!options.is_empty(), "{fs_type}");
+            let values: Vec<_> = options.iter().map(FormatOption::default_value).collect();
+            for (option, value) in options.iter().zip(&values) {
+                assert_eq!(option.validate(value), Ok(()), "{fs_type} {}", option.name);
+            }
+            assert!(mkfs_args(options, &values).is_empty(), "{fs_type}");
+        }
+        assert!(options("swap").is_empty());
+    }
+
+    #[test]
+    fn values_are_substituted_and_split() {
+        let options = options("xfs");
+        let values = [
+            OptionValue::Choice(3),
+            OptionValue::Flag(false),
+            OptionValue::Flag(true),
+        ];
+        assert_eq!(
+            mkfs_args(options, &values),
+            ["-b", "size=4096", "-m", "bigtime=1"]
+        );
+
+        let reserved = option("ext4", "Reserved Blocks (%)");
+        assert_eq!(
+            reserved.args(&OptionValue::Number("0".to_string())),
+            ["-m", "0"]
+        );
+        // Mismatched values and choices out of range are ignored
+        assert!(reserved.args(&OptionValue::Flag(true)).is_empty());
+        assert!(option("vfat", "FAT Size")
+            .args(&OptionValue::Choice(9))
+            .is_empty());
+    }
+
+    #[test]
+    fn flags() {
+        let flag = option("ext4", "Initialize Inode Tables Now");
+        assert!(flag.args(&OptionValue::Flag(false)).is_empty());
+        assert_eq!(
+            flag.args(&OptionValue::Flag(true)),
+            ["-E", "lazy_itable_init=0,lazy_journal_init=0"]
+        );
+        assert_eq!(
+            option("f2fs", "Compression Support").args(&OptionValue::Flag(true)),
+            ["-O", "extra_attr,compression"]
+        );
+    }
+
+    #[test]
+    fn numbers_in_range() {
+        let reserved = option("ext4", "Reserved Blocks (%)");
+        let validate = |number: &str| reserved.validate(&OptionValue::Number(number.to_string()));
+        assert_eq!(validate(""), Ok(()));
+        assert_eq!(validate("0"), Ok(()));
+        assert_eq!(validate("50"), Ok(()));
+        let invalid = Err("Enter a number from 0 to 50".to_string());
+        assert_eq!(validate("51"), invalid);
+        assert_eq!(validate("-1"), invalid);
+        assert_eq!(validate("5%"), invalid);
+        assert_eq!(validate("99999999999"), invalid);
+    }
+
+    #[test]
+    fn udisks_versions() {
+        assert!(!supported("2.9.4"));
+        assert!(supported("2.10.1"));
+        assert!(supported("2.10"));
+        assert!(supported("3.0.0"));
+        assert!(!supported("1.99"));
+        assert!(!supported(""));
+        assert!(!supported("unknown"));
+        assert!(!supported("2.x"));
+    }
+}
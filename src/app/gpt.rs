@@ -0,0 +1,346 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+pub const GPT_SIGNATURE: &[u8; 8] = b"EFI PART";
+pub const MBR_SIGNATURE: [u8; 2] = [0x55, 0xaa];
+/// Partition entry arrays are 16 KiB in practice, anything far larger is not a sane table.
+const MAX_ENTRIES_SIZE: u64 = 1024 * 1024;
+
+/// Reads random bytes from the kernel.
+pub fn random_bytes<const N: usize>() -> std::io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// A random version 4 GUID in the mixed endian layout GPT stores on disk.
+pub fn random_guid() -> std::io::Result<[u8; 16]> {
+    let mut guid = random_bytes::<16>()?;
+    // The third field is little endian, its version nibble ends up in the eighth byte
+    guid[7] = (guid[7] & 0x0f) | 0x40;
+    guid[8] = (guid[8] & 0x3f) | 0x80;
+    Ok(guid)
+}
+
+/// A random UUID in the form `mkfs` and friends accept for the given file system type.
+pub fn random_fs_uuid(id_type: &str) -> std::io::Result<String> {
+    let hex = |bytes: &[u8]| -> String { bytes.iter().map(|byte| format!("{byte:02x}")).collect() };
+    Ok(match id_type {
+        "vfat" | "exfat" => {
+            let serial = random_bytes::<4>()?;
+            format!("{}-{}", hex(&serial[..2]), hex(&serial[2..])).to_uppercase()
+        }
+        "ntfs" => hex(&random_bytes::<8>()?).to_uppercase(),
+        _ => {
+            let mut uuid = random_bytes::<16>()?;
+            uuid[6] = (uuid[6] & 0x0f) | 0x40;
+            uuid[8] = (uuid[8] & 0x3f) | 0x80;
+            format!(
+                "{}-{}-{}-{}-{}",
+                hex(&uuid[..4]),
+                hex(&uuid[4..6]),
+                hex(&uuid[6..8]),
+                hex(&uuid[8..10]),
+                hex(&uuid[10..])
+            )
+        }
+    })
+}
+
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(bytes);
+    crc.sum()
+}
+
+pub fn read_at(
+    device: &mut (impl Read + Seek),
+    offset: u64,
+    buf: &mut [u8],
+) -> std::io::Result<()> {
+    device.seek(SeekFrom::Start(offset))?;
+    device.read_exact(buf)
+}
+
+pub fn write_at(device: &mut (impl Write + Seek), offset: u64, buf: &[u8]) -> std::io::Result<()> {
+    device.seek(SeekFrom::Start(offset))?;
+    device.write_all(buf)
+}
+
+/// Little endian integers, as all GPT and MBR fields are stored.
+pub fn u32_at(buf: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+pub fn u64_at(buf: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(buf[offset..offset + 8].try_into().unwrap())
+}
+
+fn invalid(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+/// Finds the sector size a GPT was written with by looking for its header in the second sector.
+fn gpt_sector_size(device: &mut File) -> std::io::Result<Option<u64>> {
+    for sector_size in [512, 4096] {
+        let mut signature = [0; 8];
+        if read_at(device, sector_size, &mut signature).is_ok() && &signature == GPT_SIGNATURE {
+            return Ok(Some(sector_size));
+        }
+    }
+    Ok(None)
+}
+
+/// Gives a freshly cloned disk its own identity.
+///
+/// A GPT gets a new disk GUID and new unique partition GUIDs, and its backup header and
+/// partition entries are moved to the end of the device in case it is larger than the source.
+/// An MBR only gets a new disk signature. Returns whether a partition table was found.
+pub fn regenerate_ids(device: &mut File, size: u64) -> std::io::Result<bool> {
+    let Some(sector_size) = gpt_sector_size(device)? else {
+        let mut mbr = [0; 512];
+        read_at(device, 0, &mut mbr)?;
+        if mbr[510..] != MBR_SIGNATURE {
+            return Ok(false);
+        }
+        write_at(device, 440, &random_bytes::<4>()?)?;
+        device.sync_all()?;
+        return Ok(true);
+    };
+
+    // The header was copied from the source as is, nothing in it is trusted before it is checked
+    let mut header = vec![0; sector_size as usize];
+    read_at(device, sector_size, &mut header)?;
+    let header_size = u32_at(&header, 12) as usize;
+    if !(92..=header.len()).contains(&header_size) {
+        return Err(invalid("The GPT header has an invalid size"));
+    }
+    let mut unchecked = header[..header_size].to_vec();
+    unchecked[16..20].fill(0);
+    if crc32(&unchecked) != u32_at(&header, 16) {
+        return Err(invalid(
+            "The GPT header is damaged, its checksum does not match",
+        ));
+    }
+    let old_backup_lba = u64_at(&header, 32);
+    let entries_lba = u64_at(&header, 72);
+    let entry_count = u32_at(&header, 80) as u64;
+    let entry_size = u32_at(&header, 84) as u64;
+    if entry_size < 128 || !entry_size.is_power_of_two() {
+        return Err(invalid("The GPT partition entries have an invalid size"));
+    }
+    let entries_size = entry_count
+        .checked_mul(entry_size)
+        .filter(|size| *size <= MAX_ENTRIES_SIZE)
+        .ok_or_else(|| invalid("The GPT partition entry array is too large"))?;
+
+    let last_lba = (size / sector_size)
+        .checked_sub(1)
+        .ok_or_else(|| invalid("The device is too small for a GPT"))?;
+    let entries_sectors = entries_size.div_ceil(sector_size);
+    // The backup entries must fit between the primary ones and the backup header
+    let backup_entries_lba = last_lba
+        .checked_sub(entries_sectors)
+        .filter(|lba| {
+            entries_lba >= 2
+                && entries_lba
+                    .checked_add(entries_sectors)
+                    .is_some_and(|end| end < *lba)
+        })
+        .ok_or_else(|| invalid("The GPT partition entries do not fit onto the device"))?;
+
+    let mut entries = vec![0; entries_size as usize];
+    read_at(device, entries_lba * sector_size, &mut entries)?;
+    if crc32(&entries) != u32_at(&header, 88) {
+        return Err(invalid(
+            "The GPT partition entries are damaged, their checksum does not match",
+        ));
+    }
+    for entry in entries.chunks_mut(entry_size as usize) {
+        // Unused entries have an all zero type GUID
+        if entry[..16].iter().any(|byte| *byte != 0) {
+            entry[16..32].copy_from_slice(&random_guid()?);
+        }
+    }
+
+    header[56..72].copy_from_slice(&random_guid()?);
+    header[48..56].copy_from_slice(&(backup_entries_lba - 1).to_le_bytes());
+    header[88..92].copy_from_slice(&crc32(&entries).to_le_bytes());
+
+    let mut write_header =
+        |device: &mut File, my_lba: u64, alternate_lba: u64, entries_lba: u64| {
+            header[24..32].copy_from_slice(&my_lba.to_le_bytes());
+            header[32..40].copy_from_slice(&alternate_lba.to_le_bytes());
+            header[72..80].copy_from_slice(&entries_lba.to_le_bytes());
+            header[16..20].fill(0);
+            let crc = crc32(&header[..header_size]);
+            header[16..20].copy_from_slice(&crc.to_le_bytes());
+            write_at(device, my_lba * sector_size, &header)
+        };
+
+    write_at(device, entries_lba * sector_size, &entries)?;
+    write_header(device, 1, last_lba, entries_lba)?;
+    write_at(device, backup_entries_lba * sector_size, &entries)?;
+    write_header(device, last_lba, 1, backup_entries_lba)?;
+
+    // The backup header of the source is left in the middle of a larger target
+    if old_backup_lba != last_lba && old_backup_lba < backup_entries_lba {
+        write_at(
+            device,
+            old_backup_lba * sector_size,
+            &vec![0; sector_size as usize],
+        )?;
+    }
+
+    // The protective MBR covers the whole device, as far as 32 bits allow
+    let mut mbr = [0; 512];
+    read_at(device, 0, &mut mbr)?;
+    if mbr[510..] == MBR_SIGNATURE && mbr[446 + 4] == 0xee {
+        let sectors = last_lba.min(u32::MAX as u64) as u32;
+        mbr[446 + 12..446 + 16].copy_from_slice(&sectors.to_le_bytes());
+        write_at(device, 0, &mbr)?;
+    }
+
+    device.sync_all()?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::app::image_table::{self, TableKind};
+
+    const MIB: u64 = 1024 * 1024;
+
+    /// A temporary file, removed again when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        /// Writes `data` to a file of `size` bytes, like cloning a disk onto another.
+        fn new(name: &str, data: &[u8], size: u64) -> (Self, File) {
+            let path =
+                std::env::temp_dir().join(format!("andromeda-gpt-{}-{name}", std::process::id()));
+            std::fs::write(&path, data).unwrap();
+            let file = std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&path)
+                .unwrap();
+            file.set_len(size).unwrap();
+            (Self(path), file)
+        }
+
+        fn read(&self) -> Vec<u8> {
+            std::fs::read(&self.0).unwrap()
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// A disk with a GPT holding two partitions.
+    fn gpt_disk(size: u64) -> Vec<u8> {
+        let mut disk = Cursor::new(vec![0; size as usize]);
+        image_table::create(&mut disk, Some(TableKind::Gpt)).unwrap();
+        image_table::add_partition(&mut disk, MIB, 2 * MIB).unwrap();
+        image_table::add_partition(&mut disk, 4 * MIB, MIB).unwrap();
+        disk.into_inner()
+    }
+
+    /// Returns a header and its partition entries after checking both checksums.
+    fn checked_header(disk: &[u8], lba: u64) -> (&[u8], &[u8]) {
+        let header = &disk[lba as usize * 512..][..512];
+        assert_eq!(&header[..8], GPT_SIGNATURE);
+        let header_size = u32_at(header, 12) as usize;
+        let mut unchecked = header[..header_size].to_vec();
+        unchecked[16..20].fill(0);
+        assert_eq!(crc32(&unchecked), u32_at(header, 16), "header at {lba}");
+
+        let entries_size = (u32_at(header, 80) * u32_at(header, 84)) as usize;
+        let entries = &disk[u64_at(header, 72) as usize * 512..][..entries_size];
+        assert_eq!(crc32(entries), u32_at(header, 88), "entries of {lba}");
+        (header, entries)
+    }
+
+    #[test]
+    fn larger_targets_get_the_backup_at_the_end() {
+        let (temp, mut file) = TempFile::new("larger", &gpt_disk(16 * MIB), 32 * MIB);
+        let old_last_lba = 16 * MIB / 512 - 1;
+        assert!(regenerate_ids(&mut file, 32 * MIB).unwrap());
+
+        let disk = temp.read();
+        let last_lba = 32 * MIB / 512 - 1;
+        let (primary, primary_entries) = checked_header(&disk, 1);
+        assert_eq!(u64_at(primary, 24), 1);
+        assert_eq!(u64_at(primary, 32), last_lba);
+        assert_eq!(u64_at(primary, 72), 2);
+
+        let (backup, backup_entries) = checked_header(&disk, last_lba);
+        assert_eq!(u64_at(backup, 24), last_lba);
+        assert_eq!(u64_at(backup, 32), 1);
+        assert_eq!(u64_at(backup, 72), last_lba - 32);
+        assert_eq!(backup_entries, primary_entries);
+        assert_eq!(&backup[56..72], &primary[56..72]);
+        // The usable space reaches up to the backup entries
+        assert_eq!(u64_at(primary, 48), last_lba - 33);
+
+        // The stale backup header is wiped, the protective MBR grows
+        assert!(disk[old_last_lba as usize * 512..][..512]
+            .iter()
+            .all(|byte| *byte == 0));
+        assert_eq!(u32_at(&disk, 446 + 12) as u64, last_lba);
+    }
+
+    #[test]
+    fn guids_change() {
+        let (temp, mut file) = TempFile::new("guids", &gpt_disk(16 * MIB), 16 * MIB);
+        let before = temp.read();
+        assert!(regenerate_ids(&mut file, 16 * MIB).unwrap());
+        let after = temp.read();
+
+        let (old_header, old_entries) = checked_header(&before, 1);
+        let (new_header, new_entries) = checked_header(&after, 1);
+        assert_ne!(&old_header[56..72], &new_header[56..72]);
+        for (old, new) in old_entries.chunks(128).zip(new_entries.chunks(128)).take(2) {
+            // Types and locations stay, only the unique GUIDs change
+            assert_eq!(&old[..16], &new[..16]);
+            assert_ne!(&old[16..32], &new[16..32]);
+            assert_eq!(&old[32..], &new[32..]);
+        }
+        // Unused entries stay empty
+        assert_eq!(&old_entries[256..], &new_entries[256..]);
+        assert!(new_entries[256..].iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn damaged_tables_are_left_alone() {
+        // A flipped bit in the disk GUID, then in the first partition entry
+        for (name, offset) in [("header", 512 + 60), ("entries", 1024 + 20)] {
+            let mut disk = gpt_disk(16 * MIB);
+            disk[offset] ^= 1;
+            let (temp, mut file) = TempFile::new(name, &disk, 16 * MIB);
+
+            let err = regenerate_ids(&mut file, 16 * MIB).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{name}");
+            assert!(temp.read() == disk, "{name} was written to");
+        }
+    }
+
+    #[test]
+    fn mbr_signature() {
+        let mut source = Cursor::new(vec![0; 4 * MIB as usize]);
+        image_table::create(&mut source, Some(TableKind::Mbr)).unwrap();
+        let (temp, mut file) = TempFile::new("mbr", source.get_ref(), 4 * MIB);
+        assert!(regenerate_ids(&mut file, 4 * MIB).unwrap());
+        let disk = temp.read();
+        assert_ne!(&disk[440..444], &source.get_ref()[440..444]);
+        assert_eq!(&disk[..440], &source.get_ref()[..440]);
+        assert_eq!(&disk[444..], &source.get_ref()[444..]);
+    }
+}
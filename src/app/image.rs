@@ -0,0 +1,702 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use super::error::Error;
+use super::progress::Reporter;
+
+/// Size of each read from or write to a device.
+pub const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Zero runs down to the usual file system block size become holes in sparse images.
+const SPARSE_BLOCK: usize = 4096;
+
+/// Decoders refuse streams needing a larger window than this, keeping memory bounded.
+const DECODER_MEMORY_LIMIT: u64 = 256 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Compression {
+    None,
+    Zstd,
+    Xz,
+    Gzip,
+}
+
+impl Compression {
+    pub const ALL: [Self; 4] = [Self::None, Self::Zstd, Self::Xz, Self::Gzip];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Zstd => "Zstandard (.zst)",
+            Self::Xz => "XZ (.xz)",
+            Self::Gzip => "Gzip (.gz)",
+        }
+    }
+
+    pub fn extension(self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Zstd => Some("zst"),
+            Self::Xz => Some("xz"),
+            Self::Gzip => Some("gz"),
+        }
+    }
+
+    /// Detects the compression of an image from its file name.
+    pub fn for_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        Self::ALL
+            .into_iter()
+            .find(|compression| compression.extension() == extension.as_deref())
+            .unwrap_or(Self::None)
+    }
+
+    /// Appends the extension of this compression to a path unless it already ends in it.
+    pub fn with_extension(self, path: PathBuf) -> PathBuf {
+        match self.extension() {
+            Some(extension) if Self::for_path(&path) != self => {
+                let mut path = path.into_os_string();
+                path.push(".");
+                path.push(extension);
+                PathBuf::from(path)
+            }
+            _ => path,
+        }
+    }
+}
+
+/// Opens an image for streaming, decompressing it on the fly.
+pub fn open(path: &Path) -> Result<Box<dyn Read + Send>, Error> {
+    let file = File::open(path)?;
+    Ok(match Compression::for_path(path) {
+        Compression::None => Box::new(file),
+        Compression::Zstd => {
+            let mut decoder = zstd::stream::read::Decoder::new(file)?;
+            decoder.window_log_max(DECODER_MEMORY_LIMIT.ilog2())?;
+            Box::new(decoder)
+        }
+        Compression::Xz => Box::new(xz2::read::XzDecoder::new_stream(
+            file,
+            xz2::stream::Stream::new_stream_decoder(
+                DECODER_MEMORY_LIMIT,
+                xz2::stream::CONCATENATED,
+            )
+            .map_err(std::io::Error::from)?,
+        )),
+        Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(file)),
+    })
+}
+
+/// Size of an image once decompressed, as far as its container records it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImageSize {
+    Exact(u64),
+    /// Only the first Zstandard frame is looked at, more frames may follow it
+    AtLeast(u64),
+}
+
+impl ImageSize {
+    pub fn bytes(self) -> u64 {
+        match self {
+            Self::Exact(size) | Self::AtLeast(size) => size,
+        }
+    }
+}
+
+/// Size of the image once decompressed, if the container records it.
+///
+/// Zstandard frames carry it in their header when the size was known while compressing, but
+/// finding further frames means walking all blocks, so it is a lower bound. xz keeps it in the
+/// index at the end of each stream. Gzip only records it modulo 4 GiB, which is useless for disk
+/// images, so it is checked while writing instead.
+pub fn uncompressed_size(path: &Path) -> Option<ImageSize> {
+    let mut file = File::open(path).ok()?;
+    match Compression::for_path(path) {
+        Compression::None => file
+            .metadata()
+            .ok()
+            .map(|metadata| ImageSize::Exact(metadata.len())),
+        Compression::Zstd => {
+            let mut header = [0; 18];
+            let read = read_full(&mut file, &mut header).ok()?;
+            zstd::zstd_safe::get_frame_content_size(&header[..read])
+                .ok()
+                .flatten()
+                .map(ImageSize::AtLeast)
+        }
+        Compression::Xz => xz_uncompressed_size(&mut file).map(ImageSize::Exact),
+        Compression::Gzip => None,
+    }
+}
+
+/// Larger indices are rejected rather than read, they would take millions of blocks.
+const XZ_INDEX_LIMIT: u64 = 16 * 1024 * 1024;
+
+/// Sums the uncompressed sizes recorded in the indices of all xz streams, from last to first.
+fn xz_uncompressed_size(file: &mut File) -> Option<u64> {
+    let mut end = file.metadata().ok()?.len();
+    let mut size = 0u64;
+    while end > 0 {
+        // Streams may be followed by padding made of zero bytes
+        let mut word = [0; 4];
+        loop {
+            end = end.checked_sub(4)?;
+            file.seek(SeekFrom::Start(end)).ok()?;
+            file.read_exact(&mut word).ok()?;
+            if word != [0; 4] {
+                end += 4;
+                break;
+            }
+        }
+
+        let mut footer = [0; 12];
+        file.seek(SeekFrom::Start(end.checked_sub(12)?)).ok()?;
+        file.read_exact(&mut footer).ok()?;
+        if &footer[10..] != b"YZ" {
+            return None;
+        }
+        // The footer is untrusted, the index must fit in front of it along with the header
+        let backward_size = (u32::from_le_bytes(footer[4..8].try_into().ok()?) as u64 + 1) * 4;
+        if backward_size > XZ_INDEX_LIMIT || 12 + backward_size + 12 > end {
+            return None;
+        }
+
+        let mut index = vec![0; backward_size as usize];
+        file.seek(SeekFrom::Start(end - 12 - backward_size)).ok()?;
+        file.read_exact(&mut index).ok()?;
+        if index.first() != Some(&0) {
+            return None;
+        }
+
+        let mut position = 1;
+        let mut varint = || {
+            let mut value = 0u64;
+            for shift in 0..9 {
+                let byte = *index.get(position)?;
+                position += 1;
+                value |= ((byte & 0x7f) as u64) << (shift * 7);
+                if byte & 0x80 == 0 {
+                    return Some(value);
+                }
+            }
+            None
+        };
+        let records = varint()?;
+        // Blocks are padded to four bytes, the stream header takes another twelve
+        let mut stream_size = 12 + backward_size + 12;
+        for _ in 0..records {
+            let unpadded = varint()?;
+            stream_size = stream_size.checked_add(unpadded.checked_add(3)? / 4 * 4)?;
+            size = size.checked_add(varint()?)?;
+        }
+        end = end.checked_sub(stream_size)?;
+    }
+    Some(size)
+}
+
+/// Reads until `buf` is full or the end of the input is reached.
+pub fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(filled)
+}
+
+/// Reads the volume label from the primary volume descriptor of an ISO 9660 image.
+pub fn iso_label(path: &Path) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    // The system area takes up the first 16 sectors of 2 KiB
+    let mut descriptor = [0; 2048];
+    file.seek(SeekFrom::Start(16 * 2048)).ok()?;
+    file.read_exact(&mut descriptor).ok()?;
+    if descriptor[0] != 1 || &descriptor[1..6] != b"CD001" {
+        return None;
+    }
+    let label = String::from_utf8_lossy(&descriptor[40..72])
+        .trim()
+        .to_string();
+    (!label.is_empty()).then_some(label)
+}
+
+/// Path of the checksum file written next to an image.
+pub fn checksum_path(image: &Path) -> PathBuf {
+    let mut path = image.as_os_str().to_owned();
+    path.push(".sha256");
+    PathBuf::from(path)
+}
+
+/// Writes a checksum file in the format `sha256sum --check` understands.
+///
+/// The digest always covers the raw device data, so for compressed images it names the
+/// decompressed file.
+pub fn write_checksum(image: &Path, digest: &[u8]) -> Result<(), Error> {
+    let name = match Compression::for_path(image) {
+        Compression::None => image.file_name(),
+        _ => image.file_stem(),
+    };
+    let name = name
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let hex: String = digest.iter().map(|byte| format!("{byte:02x}")).collect();
+    std::fs::write(checksum_path(image), format!("{hex}  {name}\n"))?;
+    Ok(())
+}
+
+/// Copies a device into an image file, reporting progress along the way.
+///
+/// With `sparse` set, all zero blocks of 4 KiB are skipped instead of written so the file system
+/// can leave holes in their place. Returns the SHA-256 digest of the whole device.
+pub fn create(
+    device: &mut File,
+    image: &mut File,
+    total: u64,
+    sparse: bool,
+    reporter: &mut Reporter,
+) -> Result<Vec<u8>, Error> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut done = 0;
+    while done < total {
+        reporter.check_cancelled()?;
+        let len = (total - done).min(CHUNK_SIZE as u64) as usize;
+        let read = read_full(device, &mut buf[..len])?;
+        if read == 0 {
+            break;
+        }
+        let chunk = &buf[..read];
+        hasher.update(chunk);
+        if sparse {
+            write_sparse(image, chunk)?;
+        } else {
+            image.write_all(chunk)?;
+        }
+        done += read as u64;
+        reporter.update(done);
+    }
+    // A trailing hole is only materialized by setting the length
+    image.set_len(done)?;
+    image.sync_all()?;
+    Ok(hasher.finalize().to_vec())
+}
+
+/// Writes a chunk, seeking over its all zero blocks so they become holes.
+fn write_sparse(image: &mut File, chunk: &[u8]) -> std::io::Result<()> {
+    let is_zero = |start: usize| {
+        chunk[start..(start + SPARSE_BLOCK).min(chunk.len())]
+            .iter()
+            .all(|byte| *byte == 0)
+    };
+    let mut start = 0;
+    while start < chunk.len() {
+        // Runs of alike blocks are written or skipped at once
+        let zero = is_zero(start);
+        let mut end = start + SPARSE_BLOCK;
+        while end < chunk.len() && is_zero(end) == zero {
+            end += SPARSE_BLOCK;
+        }
+        let end = end.min(chunk.len());
+        if zero {
+            image.seek(SeekFrom::Current((end - start) as i64))?;
+        } else {
+            image.write_all(&chunk[start..end])?;
+        }
+        start = end;
+    }
+    Ok(())
+}
+
+/// Streams a device through a compressor into an image file.
+///
+/// Returns the SHA-256 digest of the whole device.
+pub fn compress(
+    device: &mut File,
+    mut image: File,
+    total: u64,
+    compression: Compression,
+    reporter: &mut Reporter,
+) -> Result<Vec<u8>, Error> {
+    let (digest, image) = match compression {
+        Compression::None => return create(device, &mut image, total, false, reporter),
+        Compression::Zstd => {
+            let mut encoder = zstd::stream::write::Encoder::new(image, 3)?;
+            // Records the size in the frame header so restores can check it up front
+            encoder.include_contentsize(true)?;
+            encoder.set_pledged_src_size(Some(total))?;
+            let digest = copy(device, &mut encoder, total, reporter)?;
+            (digest, encoder.finish()?)
+        }
+        Compression::Xz => {
+            let mut encoder = xz2::write::XzEncoder::new(image, 6);
+            let digest = copy(device, &mut encoder, total, reporter)?;
+            (digest, encoder.finish()?)
+        }
+        Compression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(image, flate2::Compression::default());
+            let digest = copy(device, &mut encoder, total, reporter)?;
+            (digest, encoder.finish()?)
+        }
+    };
+    image.sync_all()?;
+    Ok(digest)
+}
+
+fn copy(
+    device: &mut File,
+    writer: &mut impl Write,
+    total: u64,
+    reporter: &mut Reporter,
+) -> Result<Vec<u8>, Error> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut done = 0;
+    while done < total {
+        reporter.check_cancelled()?;
+        let len = (total - done).min(CHUNK_SIZE as u64) as usize;
+        let read = read_full(device, &mut buf[..len])?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+        writer.write_all(&buf[..read])?;
+        done += read as u64;
+        reporter.update(done);
+    }
+    Ok(hasher.finalize().to_vec())
+}
+
+/// Reads the digest from a checksum file next to the image, if there is one.
+pub fn read_checksum(image: &Path) -> Option<Vec<u8>> {
+    let contents = std::fs::read_to_string(checksum_path(image)).ok()?;
+    let hex = contents.split_whitespace().next()?;
+    if hex.len() != 64 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&hex[index..index + 2], 16).ok())
+        .collect()
+}
+
+/// Reads a whole image, returning the SHA-256 digest of its data.
+///
+/// Used to check an image against its checksum file before anything is written, it fails as
+/// soon as the image turns out to be larger than `capacity`.
+pub fn checksum(
+    image: &mut impl Read,
+    capacity: u64,
+    reporter: &mut Reporter,
+) -> Result<Vec<u8>, Error> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut done = 0;
+    loop {
+        reporter.check_cancelled()?;
+        let read = read_full(image, &mut buf)?;
+        if read == 0 {
+            break;
+        }
+        done += read as u64;
+        if done > capacity {
+            return Err(Error::new(
+                "The image is larger than the target device",
+                true,
+            ));
+        }
+        hasher.update(&buf[..read]);
+        reporter.update(done);
+    }
+    Ok(hasher.finalize().to_vec())
+}
+
+/// Writes an image onto a device of `capacity` bytes.
+///
+/// Returns the SHA-256 digest of the written data along with its length.
+pub fn restore(
+    image: &mut impl Read,
+    device: &mut File,
+    capacity: u64,
+    reporter: &mut Reporter,
+) -> Result<(Vec<u8>, u64), Error> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut done = 0;
+    loop {
+        reporter.check_cancelled()?;
+        let read = read_full(image, &mut buf)?;
+        if read == 0 {
+            break;
+        }
+        if done + read as u64 > capacity {
+            return Err(Error::new(
+                "The image is larger than the target device",
+                true,
+            ));
+        }
+        hasher.update(&buf[..read]);
+        device.write_all(&buf[..read])?;
+        done += read as u64;
+        reporter.update(done);
+    }
+    device.sync_all()?;
+    Ok((hasher.finalize().to_vec(), done))
+}
+
+/// Reads the first `total` bytes of a device back, returning their SHA-256 digest.
+pub fn digest(
+    device: &mut impl Read,
+    total: u64,
+    reporter: &mut Reporter,
+) -> Result<Vec<u8>, Error> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut done = 0;
+    while done < total {
+        reporter.check_cancelled()?;
+        let len = (total - done).min(CHUNK_SIZE as u64) as usize;
+        let read = read_full(device, &mut buf[..len])?;
+        if read == 0 {
+            return Err(Error::new(
+                "The device ended before the whole image was read back",
+                true,
+            ));
+        }
+        hasher.update(&buf[..read]);
+        done += read as u64;
+        reporter.update(done);
+    }
+    Ok(hasher.finalize().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::MetadataExt;
+
+    use super::*;
+
+    /// A temporary file, removed again when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, data: &[u8]) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("andromeda-image-{}-{name}", std::process::id()));
+            std::fs::write(&path, data).unwrap();
+            Self(path)
+        }
+
+        fn open(&self) -> File {
+            std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&self.0)
+                .unwrap()
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// Data blocks and long zero runs, spanning more than one chunk and ending in zeros.
+    fn device_data() -> Vec<u8> {
+        let mut data = vec![0; CHUNK_SIZE + 3 * 1024 * 1024];
+        for (index, byte) in data[..3 * SPARSE_BLOCK].iter_mut().enumerate() {
+            *byte = (index % 251) as u8 + 1;
+        }
+        data[CHUNK_SIZE - 10..CHUNK_SIZE + 10].fill(0xaa);
+        data[CHUNK_SIZE + 2 * 1024 * 1024 + 1] = 1;
+        data
+    }
+
+    fn create_image(name: &str, data: &[u8], sparse: bool) -> (TempFile, Vec<u8>) {
+        let device = TempFile::new(&format!("{name}-device"), data);
+        let image = TempFile::new(name, &[]);
+        let digest = create(
+            &mut device.open(),
+            &mut image.open(),
+            data.len() as u64,
+            sparse,
+            &mut Reporter::detached(),
+        )
+        .unwrap();
+        (image, digest)
+    }
+
+    #[test]
+    fn sparse_images_match_the_device() {
+        let data = device_data();
+        let (image, digest) = create_image("identical", &data, true);
+        assert_eq!(std::fs::read(&image.0).unwrap(), data);
+        assert_eq!(digest, Sha256::digest(&data).to_vec());
+    }
+
+    #[test]
+    fn sparse_images_have_holes() {
+        let data = device_data();
+        let (dense, _) = create_image("dense", &data, false);
+        let (sparse, _) = create_image("sparse", &data, true);
+        let dense = std::fs::metadata(&dense.0).unwrap();
+        let sparse = std::fs::metadata(&sparse.0).unwrap();
+        assert_eq!(sparse.len(), dense.len());
+        // Only the few data blocks are allocated
+        assert!(sparse.blocks() * 512 < 1024 * 1024, "{}", sparse.blocks());
+        assert!(sparse.blocks() < dense.blocks());
+    }
+
+    #[test]
+    fn trailing_zeros_keep_the_length() {
+        let mut data = vec![7; SPARSE_BLOCK + 100];
+        data.extend(vec![0; 2 * SPARSE_BLOCK + 5]);
+        let (image, _) = create_image("trailing", &data, true);
+        assert_eq!(
+            std::fs::metadata(&image.0).unwrap().len(),
+            data.len() as u64
+        );
+        assert_eq!(std::fs::read(&image.0).unwrap(), data);
+    }
+
+    fn xz(data: &[u8]) -> Vec<u8> {
+        let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 1);
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn size_of(name: &str, contents: &[u8]) -> Option<ImageSize> {
+        let file = TempFile::new(name, contents);
+        uncompressed_size(&file.0)
+    }
+
+    #[test]
+    fn xz_sizes() {
+        let single = xz(&[1; 100_000]);
+        assert_eq!(
+            size_of("single.xz", &single),
+            Some(ImageSize::Exact(100_000))
+        );
+
+        let mut multiple = single.clone();
+        multiple.extend(xz(b"more data"));
+        multiple.extend(xz(&[]));
+        assert_eq!(
+            size_of("multiple.xz", &multiple),
+            Some(ImageSize::Exact(100_009))
+        );
+
+        // Padding comes in multiples of four zero bytes, after and between streams
+        let mut padded = single.clone();
+        padded.extend([0; 8]);
+        padded.extend(xz(b"more data"));
+        padded.extend([0; 4]);
+        assert_eq!(
+            size_of("padded.xz", &padded),
+            Some(ImageSize::Exact(100_009))
+        );
+    }
+
+    #[test]
+    fn broken_xz_sizes() {
+        let single = xz(&[1; 100_000]);
+        assert_eq!(size_of("truncated.xz", &single[..single.len() - 5]), None);
+        assert_eq!(size_of("short.xz", &single[single.len() - 8..]), None);
+
+        let mut magic = single.clone();
+        let len = magic.len();
+        magic[len - 1] = b'X';
+        assert_eq!(size_of("magic.xz", &magic), None);
+
+        // A backward size pointing in front of the file
+        let mut backward = single.clone();
+        backward[len - 8..len - 4].copy_from_slice(&0x10000u32.to_le_bytes());
+        assert_eq!(size_of("backward.xz", &backward), None);
+
+        // An index too large to be read, though the file would hold it
+        let mut huge = vec![0xfd; XZ_INDEX_LIMIT as usize + 64];
+        let len = huge.len();
+        huge[len - 8..len - 4].copy_from_slice(&((XZ_INDEX_LIMIT / 4) as u32).to_le_bytes());
+        huge[len - 2..].copy_from_slice(b"YZ");
+        assert_eq!(size_of("huge.xz", &huge), None);
+
+        assert_eq!(size_of("padding.xz", &[0; 64]), None);
+    }
+
+    #[test]
+    fn zstd_sizes() {
+        let mut encoder = zstd::stream::write::Encoder::new(Vec::new(), 3).unwrap();
+        encoder.include_contentsize(true).unwrap();
+        encoder.set_pledged_src_size(Some(50_000)).unwrap();
+        encoder.write_all(&[2; 50_000]).unwrap();
+        let mut frames = encoder.finish().unwrap();
+        assert_eq!(
+            size_of("single.zst", &frames),
+            Some(ImageSize::AtLeast(50_000))
+        );
+        // Later frames are not looked at
+        frames.extend(zstd::encode_all(&[3; 1000][..], 3).unwrap());
+        assert_eq!(
+            size_of("multiple.zst", &frames),
+            Some(ImageSize::AtLeast(50_000))
+        );
+
+        // Streamed frames do not record their size
+        let mut encoder = zstd::stream::write::Encoder::new(Vec::new(), 3).unwrap();
+        encoder.include_contentsize(false).unwrap();
+        encoder.write_all(&[2; 50_000]).unwrap();
+        assert_eq!(size_of("unknown.zst", &encoder.finish().unwrap()), None);
+
+        assert_eq!(
+            size_of("plain.img", &[0; 1234]),
+            Some(ImageSize::Exact(1234))
+        );
+        assert_eq!(size_of("plain.gz", &[0; 1234]), None);
+    }
+
+    #[test]
+    fn compression_for_path() {
+        for (path, compression) in [
+            ("disk.img", Compression::None),
+            ("disk", Compression::None),
+            ("disk.img.zst", Compression::Zstd),
+            ("disk.img.XZ", Compression::Xz),
+            ("/backups/disk.gz", Compression::Gzip),
+            ("disk.xz.img", Compression::None),
+        ] {
+            assert_eq!(
+                Compression::for_path(Path::new(path)),
+                compression,
+                "{path}"
+            );
+        }
+    }
+
+    #[test]
+    fn compression_extension() {
+        for (compression, path, expected) in [
+            (Compression::None, "disk.img", "disk.img"),
+            (Compression::None, "disk.img.xz", "disk.img.xz"),
+            (Compression::Xz, "disk.img", "disk.img.xz"),
+            (Compression::Xz, "disk.img.xz", "disk.img.xz"),
+            (Compression::Gzip, "disk.img.GZ", "disk.img.GZ"),
+            (Compression::Zstd, "disk.img.xz", "disk.img.xz.zst"),
+            (Compression::Zstd, "disk", "disk.zst"),
+        ] {
+            assert_eq!(
+                compression.with_extension(PathBuf::from(path)),
+                PathBuf::from(expected),
+                "{compression:?} {path}"
+            );
+        }
+    }
+}
@@ -0,0 +1,562 @@
+//! Partition tables of raw image files, read and written in-process without udisks.
+//!
+//! Everything works on any `Read + Write + Seek`, so a temporary file or an in-memory cursor
+//! stands in for a real image just as well.
+
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+
+use super::gpt::{
+    crc32, random_bytes, random_guid, read_at, u32_at, u64_at, write_at, GPT_SIGNATURE,
+    MBR_SIGNATURE,
+};
+
+/// Image files are always addressed in 512 byte sectors.
+pub const SECTOR_SIZE: u64 = 512;
+/// New partitions start on 1 MiB boundaries, like partitioning tools place them.
+const ALIGNMENT: u64 = 1024 * 1024;
+
+const GPT_ENTRY_COUNT: usize = 128;
+const GPT_ENTRY_SIZE: usize = 128;
+/// Sectors taken up by the partition entries on each end of the image
+const GPT_ENTRY_SECTORS: u64 = (GPT_ENTRY_COUNT * GPT_ENTRY_SIZE) as u64 / SECTOR_SIZE;
+const GPT_LINUX_DATA: &str = "0fc63daf-8483-4772-8e79-3d69d8477de4";
+
+const MBR_ENTRIES: usize = 446;
+const MBR_LINUX: u8 = 0x83;
+const MBR_PROTECTIVE: u8 = 0xee;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TableKind {
+    Gpt,
+    Mbr,
+}
+
+impl TableKind {
+    /// Name of the table type as udisks reports it.
+    pub fn udisks_type(self) -> &'static str {
+        match self {
+            Self::Gpt => "gpt",
+            Self::Mbr => "dos",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub number: u32,
+    pub offset: u64,
+    pub size: u64,
+    /// Type GUID for GPT, hexadecimal type byte for MBR
+    pub type_id: String,
+    pub name: String,
+    pub uuid: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct Table {
+    pub kind: TableKind,
+    pub entries: Vec<Entry>,
+}
+
+/// The on-disk structures that get edited and written back.
+enum Raw {
+    Gpt {
+        disk_guid: [u8; 16],
+        entries: Vec<u8>,
+    },
+    Mbr(Box<[u8; 512]>),
+}
+
+fn guid_to_string(guid: &[u8]) -> String {
+    let hex = |bytes: &[u8]| -> String { bytes.iter().map(|byte| format!("{byte:02x}")).collect() };
+    let reversed = |bytes: &[u8]| -> Vec<u8> { bytes.iter().rev().copied().collect() };
+    // The first three fields are stored little endian
+    format!(
+        "{}-{}-{}-{}-{}",
+        hex(&reversed(&guid[..4])),
+        hex(&reversed(&guid[4..6])),
+        hex(&reversed(&guid[6..8])),
+        hex(&guid[8..10]),
+        hex(&guid[10..16])
+    )
+}
+
+fn guid_from_str(guid: &str) -> Option<[u8; 16]> {
+    let hex: String = guid.chars().filter(|char| *char != '-').collect();
+    if hex.len() != 32 {
+        return None;
+    }
+    let mut bytes = [0; 16];
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(index * 2..index * 2 + 2)?, 16).ok()?;
+    }
+    bytes[..4].reverse();
+    bytes[4..6].reverse();
+    bytes[6..8].reverse();
+    Some(bytes)
+}
+
+fn device_size(device: &mut impl Seek) -> Result<u64> {
+    device.seek(SeekFrom::End(0))
+}
+
+fn load(device: &mut (impl Read + Seek)) -> Result<Option<Raw>> {
+    if device_size(device)? < 2 * SECTOR_SIZE {
+        return Ok(None);
+    }
+
+    let mut header = [0; SECTOR_SIZE as usize];
+    read_at(device, SECTOR_SIZE, &mut header)?;
+    if &header[..8] == GPT_SIGNATURE {
+        if u64_at(&header, 72) != 2
+            || u32_at(&header, 80) as usize != GPT_ENTRY_COUNT
+            || u32_at(&header, 84) as usize != GPT_ENTRY_SIZE
+        {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "The GUID partition table uses a layout that can not be edited offline",
+            ));
+        }
+        let mut entries = vec![0; GPT_ENTRY_COUNT * GPT_ENTRY_SIZE];
+        read_at(device, 2 * SECTOR_SIZE, &mut entries)?;
+        return Ok(Some(Raw::Gpt {
+            disk_guid: header[56..72].try_into().unwrap(),
+            entries,
+        }));
+    }
+
+    let mut mbr = [0; 512];
+    read_at(device, 0, &mut mbr)?;
+    Ok((mbr[510..] == MBR_SIGNATURE).then_some(Raw::Mbr(Box::new(mbr))))
+}
+
+fn store(device: &mut (impl Write + Seek), size: u64, raw: &Raw) -> Result<()> {
+    match raw {
+        Raw::Gpt { disk_guid, entries } => {
+            let last_lba = size / SECTOR_SIZE - 1;
+            let backup_entries_lba = last_lba - GPT_ENTRY_SECTORS;
+
+            let mut header = [0; SECTOR_SIZE as usize];
+            header[..8].copy_from_slice(GPT_SIGNATURE);
+            header[8..12].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+            header[12..16].copy_from_slice(&92u32.to_le_bytes());
+            header[40..48].copy_from_slice(&(2 + GPT_ENTRY_SECTORS).to_le_bytes());
+            header[48..56].copy_from_slice(&(backup_entries_lba - 1).to_le_bytes());
+            header[56..72].copy_from_slice(disk_guid);
+            header[80..84].copy_from_slice(&(GPT_ENTRY_COUNT as u32).to_le_bytes());
+            header[84..88].copy_from_slice(&(GPT_ENTRY_SIZE as u32).to_le_bytes());
+            header[88..92].copy_from_slice(&crc32(entries).to_le_bytes());
+
+            let mut write_header =
+                |device: &mut _, my_lba: u64, alternate_lba: u64, entries_lba: u64| {
+                    header[16..20].fill(0);
+                    header[24..32].copy_from_slice(&my_lba.to_le_bytes());
+                    header[32..40].copy_from_slice(&alternate_lba.to_le_bytes());
+                    header[72..80].copy_from_slice(&entries_lba.to_le_bytes());
+                    let crc = crc32(&header[..92]);
+                    header[16..20].copy_from_slice(&crc.to_le_bytes());
+                    write_at(device, my_lba * SECTOR_SIZE, &header)
+                };
+            write_at(device, 2 * SECTOR_SIZE, entries)?;
+            write_header(device, 1, last_lba, 2)?;
+            write_at(device, backup_entries_lba * SECTOR_SIZE, entries)?;
+            write_header(device, last_lba, 1, backup_entries_lba)?;
+
+            // Protective MBR, keeping any boot code in front of it
+            let mut entry = [0; 66];
+            entry[..16].copy_from_slice(&[
+                0x00,
+                0x00,
+                0x02,
+                0x00,
+                MBR_PROTECTIVE,
+                0xff,
+                0xff,
+                0xff,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+            ]);
+            entry[8..12].copy_from_slice(&1u32.to_le_bytes());
+            entry[12..16].copy_from_slice(&(last_lba.min(u32::MAX as u64) as u32).to_le_bytes());
+            entry[64..].copy_from_slice(&MBR_SIGNATURE);
+            write_at(device, MBR_ENTRIES as u64, &entry)?;
+        }
+        Raw::Mbr(mbr) => write_at(device, 0, &mbr[..])?,
+    }
+    device.flush()
+}
+
+fn parse(raw: &Raw) -> Table {
+    match raw {
+        Raw::Gpt { entries, .. } => Table {
+            kind: TableKind::Gpt,
+            entries: entries
+                .chunks(GPT_ENTRY_SIZE)
+                .enumerate()
+                .filter(|(_, entry)| entry[..16].iter().any(|byte| *byte != 0))
+                .map(|(index, entry)| {
+                    let first = u64_at(entry, 32);
+                    let last = u64_at(entry, 40);
+                    let name: Vec<u16> = entry[56..128]
+                        .chunks(2)
+                        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                        .take_while(|unit| *unit != 0)
+                        .collect();
+                    Entry {
+                        number: index as u32 + 1,
+                        offset: first * SECTOR_SIZE,
+                        size: (last.saturating_sub(first) + 1) * SECTOR_SIZE,
+                        type_id: guid_to_string(&entry[..16]),
+                        name: String::from_utf16_lossy(&name),
+                        uuid: guid_to_string(&entry[16..32]),
+                    }
+                })
+                .collect(),
+        },
+        Raw::Mbr(mbr) => Table {
+            kind: TableKind::Mbr,
+            entries: mbr[MBR_ENTRIES..510]
+                .chunks(16)
+                .enumerate()
+                .filter(|(_, entry)| entry[4] != 0)
+                .map(|(index, entry)| Entry {
+                    number: index as u32 + 1,
+                    offset: u32_at(entry, 8) as u64 * SECTOR_SIZE,
+                    size: u32_at(entry, 12) as u64 * SECTOR_SIZE,
+                    type_id: format!("0x{:02x}", entry[4]),
+                    name: String::new(),
+                    uuid: String::new(),
+                })
+                .collect(),
+        },
+    }
+}
+
+/// Reads the partition table of an image, `None` when it has none.
+pub fn read(device: &mut (impl Read + Seek)) -> Result<Option<Table>> {
+    Ok(load(device)?.as_ref().map(parse))
+}
+
+/// Replaces whatever partition table the image has with an empty one, or none at all.
+pub fn create(device: &mut (impl Read + Write + Seek), kind: Option<TableKind>) -> Result<()> {
+    let size = device_size(device)?;
+    let reserved = (2 + GPT_ENTRY_SECTORS) * SECTOR_SIZE;
+    if size < 2 * reserved {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The image is too small for a partition table",
+        ));
+    }
+    // Clear old signatures on both ends, the boot code area is left alone
+    write_at(device, MBR_ENTRIES as u64, &[0; 66])?;
+    write_at(
+        device,
+        SECTOR_SIZE,
+        &vec![0; (reserved - SECTOR_SIZE) as usize],
+    )?;
+    write_at(device, size - reserved, &vec![0; reserved as usize])?;
+
+    let raw = match kind {
+        Some(TableKind::Gpt) => Raw::Gpt {
+            disk_guid: random_guid()?,
+            entries: vec![0; GPT_ENTRY_COUNT * GPT_ENTRY_SIZE],
+        },
+        Some(TableKind::Mbr) => {
+            let mut mbr = [0; 512];
+            read_at(device, 0, &mut mbr)?;
+            mbr[440..444].copy_from_slice(&random_bytes::<4>()?);
+            mbr[510..].copy_from_slice(&MBR_SIGNATURE);
+            Raw::Mbr(Box::new(mbr))
+        }
+        None => return device.flush(),
+    };
+    store(device, size, &raw)
+}
+
+/// Adds a partition in free space, aligned to 1 MiB and shrunk to fit where needed.
+pub fn add_partition(
+    device: &mut (impl Read + Write + Seek),
+    offset: u64,
+    size: u64,
+) -> Result<()> {
+    let image_size = device_size(device)?;
+    let Some(mut raw) = load(device)? else {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The image has no partition table",
+        ));
+    };
+    let table = parse(&raw);
+
+    let (first_usable, last_usable) = match raw {
+        Raw::Gpt { .. } => (
+            (2 + GPT_ENTRY_SECTORS) * SECTOR_SIZE,
+            image_size - (GPT_ENTRY_SECTORS + 1) * SECTOR_SIZE,
+        ),
+        Raw::Mbr(_) => (SECTOR_SIZE, image_size.min((u32::MAX as u64) * SECTOR_SIZE)),
+    };
+    let start = offset.max(first_usable).next_multiple_of(ALIGNMENT);
+    let mut end = (offset + size).min(last_usable);
+    // Partitions never overlap, stop at the next one
+    for entry in &table.entries {
+        if entry.offset + entry.size > start && entry.offset < end {
+            if entry.offset <= start {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "The space is already taken by another partition",
+                ));
+            }
+            end = entry.offset;
+        }
+    }
+    let end = end / SECTOR_SIZE * SECTOR_SIZE;
+    if end <= start {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The partition does not fit into the free space",
+        ));
+    }
+    let (first, last) = (start / SECTOR_SIZE, end / SECTOR_SIZE - 1);
+
+    match &mut raw {
+        Raw::Gpt { entries, .. } => {
+            let Some(entry) = entries
+                .chunks_mut(GPT_ENTRY_SIZE)
+                .find(|entry| entry[..16].iter().all(|byte| *byte == 0))
+            else {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "All partition entries are in use",
+                ));
+            };
+            entry.fill(0);
+            entry[..16].copy_from_slice(&guid_from_str(GPT_LINUX_DATA).unwrap());
+            entry[16..32].copy_from_slice(&random_guid()?);
+            entry[32..40].copy_from_slice(&first.to_le_bytes());
+            entry[40..48].copy_from_slice(&last.to_le_bytes());
+        }
+        Raw::Mbr(mbr) => {
+            let Some(entry) = mbr[MBR_ENTRIES..510]
+                .chunks_mut(16)
+                .find(|entry| entry[4] == 0)
+            else {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "A master boot record holds no more than four partitions",
+                ));
+            };
+            // Addressed by LBA only, the CHS fields are set to their maximum
+            entry.copy_from_slice(&[
+                0x00, 0xfe, 0xff, 0xff, MBR_LINUX, 0xfe, 0xff, 0xff, 0, 0, 0, 0, 0, 0, 0, 0,
+            ]);
+            entry[8..12].copy_from_slice(&(first as u32).to_le_bytes());
+            entry[12..16].copy_from_slice(&((last - first + 1) as u32).to_le_bytes());
+        }
+    }
+    store(device, image_size, &raw)
+}
+
+/// Deletes the partition starting at `offset`, its data is left in place.
+pub fn delete_partition(device: &mut (impl Read + Write + Seek), offset: u64) -> Result<()> {
+    let image_size = device_size(device)?;
+    let Some(mut raw) = load(device)? else {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The image has no partition table",
+        ));
+    };
+    let Some(number) = parse(&raw)
+        .entries
+        .iter()
+        .find(|entry| entry.offset == offset)
+        .map(|entry| entry.number as usize)
+    else {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            "There is no partition at this offset",
+        ));
+    };
+    match &mut raw {
+        Raw::Gpt { entries, .. } => {
+            entries[(number - 1) * GPT_ENTRY_SIZE..number * GPT_ENTRY_SIZE].fill(0)
+        }
+        Raw::Mbr(mbr) => mbr[MBR_ENTRIES + (number - 1) * 16..MBR_ENTRIES + number * 16].fill(0),
+    }
+    store(device, image_size, &raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn new_image(kind: Option<TableKind>) -> Cursor<Vec<u8>> {
+        let mut image = Cursor::new(vec![0; 16 * MIB as usize]);
+        create(&mut image, kind).unwrap();
+        image
+    }
+
+    #[test]
+    fn create_empty_tables() {
+        for kind in [TableKind::Gpt, TableKind::Mbr] {
+            let table = read(&mut new_image(Some(kind))).unwrap().unwrap();
+            assert_eq!(table.kind, kind);
+            assert!(table.entries.is_empty());
+        }
+        assert!(read(&mut new_image(None)).unwrap().is_none());
+    }
+
+    #[test]
+    fn create_replaces_table() {
+        let mut image = new_image(Some(TableKind::Gpt));
+        create(&mut image, Some(TableKind::Mbr)).unwrap();
+        assert_eq!(read(&mut image).unwrap().unwrap().kind, TableKind::Mbr);
+        create(&mut image, None).unwrap();
+        assert!(read(&mut image).unwrap().is_none());
+    }
+
+    #[test]
+    fn create_too_small() {
+        let mut image = Cursor::new(vec![0; 16 * 1024]);
+        assert!(create(&mut image, Some(TableKind::Gpt)).is_err());
+    }
+
+    #[test]
+    fn gpt_add_and_delete() {
+        let mut image = new_image(Some(TableKind::Gpt));
+        add_partition(&mut image, MIB, 4 * MIB).unwrap();
+        add_partition(&mut image, 8 * MIB, 2 * MIB).unwrap();
+
+        let entries = read(&mut image).unwrap().unwrap().entries;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            (entries[0].number, entries[0].offset, entries[0].size),
+            (1, MIB, 4 * MIB)
+        );
+        assert_eq!(entries[0].type_id, GPT_LINUX_DATA);
+        assert_ne!(entries[0].uuid, entries[1].uuid);
+        assert_eq!((entries[1].number, entries[1].offset), (2, 8 * MIB));
+
+        delete_partition(&mut image, MIB).unwrap();
+        let entries = read(&mut image).unwrap().unwrap().entries;
+        assert_eq!(entries.len(), 1);
+        assert_eq!((entries[0].number, entries[0].offset), (2, 8 * MIB));
+
+        // The freed entry is the first one to be used again
+        add_partition(&mut image, MIB, MIB).unwrap();
+        let entries = read(&mut image).unwrap().unwrap().entries;
+        assert_eq!((entries[0].number, entries[0].offset), (1, MIB));
+    }
+
+    #[test]
+    fn mbr_add_and_delete() {
+        let mut image = new_image(Some(TableKind::Mbr));
+        for index in 0..4 {
+            add_partition(&mut image, (1 + 3 * index) * MIB, 2 * MIB).unwrap();
+        }
+        assert!(add_partition(&mut image, 14 * MIB, MIB).is_err());
+
+        let entries = read(&mut image).unwrap().unwrap().entries;
+        assert_eq!(entries.len(), 4);
+        assert_eq!((entries[3].offset, entries[3].size), (10 * MIB, 2 * MIB));
+        assert_eq!(entries[3].type_id, "0x83");
+
+        delete_partition(&mut image, 4 * MIB).unwrap();
+        let offsets: Vec<_> = read(&mut image)
+            .unwrap()
+            .unwrap()
+            .entries
+            .iter()
+            .map(|entry| entry.offset)
+            .collect();
+        assert_eq!(offsets, [MIB, 7 * MIB, 10 * MIB]);
+    }
+
+    #[test]
+    fn add_aligns_and_fits() {
+        let mut image = new_image(Some(TableKind::Gpt));
+        add_partition(&mut image, 8 * MIB, 2 * MIB).unwrap();
+        // Starts at the next boundary and stops at the following partition
+        add_partition(&mut image, MIB + 512, 16 * MIB).unwrap();
+        let entries = read(&mut image).unwrap().unwrap().entries;
+        assert_eq!((entries[1].offset, entries[1].size), (2 * MIB, 6 * MIB));
+
+        // The last one ends in front of the backup entries
+        add_partition(&mut image, 10 * MIB, 16 * MIB).unwrap();
+        let entries = read(&mut image).unwrap().unwrap().entries;
+        assert_eq!(
+            entries[2].offset + entries[2].size,
+            16 * MIB - (GPT_ENTRY_SECTORS + 1) * SECTOR_SIZE
+        );
+    }
+
+    #[test]
+    fn add_rejects_overlap() {
+        let mut image = new_image(Some(TableKind::Gpt));
+        add_partition(&mut image, MIB, 4 * MIB).unwrap();
+        let err = add_partition(&mut image, 2 * MIB, MIB).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(read(&mut image).unwrap().unwrap().entries.len(), 1);
+    }
+
+    #[test]
+    fn delete_missing() {
+        let mut image = new_image(Some(TableKind::Gpt));
+        let err = delete_partition(&mut image, MIB).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+
+        let mut image = new_image(None);
+        assert!(add_partition(&mut image, MIB, MIB).is_err());
+        assert!(delete_partition(&mut image, MIB).is_err());
+    }
+
+    #[test]
+    fn gpt_backup_header() {
+        let mut image = new_image(Some(TableKind::Gpt));
+        add_partition(&mut image, MIB, 4 * MIB).unwrap();
+        let data = image.into_inner();
+        let last_lba = data.len() as u64 / SECTOR_SIZE - 1;
+
+        let sector = |lba: u64| &data[(lba * SECTOR_SIZE) as usize..][..SECTOR_SIZE as usize];
+        let primary = sector(1);
+        let backup = sector(last_lba);
+        assert_eq!(&backup[..8], GPT_SIGNATURE);
+        assert_eq!(u64_at(backup, 24), last_lba);
+        assert_eq!(u64_at(backup, 32), 1);
+        assert_eq!(u64_at(primary, 32), last_lba);
+        assert_eq!(backup[56..72], primary[56..72]);
+
+        // Both headers carry valid checksums
+        for header in [primary, backup] {
+            let mut copy = header[..92].to_vec();
+            copy[16..20].fill(0);
+            assert_eq!(crc32(&copy), u32_at(header, 16));
+        }
+
+        // The backup entries are a copy of the primary ones
+        let entries_size = GPT_ENTRY_COUNT * GPT_ENTRY_SIZE;
+        let backup_entries = (u64_at(backup, 72) * SECTOR_SIZE) as usize;
+        assert_eq!(u64_at(backup, 72), last_lba - GPT_ENTRY_SECTORS);
+        assert_eq!(
+            data[backup_entries..][..entries_size],
+            data[2 * SECTOR_SIZE as usize..][..entries_size]
+        );
+        assert_eq!(
+            crc32(&data[backup_entries..][..entries_size]),
+            u32_at(backup, 88)
+        );
+
+        // A protective MBR covers the whole image
+        assert_eq!(data[MBR_ENTRIES + 4], MBR_PROTECTIVE);
+        assert_eq!(data[510..512], MBR_SIGNATURE);
+    }
+}
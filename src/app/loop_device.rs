@@ -0,0 +1,77 @@
+use std::fs::OpenOptions;
+use std::os::fd::AsFd;
+use std::path::PathBuf;
+
+use udisks2::{
+    zbus::zvariant::{OwnedObjectPath, Value},
+    Client,
+};
+
+use super::error::Error;
+
+/// How an image file is attached as a loop device.
+#[derive(Clone, Debug)]
+pub struct AttachOptions {
+    pub path: PathBuf,
+    pub read_only: bool,
+    /// Bytes to skip at the start of the file
+    pub offset: u64,
+    /// Bytes of the file to expose, everything after the offset when zero
+    pub size: u64,
+    /// Detach the loop device automatically once it is no longer in use
+    pub autoclear: bool,
+}
+
+impl AttachOptions {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            read_only: true,
+            offset: 0,
+            size: 0,
+            autoclear: false,
+        }
+    }
+}
+
+/// Sets up a loop device for an image file, returning the path of its block object.
+pub async fn attach(client: &Client, options: AttachOptions) -> Result<OwnedObjectPath, Error> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(!options.read_only)
+        .open(&options.path)?;
+
+    let mut setup_options = udisks2::standard_options(false);
+    setup_options.insert("read-only", Value::from(options.read_only));
+    if options.offset > 0 {
+        setup_options.insert("offset", Value::from(options.offset));
+    }
+    if options.size > 0 {
+        setup_options.insert("size", Value::from(options.size));
+    }
+    let block_path = client
+        .manager()
+        .loop_setup(file.as_fd().into(), setup_options)
+        .await?;
+
+    if options.autoclear {
+        client
+            .object(block_path.clone())
+            .unwrap()
+            .r#loop()
+            .await?
+            .set_autoclear(true, udisks2::standard_options(false))
+            .await?;
+    }
+    Ok(block_path)
+}
+
+/// Whether a block device is a loop device without an image attached.
+pub async fn is_unused(client: &Client, block_path: OwnedObjectPath) -> bool {
+    match client.object(block_path).unwrap().r#loop().await {
+        Ok(r#loop) => r#loop.backing_file().await.map_or(true, |backing_file| {
+            backing_file.iter().all(|byte| *byte == 0)
+        }),
+        Err(_) => false,
+    }
+}
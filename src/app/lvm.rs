@@ -0,0 +1,602 @@
+use std::collections::HashMap;
+
+use cosmic::prelude::*;
+use cosmic::widget::nav_bar::Id;
+use cosmic::{iced, theme, widget};
+
+use udisks2::{
+    block::BlockProxy,
+    filesystem::FilesystemProxy,
+    zbus::zvariant::{ObjectPath, OwnedObjectPath, Value},
+    Client,
+};
+
+use super::operation::{lv_create::LogicalVolumeCreateKind, Operation};
+use super::{error::Error, message::AppMessage};
+use crate::widget::{
+    ring::{section_color, RingSection},
+    Ring,
+};
+
+pub const VOLUME_GROUP_INTERFACE: &str = "org.freedesktop.UDisks2.VolumeGroup";
+
+#[zbus::proxy(
+    interface = "org.freedesktop.UDisks2.VolumeGroup",
+    default_service = "org.freedesktop.UDisks2"
+)]
+pub trait VolumeGroup {
+    fn poll(&self) -> zbus::Result<()>;
+
+    fn create_plain_volume(
+        &self,
+        name: &str,
+        size: u64,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<OwnedObjectPath>;
+
+    fn create_thin_pool_volume(
+        &self,
+        name: &str,
+        size: u64,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<OwnedObjectPath>;
+
+    fn create_thin_volume(
+        &self,
+        name: &str,
+        virtual_size: u64,
+        pool: &ObjectPath<'_>,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn name(&self) -> zbus::Result<String>;
+
+    #[zbus(property, name = "UUID")]
+    fn uuid(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn size(&self) -> zbus::Result<u64>;
+
+    #[zbus(property)]
+    fn free_size(&self) -> zbus::Result<u64>;
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.UDisks2.LogicalVolume",
+    default_service = "org.freedesktop.UDisks2"
+)]
+pub trait LogicalVolume {
+    fn delete(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+
+    fn resize(&self, new_size: u64, options: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+
+    fn create_snapshot(
+        &self,
+        name: &str,
+        size: u64,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn name(&self) -> zbus::Result<String>;
+
+    #[zbus(property, name = "UUID")]
+    fn uuid(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn size(&self) -> zbus::Result<u64>;
+
+    #[zbus(property)]
+    fn data_allocated_ratio(&self) -> zbus::Result<f64>;
+
+    #[zbus(property)]
+    fn active(&self) -> zbus::Result<bool>;
+
+    #[zbus(property, name = "Type")]
+    fn type_(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn thin_pool(&self) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn origin(&self) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn volume_group(&self) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn block_device(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.UDisks2.Block.LVM2",
+    default_service = "org.freedesktop.UDisks2"
+)]
+pub trait BlockLvm2 {
+    #[zbus(property)]
+    fn logical_volume(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+/// Whether LVM accepts a name for a new logical volume.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 127
+        && !name.starts_with('-')
+        && name != "."
+        && name != ".."
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+_.-".contains(c))
+}
+
+/// Enables the udisks lvm2 module and lists the object paths of all volume groups.
+pub async fn volume_groups(client: Client) -> Result<AppMessage, Error> {
+    // Without the module there is nothing to show, this is not an error
+    if client.manager().enable_module("lvm2", true).await.is_err() {
+        return Ok(AppMessage::ReadVolumeGroupsDone(Vec::new()));
+    }
+    let objects = client.object_manager().get_managed_objects().await?;
+    Ok(AppMessage::ReadVolumeGroupsDone(
+        objects
+            .into_iter()
+            .filter(|(_, interfaces)| {
+                interfaces
+                    .keys()
+                    .any(|interface| interface.as_str() == VOLUME_GROUP_INTERFACE)
+            })
+            .map(|(path, _)| path)
+            .collect(),
+    ))
+}
+
+/// Whether the block device backs a logical volume, these are shown inside their volume group.
+pub async fn is_logical_volume(client: &Client, block_path: OwnedObjectPath) -> bool {
+    let proxy =
+        match BlockLvm2Proxy::builder(client.manager().inner().connection()).path(block_path) {
+            Ok(builder) => builder.build().await,
+            Err(err) => Err(err),
+        };
+    match proxy {
+        Ok(proxy) => proxy.logical_volume().await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct VolumeGroup {
+    pub vg: VolumeGroupProxy<'static>,
+    pub vg_path: OwnedObjectPath,
+
+    pub ring: Ring,
+
+    pub name: String,
+    pub uuid: String,
+    pub size: String,
+    pub free: String,
+    pub free_size: u64,
+
+    pub volumes: Vec<LogicalVolume>,
+}
+
+#[derive(Eq, PartialEq, Clone, Copy)]
+enum VolumeGroupAction {
+    CreateVolume(u64),
+    CreateThinPool(u64),
+}
+
+impl widget::menu::Action for VolumeGroupAction {
+    type Message = Result<AppMessage, Error>;
+
+    fn message(&self) -> Self::Message {
+        match self {
+            Self::CreateVolume(max_size) => Ok(AppMessage::OpenOperationDialog(
+                Operation::LogicalVolumeCreate(LogicalVolumeCreateKind::Linear, *max_size),
+            )),
+            Self::CreateThinPool(max_size) => Ok(AppMessage::OpenOperationDialog(
+                Operation::LogicalVolumeCreate(LogicalVolumeCreateKind::ThinPool, *max_size),
+            )),
+        }
+    }
+}
+
+impl VolumeGroup {
+    pub async fn load(
+        client: Client,
+        id: Id,
+        vg_path: OwnedObjectPath,
+    ) -> Result<AppMessage, Error> {
+        let connection = client.manager().inner().connection().clone();
+        let vg = VolumeGroupProxy::builder(&connection)
+            .path(vg_path.clone())?
+            .build()
+            .await?;
+        // LVM state is only refreshed by udisks on demand
+        let _ = vg.poll().await;
+
+        let objects = client.object_manager().get_managed_objects().await?;
+        let mut volumes = Vec::new();
+        for (path, interfaces) in objects {
+            if !interfaces
+                .keys()
+                .any(|interface| interface.as_str() == "org.freedesktop.UDisks2.LogicalVolume")
+            {
+                continue;
+            }
+            let lv = LogicalVolumeProxy::builder(&connection)
+                .path(path.clone())?
+                .build()
+                .await?;
+            if lv.volume_group().await? != vg_path {
+                continue;
+            }
+            volumes.push(LogicalVolume::load(&client, lv, path).await?);
+        }
+        volumes.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let size = vg.size().await?;
+        let free_size = vg.free_size().await?;
+        let ring = Ring {
+            sections: volumes
+                .iter()
+                .enumerate()
+                // Thin volumes live inside their pool and take no space of their own
+                .filter(|(_, volume)| volume.kind != LogicalVolumeKind::Thin)
+                .map(|(index, volume)| RingSection {
+                    color: section_color(index),
+                    size: volume.size as usize,
+                    index,
+                })
+                .chain(std::iter::once(RingSection::free(free_size as usize)))
+                .collect(),
+            line_width: 12.0,
+            selected_par: None,
+        };
+
+        Ok(AppMessage::VolumeGroupRead(
+            id,
+            VolumeGroup {
+                name: vg.name().await?,
+                uuid: vg.uuid().await?,
+                size: client.size_for_display(size, true, false),
+                free: client.size_for_display(free_size, true, false),
+                free_size,
+                ring,
+                volumes,
+                vg,
+                vg_path,
+            },
+        ))
+    }
+
+    pub fn menu_bar(&self) -> Element<Result<AppMessage, Error>> {
+        use widget::menu;
+        menu::bar(vec![
+            menu::Tree::with_children(
+                menu::root("Volume Group"),
+                menu::items(
+                    &HashMap::new(),
+                    vec![
+                        menu::Item::Button(
+                            "Create Logical Volume",
+                            None,
+                            VolumeGroupAction::CreateVolume(self.free_size),
+                        ),
+                        menu::Item::Button(
+                            "Create Thin Pool",
+                            None,
+                            VolumeGroupAction::CreateThinPool(self.free_size),
+                        ),
+                    ],
+                ),
+            ),
+            menu::Tree::with_children(
+                menu::root("Logical Volumes"),
+                menu::items(
+                    &HashMap::new(),
+                    self.volumes
+                        .iter()
+                        .enumerate()
+                        .map(|(index, volume)| volume.menu_folder(index, self.free_size))
+                        .collect(),
+                ),
+            ),
+        ])
+        .apply(Element::from)
+    }
+
+    pub fn view(&self) -> Element<Result<AppMessage, Error>> {
+        let theme = theme::active();
+        let cosmic = theme.cosmic();
+
+        widget::column()
+            .spacing(cosmic.space_s())
+            .push(
+                widget::column()
+                    .spacing(cosmic.space_xs())
+                    .push(widget::text::title3(&self.name))
+                    .push(
+                        widget::flex_row(vec![
+                            widget::canvas(&self.ring)
+                                .width(iced::Length::Fill)
+                                .height(iced::Length::Fixed(250.0))
+                                .apply(Element::from),
+                            widget::container(
+                                widget::settings::section()
+                                    .add(widget::settings::item(
+                                        "Size",
+                                        widget::text::heading(&self.size),
+                                    ))
+                                    .add(widget::settings::item(
+                                        "Free",
+                                        widget::text::body(&self.free),
+                                    ))
+                                    .add(widget::settings::item(
+                                        "UUID",
+                                        widget::text::caption(&self.uuid),
+                                    )),
+                            )
+                            .apply(Element::from),
+                        ])
+                        .align_items(iced::Alignment::Center),
+                    ),
+            )
+            .push(
+                widget::column()
+                    .push(widget::text::title3("Logical Volumes"))
+                    .push(iced::widget::horizontal_rule(1)),
+            )
+            .push(widget::scrollable(
+                widget::column::with_children(
+                    self.volumes.iter().map(|volume| volume.view()).collect(),
+                )
+                .padding([0, cosmic.space_xs(), 0, 0])
+                .spacing(cosmic.space_m()),
+            ))
+            .into()
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum LogicalVolumeKind {
+    Linear,
+    Snapshot,
+    ThinPool,
+    Thin,
+}
+
+impl LogicalVolumeKind {
+    fn for_display(&self) -> &'static str {
+        match self {
+            Self::Linear => "Linear",
+            Self::Snapshot => "Snapshot",
+            Self::ThinPool => "Thin Pool",
+            Self::Thin => "Thin Volume",
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum LogicalVolumeAction {
+    Resize(usize, u64, u64),
+    Snapshot(usize, Option<u64>),
+    CreateThin(usize),
+    Delete(usize),
+}
+
+impl widget::menu::Action for LogicalVolumeAction {
+    type Message = Result<AppMessage, Error>;
+
+    fn message(&self) -> Self::Message {
+        match self {
+            Self::Resize(index, size, max_size) => Ok(AppMessage::OpenOperationDialog(
+                Operation::LogicalVolumeResize(*index, *size, *max_size),
+            )),
+            Self::Snapshot(index, max_size) => Ok(AppMessage::OpenOperationDialog(
+                Operation::LogicalVolumeSnapshot(*index, *max_size),
+            )),
+            Self::CreateThin(index) => Ok(AppMessage::OpenOperationDialog(
+                Operation::LogicalVolumeCreate(LogicalVolumeCreateKind::Thin(*index), u64::MAX),
+            )),
+            Self::Delete(index) => Ok(AppMessage::OpenOperationDialog(
+                Operation::LogicalVolumeDelete(*index),
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LogicalVolume {
+    pub lv: LogicalVolumeProxy<'static>,
+    pub lv_path: OwnedObjectPath,
+    pub block: Option<BlockProxy<'static>>,
+    pub fs: Option<FilesystemProxy<'static>>,
+
+    pub name: String,
+    pub kind: LogicalVolumeKind,
+    pub size: u64,
+    pub size_for_display: String,
+    pub active: bool,
+    pub usage: Option<String>,
+    pub origin: Option<String>,
+    pub partition_id: String,
+    pub uuid: String,
+}
+
+impl LogicalVolume {
+    async fn load(
+        client: &Client,
+        lv: LogicalVolumeProxy<'static>,
+        lv_path: OwnedObjectPath,
+    ) -> Result<Self, Error> {
+        let size = lv.size().await?;
+        let kind = if lv.type_().await? == "pool" {
+            LogicalVolumeKind::ThinPool
+        } else if lv.thin_pool().await?.as_str() != "/" {
+            LogicalVolumeKind::Thin
+        } else if lv.origin().await?.as_str() != "/" {
+            LogicalVolumeKind::Snapshot
+        } else {
+            LogicalVolumeKind::Linear
+        };
+
+        let origin = match lv.origin().await?.as_str() {
+            "/" => None,
+            origin => Some(
+                std::path::Path::new(origin)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string(),
+            ),
+        };
+
+        let (block, fs) = match lv.block_device().await?.as_str() {
+            // Inactive volumes have no block device
+            "/" => (None, None),
+            path => {
+                let object = client.object(path.to_string()).unwrap();
+                (object.block().await.ok(), object.filesystem().await.ok())
+            }
+        };
+
+        let partition_id = match &block {
+            Some(block) => client
+                .id_for_display(
+                    block.id_usage().await?.as_str(),
+                    block.id_type().await?.as_str(),
+                    block.id_version().await?.as_str(),
+                    false,
+                )
+                .split('\u{004}')
+                .last()
+                .unwrap_or(block.id_type().await?.as_str())
+                .to_string(),
+            None => "Inactive".to_string(),
+        };
+
+        Ok(Self {
+            name: lv.name().await?,
+            uuid: lv.uuid().await?,
+            size,
+            size_for_display: client.size_for_display(size, true, false),
+            active: lv.active().await?,
+            usage: match kind {
+                LogicalVolumeKind::ThinPool => {
+                    Some(format!("{:.1}%", lv.data_allocated_ratio().await? * 100.0))
+                }
+                _ => None,
+            },
+            origin,
+            partition_id,
+            kind,
+            block,
+            fs,
+            lv,
+            lv_path,
+        })
+    }
+
+    fn menu_folder(
+        &self,
+        index: usize,
+        free_size: u64,
+    ) -> widget::menu::Item<LogicalVolumeAction, String> {
+        use widget::menu;
+        // Thin volumes grow into their pool rather than the volume group
+        let max_size = match self.kind {
+            LogicalVolumeKind::Thin => u64::MAX,
+            _ => self.size + free_size,
+        };
+        let mut items = vec![menu::Item::Button(
+            "Resize".to_string(),
+            None,
+            LogicalVolumeAction::Resize(index, self.size, max_size),
+        )];
+        match self.kind {
+            // Pools are not snapshotted themselves, only the thin volumes inside them
+            LogicalVolumeKind::ThinPool => items.push(menu::Item::Button(
+                "Create Thin Volume".to_string(),
+                None,
+                LogicalVolumeAction::CreateThin(index),
+            )),
+            LogicalVolumeKind::Thin => items.push(menu::Item::Button(
+                "Create Snapshot".to_string(),
+                None,
+                LogicalVolumeAction::Snapshot(index, None),
+            )),
+            _ => items.push(menu::Item::Button(
+                "Create Snapshot".to_string(),
+                None,
+                LogicalVolumeAction::Snapshot(index, Some(free_size)),
+            )),
+        }
+        items.push(menu::Item::Divider);
+        items.push(menu::Item::Button(
+            "Delete".to_string(),
+            None,
+            LogicalVolumeAction::Delete(index),
+        ));
+        menu::Item::Folder(self.name.clone(), items)
+    }
+
+    pub fn view(&self) -> Element<Result<AppMessage, Error>> {
+        let mut section = widget::settings::section()
+            .title(&self.name)
+            .add(widget::settings::item(
+                "File System",
+                widget::text::heading(&self.partition_id),
+            ))
+            .add(widget::settings::item(
+                "Type",
+                widget::text::heading(self.kind.for_display()),
+            ))
+            .add(widget::settings::item(
+                "Size",
+                widget::text::heading(&self.size_for_display),
+            ))
+            .add(widget::settings::item(
+                "Active",
+                widget::text::body(if self.active { "Yes" } else { "No" }),
+            ));
+        if let Some(usage) = &self.usage {
+            section = section.add(widget::settings::item(
+                "Data Used",
+                widget::text::body(usage),
+            ));
+        }
+        if let Some(origin) = &self.origin {
+            section = section.add(widget::settings::item("Origin", widget::text::body(origin)));
+        }
+        section
+            .add(widget::settings::item(
+                "UUID",
+                widget::text::caption(&self.uuid),
+            ))
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        for name in ["root", "home_2", "a.b+c-d", "x-", "..."] {
+            assert!(is_valid_name(name), "{name:?}");
+        }
+        for name in [
+            "",
+            "-root",
+            ".",
+            "..",
+            "my volume",
+            "a/b",
+            "ü",
+            &"a".repeat(128),
+        ] {
+            assert!(!is_valid_name(name), "{name:?}");
+        }
+    }
+}
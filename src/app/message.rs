@@ -1,4 +1,16 @@
+use super::benchmark::Benchmark;
+use super::device::{Device, DriveTarget};
 use super::drive::Drive;
+use super::error::Error;
+use super::filesystem::{CheckResult, FormatType};
+use super::loop_device::AttachOptions;
+use super::lvm::VolumeGroup;
+use super::nvme::Namespace;
+use super::operation::clone::CloneTarget;
+use super::progress::Progress;
+use super::raid::Raid;
+use super::smart::SelftestKind;
+use super::surface::SurfaceCheck;
 
 #[derive(Clone, Debug)]
 pub enum AppMessage {
@@ -18,13 +30,63 @@ pub enum AppMessage {
         udisks2::zbus::zvariant::OwnedObjectPath,
     ),
     DriveRead(cosmic::widget::nav_bar::Id, Drive),
+    DriveRemoved(udisks2::zbus::zvariant::OwnedObjectPath),
+
+    OpenPath(std::path::PathBuf),
+    OpenDevice(udisks2::zbus::zvariant::OwnedObjectPath),
+    AttachImage(AttachOptions),
+    ImageAttached(udisks2::zbus::zvariant::OwnedObjectPath),
+    LoopSetAutoclear(bool),
+    SmartSelftestStart(SelftestKind),
+    SmartSelftestAbort,
+    SmartChooseBlob,
+    SmartLoadBlob(std::path::PathBuf),
+    /// Refreshes drives running a self-test or another operation on their own
+    SmartPoll,
+    DriveStandby,
+    DriveWakeup,
+    /// Ejects the drive of a nav entry, the active one if `None`
+    DriveEject(Option<cosmic::widget::nav_bar::Id>),
+    DrivePowerOff(Option<cosmic::widget::nav_bar::Id>),
+    /// A benchmark run of the active drive, shown even if it could not be saved
+    Benchmarked(Benchmark),
+    /// A surface check of the active drive, shown even if it could not be saved
+    SurfaceChecked(SurfaceCheck),
+    SurfaceExport,
+    SurfaceExportTo(std::path::PathBuf),
+    /// The result of a check, with the error of mounting the file system again if that failed
+    FilesystemChecked(CheckResult, Option<Error>),
+    OpenImageFile(std::path::PathBuf),
+    LoadImageFile(cosmic::widget::nav_bar::Id, std::path::PathBuf),
+    CloseImageFile,
+
+    ReadVolumeGroups,
+    ReadVolumeGroupsDone(Vec<udisks2::zbus::zvariant::OwnedObjectPath>),
+    LoadVolumeGroup(
+        cosmic::widget::nav_bar::Id,
+        udisks2::zbus::zvariant::OwnedObjectPath,
+    ),
+    VolumeGroupRead(cosmic::widget::nav_bar::Id, VolumeGroup),
+
+    ReadRaids,
+    ReadRaidsDone(Vec<udisks2::zbus::zvariant::OwnedObjectPath>),
+    LoadRaid(
+        cosmic::widget::nav_bar::Id,
+        udisks2::zbus::zvariant::OwnedObjectPath,
+    ),
+    RaidRead(cosmic::widget::nav_bar::Id, Raid),
+    RaidCreated(udisks2::zbus::zvariant::OwnedObjectPath),
 
     // === === === Operations === === ===
     OpenOperationDialog(super::operation::Operation),
     CancelOperation,
     ConfirmOperation,
     PerformOperation(super::drive::Drive),
+    PerformVolumeGroupOperation(VolumeGroup),
+    PerformRaidOperation(Raid),
     OperationFinish,
+    OperationProgress(Progress),
+    CancelPending,
 
     // Drive Format
     OperationDriveFormatEraseMode(usize),
@@ -36,6 +98,59 @@ pub enum AppMessage {
 
     // Format Partition
     OperationPartitionFormatNameUpdate(String),
-    OperationPartitionFormatToggleErase(bool),
+    OperationPartitionFormatEraseMode(usize),
     OperationPartitionFormatSelectFS(usize),
+    OperationPartitionFormatTypes(Vec<FormatType>, bool),
+    OperationPartitionFormatToggleAdvanced,
+    OperationPartitionFormatOptionChoice(usize, usize),
+    OperationPartitionFormatOptionNumber(usize, String),
+    OperationPartitionFormatOptionFlag(usize, bool),
+
+    // Logical Volumes
+    OperationLogicalVolumeNameUpdate(String),
+    OperationLogicalVolumeSizeUpdate(String),
+    OperationLogicalVolumeSizeSave,
+    OperationLogicalVolumeToggleResizeFs(bool),
+
+    // Btrfs Subvolumes
+    OperationBtrfsNameUpdate(String),
+    OperationBtrfsToggleReadOnly(bool),
+
+    // RAID Arrays
+    OperationRaidCandidates(Vec<Device>),
+    OperationRaidToggleMember(usize, bool),
+    OperationRaidSelectLevel(usize),
+    OperationRaidNameUpdate(String),
+    OperationRaidSelectSpare(usize),
+
+    // Disk Images
+    OperationImageChoosePath,
+    OperationImagePath(Option<std::path::PathBuf>),
+    OperationImageSelectCompression(usize),
+    OperationImageToggleSparse(bool),
+    OperationImageToggleChecksum(bool),
+    OperationImageToggleVerify(bool),
+    OperationImageToggleReadOnly(bool),
+    OperationImageOffsetUpdate(String),
+    OperationImageSizeUpdate(String),
+    OperationImageToggleAutoclear(bool),
+    OperationImageToggleOffline(bool),
+
+    OperationIsoTargets(Vec<DriveTarget>),
+    OperationIsoSelectTarget(usize),
+
+    OperationCloneTargets(Vec<CloneTarget>),
+    OperationCloneSelectTarget(usize),
+    OperationCloneToggleGrow(bool),
+    OperationNamespaceLoaded(Namespace),
+    OperationNamespaceSelectFormat(usize),
+    OperationNamespaceSelectSecureErase(usize),
+    OperationSanitizeSelectAction(usize),
+    OperationDriveSettingsStandby(usize),
+    OperationDriveSettingsApm(usize),
+    OperationDriveSettingsAam(usize),
+    OperationDriveSettingsWriteCache(usize),
+    OperationBenchmarkSamples(usize),
+    OperationBenchmarkSampleSize(usize),
+    OperationBenchmarkToggleWrite(bool),
 }
@@ -1,30 +1,138 @@
 //pub mod action;
+pub mod benchmark;
+pub mod btrfs;
+pub mod device;
 pub mod drive;
+pub mod erase;
 pub mod error;
+pub mod filesystem;
+pub mod format_options;
+pub mod gpt;
+pub mod image;
+pub mod image_table;
+pub mod loop_device;
+pub mod lvm;
 pub mod message;
+pub mod nvme;
 pub mod operation;
+pub mod power;
+pub mod progress;
+pub mod raid;
+pub mod smart;
+pub mod surface;
 
 use error::Error;
 use message::AppMessage;
 
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use cosmic::dialog::file_chooser;
 use cosmic::{cosmic_theme, iced, prelude::*, widget};
 use udisks2::zbus::zvariant::OwnedObjectPath;
 
+pub struct Flags {
+    /// Device node or image file given on the command line
+    pub path: Option<PathBuf>,
+}
+
 pub struct App {
     core: cosmic::app::Core,
     nav_model: cosmic::widget::nav_bar::Model,
 
     active_drive: Option<OwnedObjectPath>,
+    /// Path to open once the devices have been read
+    open_path: Option<PathBuf>,
+    /// Drive to activate as soon as it is loaded
+    open_device: Option<OwnedObjectPath>,
     client: Option<udisks2::Client>,
     current_operation: Option<Box<dyn operation::OperationDialog>>,
     pending: bool,
+    progress: Option<progress::Progress>,
 
     errors: Vec<Error>,
 }
 
+/// Actions of the context menu of a nav entry, which need not be the active one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum NavMenuAction {
+    Eject(widget::nav_bar::Id),
+    PowerOff(widget::nav_bar::Id),
+}
+
+impl widget::menu::Action for NavMenuAction {
+    type Message = cosmic::Action<Result<AppMessage, Error>>;
+
+    fn message(&self) -> Self::Message {
+        cosmic::Action::App(match self {
+            Self::Eject(id) => Ok(AppMessage::DriveEject(Some(*id))),
+            Self::PowerOff(id) => Ok(AppMessage::DrivePowerOff(Some(*id))),
+        })
+    }
+}
+
+/// File in the user's data directory holding results of a drive, e.g. of benchmarks.
+///
+/// Results are stored by drive serial, so they follow the drive from port to port.
+pub fn data_path(kind: &str, key: &str) -> Option<PathBuf> {
+    if key.is_empty() {
+        return None;
+    }
+    let dir = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+        })?;
+    let name: String = key
+        .chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                true => c,
+                false => '_',
+            },
+        )
+        .collect();
+    Some(dir.join("andromeda").join(kind).join(name))
+}
+
+impl App {
+    /// The active drive, with what is needed to reload it afterwards.
+    fn active_drive_data(
+        &self,
+    ) -> Option<(cosmic::widget::nav_bar::Id, OwnedObjectPath, drive::Drive)> {
+        let drive = self.nav_model.active_data::<drive::Drive>()?;
+        Some((
+            self.nav_model.active(),
+            drive.block_path.clone()?,
+            drive.clone(),
+        ))
+    }
+
+    /// Continues with `then` unless the loop device lost its image, which happens without a
+    /// change to the drive list when it was attached with autoclear, e.g. once its last file
+    /// system is unmounted.
+    fn check_loop_device(
+        &self,
+        block_path: OwnedObjectPath,
+        then: AppMessage,
+    ) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let Some(client) = self.client.clone() else {
+            return cosmic::task::message(Ok(then));
+        };
+        cosmic::task::future(async move {
+            if loop_device::is_unused(&client, block_path.clone()).await {
+                Ok(AppMessage::DriveRemoved(block_path))
+            } else {
+                Ok(then)
+            }
+        })
+    }
+}
+
 impl cosmic::Application for App {
     type Executor = cosmic::executor::multi::Executor;
-    type Flags = ();
+    type Flags = Flags;
     type Message = Result<AppMessage, Error>;
 
     const APP_ID: &'static str = "io.github.cosmic_utils.andromeda";
@@ -46,17 +154,24 @@ impl cosmic::Application for App {
         id: cosmic::widget::nav_bar::Id,
     ) -> cosmic::app::Task<Self::Message> {
         self.nav_model.activate(id);
-        self.active_drive = if let Some(drive) = self.nav_model.active_data::<drive::Drive>() {
-            Some(drive.block_path.clone())
-        } else {
-            None
-        };
-        cosmic::Task::none()
+        self.active_drive = self
+            .nav_model
+            .active_data::<drive::Drive>()
+            .and_then(|drive| drive.block_path.clone());
+
+        let is_loop = self
+            .nav_model
+            .active_data::<drive::Drive>()
+            .is_some_and(|drive| drive.r#loop.is_some());
+        match (is_loop, self.active_drive.clone()) {
+            (true, Some(block_path)) => self.check_loop_device(block_path, AppMessage::NoOp),
+            _ => cosmic::Task::none(),
+        }
     }
 
     fn init(
         core: cosmic::app::Core,
-        _flags: Self::Flags,
+        flags: Self::Flags,
     ) -> (Self, cosmic::app::Task<Self::Message>) {
         let mut tasks: Vec<cosmic::app::Task<Self::Message>> = Vec::new();
         let nav_model = cosmic::widget::nav_bar::Model::default();
@@ -67,9 +182,12 @@ impl cosmic::Application for App {
                 core,
                 nav_model,
                 active_drive: None,
+                open_path: flags.path,
+                open_device: None,
                 client: None,
                 current_operation: None,
                 pending: false,
+                progress: None,
                 errors: Vec::new(),
             },
             cosmic::task::batch(tasks),
@@ -90,12 +208,17 @@ impl cosmic::Application for App {
 
                     AppMessage::InitClient => {
                         tasks.push(cosmic::task::future(async move {
-                            Ok(AppMessage::InitClientDone(udisks2::Client::new().await?))
+                            let client = udisks2::Client::new().await?;
+                            // Optional module, btrfs features stay hidden when it is unavailable
+                            let _ = client.manager().enable_module("btrfs", true).await;
+                            Ok(AppMessage::InitClientDone(client))
                         }));
                     }
                     AppMessage::InitClientDone(client) => {
                         self.client = Some(client);
                         tasks.push(cosmic::task::message(Ok(AppMessage::ReadDevices)));
+                        tasks.push(cosmic::task::message(Ok(AppMessage::ReadVolumeGroups)));
+                        tasks.push(cosmic::task::message(Ok(AppMessage::ReadRaids)));
                     }
 
                     AppMessage::InsertDrive(block_path) => {
@@ -113,15 +236,329 @@ impl cosmic::Application for App {
                         }
                     }
 
-                    AppMessage::DriveRead(id, drive) => {
+                    AppMessage::DriveRead(id, mut drive) => {
+                        if let Some(previous) = self.nav_model.data::<drive::Drive>(id) {
+                            drive.keep_unsaved(previous);
+                        }
                         self.nav_model.text_set(id, drive.model.clone());
                         self.nav_model.icon_set(
                             id,
-                            widget::icon::from_name("drive-harddisk-system-symbolic").icon(),
+                            widget::icon::from_name(
+                                // A warning or error icon stands in for the drive icon
+                                drive
+                                    .assessment()
+                                    .and_then(|assessment| assessment.icon_name())
+                                    .unwrap_or(match drive.r#loop {
+                                        Some(_) => "application-x-cd-image-symbolic",
+                                        None => "drive-harddisk-system-symbolic",
+                                    }),
+                            )
+                            .icon(),
                         );
+                        if drive.block_path.is_some() && self.open_device == drive.block_path {
+                            self.open_device = None;
+                            self.active_drive = drive.block_path.clone();
+                            self.nav_model.activate(id);
+                        }
                         self.nav_model.data_set(id, drive);
                     }
 
+                    AppMessage::DriveRemoved(block_path) => {
+                        let removed: Vec<_> = self
+                            .nav_model
+                            .iter()
+                            .filter(|id| {
+                                self.nav_model
+                                    .data::<drive::Drive>(*id)
+                                    .is_some_and(|drive| {
+                                        drive.block_path.as_ref() == Some(&block_path)
+                                    })
+                            })
+                            .collect();
+                        for id in removed {
+                            self.nav_model.remove(id);
+                        }
+                        if self.active_drive.as_ref() == Some(&block_path) {
+                            self.active_drive = None;
+                        }
+                        self.pending = false;
+                        self.progress = None;
+                    }
+
+                    AppMessage::OpenPath(path) => {
+                        use std::os::unix::fs::FileTypeExt;
+                        let is_device = std::fs::metadata(&path)
+                            .is_ok_and(|metadata| metadata.file_type().is_block_device());
+                        if is_device {
+                            if let Some(client) = self.client.clone() {
+                                tasks.push(cosmic::task::future(async move {
+                                    Ok(AppMessage::OpenDevice(
+                                        device::resolve(&client, &path).await?,
+                                    ))
+                                }));
+                            }
+                        } else {
+                            tasks.push(cosmic::task::message(Ok(AppMessage::AttachImage(
+                                loop_device::AttachOptions::new(path),
+                            ))));
+                        }
+                    }
+
+                    AppMessage::OpenDevice(block_path) => {
+                        let loaded = self.nav_model.iter().find(|id| {
+                            self.nav_model
+                                .data::<drive::Drive>(*id)
+                                .is_some_and(|drive| drive.block_path.as_ref() == Some(&block_path))
+                        });
+                        match loaded {
+                            Some(id) => {
+                                self.nav_model.activate(id);
+                                self.active_drive = Some(block_path);
+                            }
+                            None => self.open_device = Some(block_path),
+                        }
+                    }
+
+                    AppMessage::AttachImage(options) => {
+                        self.current_operation = None;
+                        if let Some(client) = self.client.clone() {
+                            tasks.push(cosmic::task::future(async move {
+                                Ok(AppMessage::ImageAttached(
+                                    loop_device::attach(&client, options).await?,
+                                ))
+                            }));
+                        }
+                    }
+
+                    AppMessage::ImageAttached(block_path) => {
+                        let entity = self.nav_model.insert().activate().id();
+                        self.active_drive = Some(block_path.clone());
+                        tasks.push(cosmic::task::message(Ok(AppMessage::LoadDrive(
+                            entity, block_path,
+                        ))));
+                    }
+
+                    AppMessage::OpenImageFile(path) => {
+                        self.current_operation = None;
+                        let entity = self.nav_model.insert().activate().id();
+                        self.active_drive = None;
+                        tasks.push(cosmic::task::message(Ok(AppMessage::LoadImageFile(
+                            entity, path,
+                        ))));
+                    }
+
+                    AppMessage::LoadImageFile(id, path) => {
+                        if let Some(client) = self.client.clone() {
+                            tasks.push(cosmic::task::future(drive::Drive::load_image_file(
+                                client, id, path,
+                            )));
+                        }
+                    }
+
+                    AppMessage::CloseImageFile => {
+                        if self
+                            .nav_model
+                            .active_data::<drive::Drive>()
+                            .is_some_and(|drive| drive.image_file.is_some())
+                        {
+                            self.nav_model.remove(self.nav_model.active());
+                        }
+                    }
+
+                    AppMessage::LoopSetAutoclear(autoclear) => {
+                        if let Some(drive) = self.nav_model.active_data::<drive::Drive>() {
+                            if let (Some(r#loop), Some(block_path)) =
+                                (drive.r#loop.clone(), drive.block_path.clone())
+                            {
+                                let id = self.nav_model.active();
+                                tasks.push(cosmic::task::future(async move {
+                                    r#loop
+                                        .set_autoclear(autoclear, udisks2::standard_options(false))
+                                        .await?;
+                                    Ok(AppMessage::LoadDrive(id, block_path))
+                                }));
+                            }
+                        }
+                    }
+
+                    AppMessage::SmartSelftestStart(kind) => {
+                        if let Some((id, block_path, drive)) = self.active_drive_data() {
+                            tasks.push(cosmic::task::future(async move {
+                                drive.start_selftest(kind).await?;
+                                Ok(AppMessage::LoadDrive(id, block_path))
+                            }));
+                        }
+                    }
+
+                    AppMessage::SmartSelftestAbort => {
+                        if let Some((id, block_path, drive)) = self.active_drive_data() {
+                            tasks.push(cosmic::task::future(async move {
+                                drive.abort_selftest().await?;
+                                Ok(AppMessage::LoadDrive(id, block_path))
+                            }));
+                        }
+                    }
+
+                    AppMessage::DriveStandby | AppMessage::DriveWakeup => {
+                        if let Some((id, block_path, drive)) = self.active_drive_data() {
+                            if let Some(ata) = drive.ata {
+                                let standby = matches!(message, AppMessage::DriveStandby);
+                                tasks.push(cosmic::task::future(async move {
+                                    match standby {
+                                        true => {
+                                            ata.pm_standby(udisks2::standard_options(false)).await?
+                                        }
+                                        false => {
+                                            ata.pm_wakeup(udisks2::standard_options(false)).await?
+                                        }
+                                    }
+                                    Ok(AppMessage::LoadDrive(id, block_path))
+                                }));
+                            }
+                        }
+                    }
+
+                    AppMessage::DriveEject(id) | AppMessage::DrivePowerOff(id) => {
+                        let power_off = matches!(message, AppMessage::DrivePowerOff(_));
+                        let id = id.unwrap_or_else(|| self.nav_model.active());
+                        if let (Some(client), Some(drive)) =
+                            (self.client.clone(), self.nav_model.data::<drive::Drive>(id))
+                        {
+                            if let (Some(proxy), Some(drive_path), Some(block_path)) = (
+                                drive.drive.clone(),
+                                drive.drive_path(),
+                                drive.block_path.clone(),
+                            ) {
+                                tasks.push(cosmic::task::future(async move {
+                                    device::release_drive(&client, &drive_path).await?;
+                                    if power_off {
+                                        proxy.power_off(udisks2::standard_options(false)).await?;
+                                        return Ok(AppMessage::DriveRemoved(block_path));
+                                    }
+                                    proxy.eject(udisks2::standard_options(false)).await?;
+                                    // Card readers and optical drives stay, only the media is gone
+                                    let present = match client
+                                        .object(block_path.clone())
+                                        .unwrap()
+                                        .block()
+                                        .await
+                                    {
+                                        Ok(block) => block.size().await.is_ok(),
+                                        Err(_) => false,
+                                    };
+                                    match present {
+                                        true => Ok(AppMessage::LoadDrive(id, block_path)),
+                                        false => Ok(AppMessage::ReadDevices),
+                                    }
+                                }));
+                            }
+                        }
+                    }
+
+                    AppMessage::FilesystemChecked(result, remount) => {
+                        // The result is shown once the drive was reloaded, a failed mount after it
+                        let mut task = cosmic::task::message(Ok(AppMessage::OperationFinish))
+                            .chain(cosmic::task::message(Ok(AppMessage::OpenOperationDialog(
+                                operation::Operation::FilesystemCheckResult(result),
+                            ))));
+                        if let Some(err) = remount {
+                            task = task.chain(cosmic::task::message(Err(err)));
+                        }
+                        tasks.push(task);
+                    }
+
+                    AppMessage::Benchmarked(benchmark) => {
+                        if let Some(drive) = self.nav_model.active_data_mut::<drive::Drive>() {
+                            drive.benchmarks.push(benchmark);
+                        }
+                    }
+
+                    AppMessage::SurfaceChecked(check) => {
+                        if let Some(drive) = self.nav_model.active_data_mut::<drive::Drive>() {
+                            drive.surface = Some(check);
+                        }
+                    }
+
+                    AppMessage::SurfaceExport => {
+                        let file_name = self
+                            .nav_model
+                            .active_data::<drive::Drive>()
+                            .map_or("Surface Check.txt".to_string(), |drive| {
+                                format!("Surface Check {}.txt", drive.model)
+                            });
+                        tasks.push(cosmic::task::future(async move {
+                            let dialog = file_chooser::save::Dialog::new()
+                                .title("Export Report")
+                                .file_name(file_name);
+                            match dialog.save_file().await {
+                                Ok(response) => Ok(match response.url() {
+                                    Some(url) => match url.to_file_path() {
+                                        Ok(path) => AppMessage::SurfaceExportTo(path),
+                                        Err(_) => AppMessage::NoOp,
+                                    },
+                                    None => AppMessage::NoOp,
+                                }),
+                                Err(file_chooser::Error::Cancelled) => Ok(AppMessage::NoOp),
+                                Err(err) => Err(Error::new(err.to_string(), true)),
+                            }
+                        }));
+                    }
+
+                    AppMessage::SurfaceExportTo(path) => {
+                        if let Some(drive) = self.nav_model.active_data::<drive::Drive>() {
+                            if let Some(surface) = &drive.surface {
+                                if let Err(err) = std::fs::write(&path, surface.report(drive)) {
+                                    self.errors.push(err.into());
+                                }
+                            }
+                        }
+                    }
+
+                    AppMessage::SmartChooseBlob => {
+                        tasks.push(cosmic::task::future(async move {
+                            let dialog = file_chooser::open::Dialog::new().title("Load SMART Data");
+                            match dialog.open_file().await {
+                                Ok(response) => Ok(match response.url().to_file_path() {
+                                    Ok(path) => AppMessage::SmartLoadBlob(path),
+                                    Err(_) => AppMessage::NoOp,
+                                }),
+                                Err(file_chooser::Error::Cancelled) => Ok(AppMessage::NoOp),
+                                Err(err) => Err(Error::new(err.to_string(), true)),
+                            }
+                        }));
+                    }
+
+                    AppMessage::SmartLoadBlob(path) => {
+                        if let Some((id, block_path, drive)) = self.active_drive_data() {
+                            if let Some(health) = drive.health {
+                                tasks.push(cosmic::task::future(async move {
+                                    health.load_blob(std::fs::read(&path)?).await?;
+                                    Ok(AppMessage::LoadDrive(id, block_path))
+                                }));
+                            }
+                        }
+                    }
+
+                    AppMessage::SmartPoll => {
+                        for id in self.nav_model.iter() {
+                            let Some(drive) = self.nav_model.data::<drive::Drive>(id) else {
+                                continue;
+                            };
+                            if !drive.in_progress() {
+                                continue;
+                            }
+                            if drive.nvme.is_some() {
+                                tasks
+                                    .push(cosmic::task::future(drive.clone().refresh_progress(id)));
+                            } else if let Some(block_path) = &drive.block_path {
+                                tasks.push(cosmic::task::message(Ok(AppMessage::LoadDrive(
+                                    id,
+                                    block_path.clone(),
+                                ))));
+                            }
+                        }
+                    }
+
                     AppMessage::ReadDevices => {
                         let client = self.client.clone();
 
@@ -142,27 +579,65 @@ impl cosmic::Application for App {
                     }
 
                     AppMessage::ReadDevicesDone(blocks) => {
-                        for block_path in blocks {
+                        // Attaching before the devices are listed would show the image twice
+                        if let Some(path) = self.open_path.take() {
+                            tasks.push(cosmic::task::message(Ok(AppMessage::OpenPath(path))));
+                        }
+                        // Reading again drops devices that went away and keeps those still shown
+                        let mut shown = Vec::new();
+                        let mut gone = Vec::new();
+                        for id in self.nav_model.iter() {
+                            let Some(block_path) = self
+                                .nav_model
+                                .data::<drive::Drive>(id)
+                                .and_then(|drive| drive.block_path.clone())
+                            else {
+                                continue;
+                            };
+                            match blocks.contains(&block_path) {
+                                true => shown.push(block_path),
+                                false => gone.push(id),
+                            }
+                        }
+                        for id in gone {
+                            self.nav_model.remove(id);
+                        }
+                        if self
+                            .active_drive
+                            .as_ref()
+                            .is_some_and(|block_path| !blocks.contains(block_path))
+                        {
+                            self.active_drive = None;
+                        }
+                        for block_path in blocks
+                            .into_iter()
+                            .filter(|block_path| !shown.contains(block_path))
+                        {
                             if let Some(client) = self.client.clone() {
                                 tasks.push(cosmic::task::future(async move {
-                                    if client
+                                    // Running arrays are shown with their RAID entry
+                                    let raid = client
                                         .object(block_path.clone())
                                         .unwrap()
-                                        .partition()
-                                        .await
-                                        .is_ok()
+                                        .block()
+                                        .await?
+                                        .mdraid()
+                                        .await?;
+                                    if raid.as_str() != "/"
                                         || client
                                             .object(block_path.clone())
                                             .unwrap()
-                                            .r#loop()
+                                            .partition()
                                             .await
                                             .is_ok()
+                                        || loop_device::is_unused(&client, block_path.clone()).await
                                         || client
                                             .object(block_path.clone())
                                             .unwrap()
                                             .swapspace()
                                             .await
                                             .is_ok()
+                                        || lvm::is_logical_volume(&client, block_path.clone()).await
                                     {
                                         Ok(AppMessage::NoOp)
                                     } else {
@@ -172,28 +647,146 @@ impl cosmic::Application for App {
                             };
                         }
                     }
+
+                    AppMessage::ReadVolumeGroups => {
+                        if let Some(client) = self.client.clone() {
+                            tasks.push(cosmic::task::future(lvm::volume_groups(client)));
+                        }
+                    }
+
+                    AppMessage::ReadVolumeGroupsDone(vg_paths) => {
+                        for vg_path in vg_paths {
+                            let entity = self.nav_model.insert().id();
+                            tasks.push(cosmic::task::message(Ok(AppMessage::LoadVolumeGroup(
+                                entity, vg_path,
+                            ))));
+                        }
+                    }
+
+                    AppMessage::LoadVolumeGroup(id, vg_path) => {
+                        if let Some(client) = self.client.clone() {
+                            tasks.push(cosmic::task::future(lvm::VolumeGroup::load(
+                                client, id, vg_path,
+                            )));
+                        }
+                    }
+
+                    AppMessage::VolumeGroupRead(id, vg) => {
+                        self.nav_model.text_set(id, vg.name.clone());
+                        self.nav_model.icon_set(
+                            id,
+                            widget::icon::from_name("drive-multidisk-symbolic").icon(),
+                        );
+                        self.nav_model.data_set(id, vg);
+                    }
+
+                    AppMessage::ReadRaids => {
+                        if let Some(client) = self.client.clone() {
+                            tasks.push(cosmic::task::future(raid::arrays(client)));
+                        }
+                    }
+
+                    AppMessage::ReadRaidsDone(raid_paths) => {
+                        for raid_path in raid_paths {
+                            let entity = self.nav_model.insert().id();
+                            tasks.push(cosmic::task::message(Ok(AppMessage::LoadRaid(
+                                entity, raid_path,
+                            ))));
+                        }
+                    }
+
+                    AppMessage::LoadRaid(id, raid_path) => {
+                        if let Some(client) = self.client.clone() {
+                            tasks.push(cosmic::task::future(raid::Raid::load(
+                                client, id, raid_path,
+                            )));
+                        }
+                    }
+
+                    AppMessage::RaidRead(id, raid) => {
+                        self.nav_model.text_set(id, raid.name.clone());
+                        self.nav_model.icon_set(
+                            id,
+                            widget::icon::from_name("drive-multidisk-symbolic").icon(),
+                        );
+                        self.nav_model.data_set(id, raid);
+                    }
+
+                    AppMessage::RaidCreated(raid_path) => {
+                        // The new array gets its own entry, the members are reloaded as usual
+                        tasks.push(
+                            cosmic::task::message(Ok(AppMessage::OperationFinish)).chain(
+                                cosmic::task::message(Ok(AppMessage::ReadRaidsDone(vec![
+                                    raid_path,
+                                ]))),
+                            ),
+                        );
+                    }
+
                     AppMessage::OpenOperationDialog(operation_type) => {
-                        self.current_operation = Some(operation_type.into());
+                        let mut operation: Box<dyn operation::OperationDialog> =
+                            operation_type.into();
+                        if let Some(client) = self.client.clone() {
+                            tasks.push(operation.init(client));
+                        }
+                        self.current_operation = Some(operation);
                     }
-                    AppMessage::PerformOperation(_) => self.current_operation = None,
+                    AppMessage::PerformOperation(_)
+                    | AppMessage::PerformVolumeGroupOperation(_)
+                    | AppMessage::PerformRaidOperation(_) => self.current_operation = None,
                     AppMessage::ConfirmOperation => {
                         if let Some(drive) = self.nav_model.active_data::<drive::Drive>() {
                             tasks.push(cosmic::task::message(Ok(AppMessage::PerformOperation(
                                 drive.clone(),
                             ))));
                             self.pending = true;
+                        } else if let Some(vg) = self.nav_model.active_data::<lvm::VolumeGroup>() {
+                            tasks.push(cosmic::task::message(Ok(
+                                AppMessage::PerformVolumeGroupOperation(vg.clone()),
+                            )));
+                            self.pending = true;
+                        } else if let Some(raid) = self.nav_model.active_data::<raid::Raid>() {
+                            tasks.push(cosmic::task::message(Ok(
+                                AppMessage::PerformRaidOperation(raid.clone()),
+                            )));
+                            self.pending = true;
                         }
                     }
                     AppMessage::CancelOperation => {
                         self.current_operation = None;
                     }
+                    AppMessage::OperationProgress(progress) => self.progress = Some(progress),
+                    AppMessage::CancelPending => {
+                        if let Some(progress) = &self.progress {
+                            progress.cancel();
+                        }
+                    }
                     AppMessage::OperationFinish => {
                         self.pending = false;
+                        self.progress = None;
                         self.current_operation = None;
                         if let Some(drive) = self.nav_model.active_data::<drive::Drive>() {
-                            tasks.push(cosmic::task::message(Ok(AppMessage::LoadDrive(
+                            let id = self.nav_model.active();
+                            if let Some(path) = drive.image_file.clone() {
+                                tasks.push(cosmic::task::message(Ok(AppMessage::LoadImageFile(
+                                    id, path,
+                                ))))
+                            } else if let Some(block_path) = drive.block_path.clone() {
+                                let load = AppMessage::LoadDrive(id, block_path.clone());
+                                tasks.push(match drive.r#loop {
+                                    Some(_) => self.check_loop_device(block_path, load),
+                                    None => cosmic::task::message(Ok(load)),
+                                })
+                            }
+                        } else if let Some(vg) = self.nav_model.active_data::<lvm::VolumeGroup>() {
+                            tasks.push(cosmic::task::message(Ok(AppMessage::LoadVolumeGroup(
                                 self.nav_model.active(),
-                                drive.block_path.clone(),
+                                vg.vg_path.clone(),
+                            ))))
+                        } else if let Some(raid) = self.nav_model.active_data::<raid::Raid>() {
+                            tasks.push(cosmic::task::message(Ok(AppMessage::LoadRaid(
+                                self.nav_model.active(),
+                                raid.raid_path.clone(),
                             ))))
                         }
                     }
@@ -206,14 +799,75 @@ impl cosmic::Application for App {
         cosmic::Task::batch(tasks)
     }
 
+    fn nav_context_menu(
+        &self,
+        id: widget::nav_bar::Id,
+    ) -> Option<Vec<widget::menu::Tree<cosmic::Action<Self::Message>>>> {
+        let drive = self.nav_model.data::<drive::Drive>(id)?;
+        let mut items = Vec::new();
+        if drive.ejectable {
+            items.push(widget::menu::Item::Button(
+                "Eject",
+                None,
+                NavMenuAction::Eject(id),
+            ));
+        }
+        if drive.can_power_off {
+            items.push(widget::menu::Item::Button(
+                "Power Off",
+                None,
+                NavMenuAction::PowerOff(id),
+            ));
+        }
+        if items.is_empty() {
+            return None;
+        }
+        Some(widget::menu::items(&HashMap::new(), items))
+    }
+
     fn header_start(&self) -> Vec<Element<Self::Message>> {
         if let Some(active_drive) = self.nav_model.active_data::<drive::Drive>() {
             vec![active_drive.menu_bar()]
+        } else if let Some(vg) = self.nav_model.active_data::<lvm::VolumeGroup>() {
+            vec![vg.menu_bar()]
+        } else if let Some(raid) = self.nav_model.active_data::<raid::Raid>() {
+            vec![raid.menu_bar()]
         } else {
             Vec::new()
         }
     }
 
+    fn subscription(&self) -> cosmic::iced::Subscription<Self::Message> {
+        // Image files dropped onto the window are opened like command line arguments
+        let mut subscriptions = vec![iced::event::listen_with(|event, _, _| match event {
+            iced::Event::Window(iced::window::Event::FileDropped(path)) => {
+                Some(Ok(AppMessage::OpenPath(path)))
+            }
+            _ => None,
+        })];
+        let in_progress = self.nav_model.iter().any(|id| {
+            self.nav_model
+                .data::<drive::Drive>(id)
+                .is_some_and(|drive| drive.in_progress())
+        });
+        if in_progress {
+            subscriptions
+                .push(iced::time::every(Duration::from_secs(5)).map(|_| Ok(AppMessage::SmartPoll)));
+        }
+        iced::Subscription::batch(subscriptions)
+    }
+
+    fn header_end(&self) -> Vec<Element<Self::Message>> {
+        vec![
+            widget::button::icon(widget::icon::from_name("document-open-symbolic"))
+                .tooltip("Attach Disk Image...")
+                .on_press(Ok(AppMessage::OpenOperationDialog(
+                    operation::Operation::ImageAttach,
+                )))
+                .into(),
+        ]
+    }
+
     fn dialog(&self) -> Option<Element<Self::Message>> {
         if self.errors.len() > 0 {
             let error = self.errors.last().unwrap();
@@ -242,6 +896,8 @@ impl cosmic::Application for App {
             }
         } else if let Some(action) = &self.current_operation {
             Some(action.dialog())
+        } else if let Some(progress) = self.progress.as_ref().filter(|_| self.pending) {
+            Some(progress.dialog(self.client.as_ref()))
         } else if self.pending {
             Some(
                 cosmic::widget::dialog()
@@ -257,6 +913,18 @@ impl cosmic::Application for App {
     fn view(&self) -> Element<Self::Message> {
         let theme = cosmic::theme::active();
         let cosmic = theme.cosmic();
+        if let Some(vg) = self.nav_model.active_data::<lvm::VolumeGroup>() {
+            return widget::layer_container(vg.view())
+                .width(iced::Length::Fill)
+                .layer(cosmic_theme::Layer::Background)
+                .into();
+        }
+        if let Some(raid) = self.nav_model.active_data::<raid::Raid>() {
+            return widget::layer_container(raid.view())
+                .width(iced::Length::Fill)
+                .layer(cosmic_theme::Layer::Background)
+                .into();
+        }
         match self.nav_model.active_data::<drive::Drive>() {
             Some(drive) => widget::layer_container(drive.view())
                 .width(iced::Length::Fill)
@@ -0,0 +1,822 @@
+use std::collections::HashMap;
+use std::future::Future;
+
+use cosmic::iced::futures::future::{self, Either};
+use cosmic::iced::futures::StreamExt;
+
+use cosmic::{iced, prelude::*, theme, widget};
+
+use udisks2::{
+    zbus::zvariant::{OwnedObjectPath, OwnedValue, Value},
+    Client,
+};
+
+use super::progress::Reporter;
+use super::smart::{duration_for_display, temperature_for_display, Assessment, SelftestKind};
+use super::{error::Error, message::AppMessage};
+
+#[zbus::proxy(
+    interface = "org.freedesktop.UDisks2.NVMe.Controller",
+    default_service = "org.freedesktop.UDisks2"
+)]
+pub trait NvmeController {
+    fn smart_update(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+
+    fn smart_get_attributes(
+        &self,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<HashMap<String, OwnedValue>>;
+
+    fn smart_selftest_start(
+        &self,
+        type_: &str,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<()>;
+
+    fn smart_selftest_abort(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+
+    fn sanitize_start(&self, action: &str, options: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn state(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn controller_id(&self) -> zbus::Result<u16>;
+
+    #[zbus(property, name = "NVMeRevision")]
+    fn nvme_revision(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn smart_updated(&self) -> zbus::Result<u64>;
+
+    #[zbus(property)]
+    fn smart_critical_warning(&self) -> zbus::Result<Vec<String>>;
+
+    #[zbus(property)]
+    fn smart_power_on_hours(&self) -> zbus::Result<u64>;
+
+    #[zbus(property)]
+    fn smart_temperature(&self) -> zbus::Result<u16>;
+
+    #[zbus(property)]
+    fn smart_selftest_status(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn smart_selftest_percent_remaining(&self) -> zbus::Result<i32>;
+
+    #[zbus(property)]
+    fn sanitize_status(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn sanitize_percent_remaining(&self) -> zbus::Result<i32>;
+}
+
+pub const NAMESPACE_INTERFACE: &str = "org.freedesktop.UDisks2.NVMe.Namespace";
+
+#[zbus::proxy(
+    interface = "org.freedesktop.UDisks2.NVMe.Namespace",
+    default_service = "org.freedesktop.UDisks2"
+)]
+pub trait NvmeNamespace {
+    fn format_namespace(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+
+    #[zbus(property, name = "NSID")]
+    fn nsid(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn namespace_size(&self) -> zbus::Result<u64>;
+
+    #[zbus(property)]
+    fn namespace_utilization(&self) -> zbus::Result<u64>;
+
+    /// Data size, metadata size and relative performance of each supported format
+    #[zbus(property, name = "LBAFormats")]
+    fn lba_formats(&self) -> zbus::Result<Vec<(u16, u16, u8)>>;
+
+    #[zbus(property, name = "FormattedLBASize")]
+    fn formatted_lba_size(&self) -> zbus::Result<(u16, u16, u8)>;
+
+    #[zbus(property)]
+    fn format_percent_remaining(&self) -> zbus::Result<i32>;
+}
+
+fn state_for_display(state: &str) -> &str {
+    match state {
+        "live" => "Live",
+        "new" => "New",
+        "connecting" => "Connecting",
+        "resetting" => "Resetting",
+        "deleting" => "Deleting",
+        "deleting (no IO)" => "Deleting (No IO)",
+        "dead" => "Dead",
+        state => state,
+    }
+}
+
+fn critical_warning_for_display(warning: &str) -> &str {
+    match warning {
+        "spare" => "Available spare below threshold",
+        "temperature" => "Temperature outside the allowed range",
+        "degraded" => "Reliability degraded by media errors",
+        "readonly" => "Media placed in read only mode",
+        "volatile_mem" => "Volatile memory backup failed",
+        "pmr_readonly" => "Persistent memory region is read only",
+        warning => warning,
+    }
+}
+
+fn sanitize_status_for_display(status: &str) -> &'static str {
+    match status {
+        "never_sanitized" | "" => "Never Sanitized",
+        "success" => "Completed Successfully",
+        "failure" => "Failed",
+        "inprogress" => "In Progress",
+        _ => "Unknown",
+    }
+}
+
+/// Progress of an operation the controller runs on its own.
+fn percent_remaining_view<'a>(percent_remaining: i32) -> Element<'a, Result<AppMessage, Error>> {
+    let done = (100 - percent_remaining).clamp(0, 100);
+    widget::column()
+        .spacing(theme::active().cosmic().space_xxs())
+        .push(widget::text::body(format!("{done}% Done")))
+        .push(iced::widget::progress_bar(0.0..=100.0, done as f32))
+        .into()
+}
+
+fn selftest_status_for_display(status: &str) -> &'static str {
+    match status {
+        "success" => "Completed Successfully",
+        "aborted" => "Aborted",
+        "ctrl_reset" => "Aborted by a Controller Reset",
+        "ns_removed" => "Aborted, Namespace Removed",
+        "aborted_format" => "Aborted by a Format",
+        "fatal" => "Did Not Complete",
+        "error_known_segment" | "error_unknown_segment" => "Failed, Segment Error",
+        "error_unknown" => "Failed",
+        "inprogress" => "In Progress",
+        _ => "Unknown",
+    }
+}
+
+/// Values of the SMART / Health Information log page.
+#[derive(Clone, Debug, Default)]
+pub struct HealthLog {
+    /// Percentages of the spare capacity
+    pub available_spare: u8,
+    pub spare_threshold: u8,
+    /// Estimate of the rated endurance used up, may exceed 100
+    pub percent_used: u8,
+    pub media_errors: u64,
+    pub unsafe_shutdowns: u64,
+    pub power_cycles: u64,
+    /// Kelvin, one per sensor the controller implements
+    pub temperature_sensors: Vec<u16>,
+    pub warning_temperature: u16,
+    pub critical_temperature: u16,
+}
+
+impl HealthLog {
+    fn parse(attributes: &HashMap<String, OwnedValue>) -> Self {
+        let get = |key: &str| -> u64 {
+            let Some(value) = attributes.get(key) else {
+                return 0;
+            };
+            match &**value {
+                Value::U8(value) => *value as u64,
+                Value::U16(value) => *value as u64,
+                Value::U32(value) => *value as u64,
+                Value::U64(value) => *value,
+                Value::I32(value) => (*value).max(0) as u64,
+                _ => 0,
+            }
+        };
+        // Percentages are single bytes in the log, larger values only come from broken drivers
+        let percent = |key: &str| get(key).min(u8::MAX as u64) as u8;
+        let temperature_sensors = match attributes.get("temp_sensors").map(|value| &**value) {
+            Some(Value::Array(sensors)) => sensors
+                .iter()
+                .filter_map(|sensor| match sensor {
+                    // Unimplemented sensors report zero
+                    Value::U16(kelvin) if *kelvin > 0 => Some(*kelvin),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        Self {
+            available_spare: percent("avail_spare"),
+            spare_threshold: percent("spare_thresh"),
+            percent_used: percent("percent_used"),
+            media_errors: get("media_errors"),
+            unsafe_shutdowns: get("unsafe_shutdowns"),
+            power_cycles: get("power_cycles"),
+            temperature_sensors,
+            warning_temperature: get("wctemp") as u16,
+            critical_temperature: get("cctemp") as u16,
+        }
+    }
+}
+
+/// Rates a drive from its critical warnings and health log.
+fn assess(critical_warnings: &[String], log: Option<&HealthLog>) -> Assessment {
+    // Running hot is reported as a critical warning too, but does not mean wear
+    let critical = critical_warnings
+        .iter()
+        .any(|warning| warning != "temperature");
+    let worn_out =
+        log.is_some_and(|log| log.percent_used >= 100 || log.available_spare < log.spare_threshold);
+    if critical || worn_out {
+        Assessment::Failing
+    } else if !critical_warnings.is_empty()
+        || log.is_some_and(|log| log.media_errors > 0 || log.percent_used >= 90)
+    {
+        Assessment::Warning
+    } else {
+        Assessment::Good
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LbaFormat {
+    pub data_size: u16,
+    pub metadata_size: u16,
+    /// 0 is the best performance, 3 degraded
+    pub relative_performance: u8,
+}
+
+impl LbaFormat {
+    pub fn name(&self) -> String {
+        let mut name = match self.data_size {
+            512 => "512 Bytes (512e)".to_string(),
+            4096 => "4096 Bytes (4Kn)".to_string(),
+            size => format!("{size} Bytes"),
+        };
+        if self.metadata_size > 0 {
+            name.push_str(&format!(" + {} Bytes Metadata", self.metadata_size));
+        }
+        name.push_str(match self.relative_performance {
+            0 => ", Best Performance",
+            1 => ", Better Performance",
+            2 => ", Good Performance",
+            _ => ", Degraded Performance",
+        });
+        name
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Namespace {
+    pub proxy: NvmeNamespaceProxy<'static>,
+    pub device: String,
+    pub device_number: u64,
+    pub nsid: u32,
+    pub capacity: u64,
+    pub size: String,
+    pub utilization: String,
+    pub lba_formats: Vec<LbaFormat>,
+    /// Index into the formats the namespace is currently formatted with
+    pub formatted: Option<usize>,
+    /// -1 when no format is running
+    pub format_percent_remaining: i32,
+}
+
+impl Namespace {
+    /// Loads the namespace behind a block device, `None` for any other block device.
+    pub async fn load(client: &Client, block_path: OwnedObjectPath) -> Result<Option<Self>, Error> {
+        let block = client.object(block_path.clone()).unwrap().block().await?;
+        let proxy = NvmeNamespaceProxy::builder(client.manager().inner().connection())
+            .path(block_path)?
+            .build()
+            .await?;
+        let Ok(nsid) = proxy.nsid().await else {
+            return Ok(None);
+        };
+
+        let lba_formats: Vec<LbaFormat> = proxy
+            .lba_formats()
+            .await?
+            .into_iter()
+            .map(
+                |(data_size, metadata_size, relative_performance)| LbaFormat {
+                    data_size,
+                    metadata_size,
+                    relative_performance,
+                },
+            )
+            .collect();
+        let (data_size, metadata_size, _) = proxy.formatted_lba_size().await?;
+        let capacity = block.size().await?;
+        Ok(Some(Self {
+            device: String::from_utf8_lossy(&block.preferred_device().await?)
+                .trim_end_matches('\0')
+                .to_string(),
+            device_number: block.device_number().await?,
+            nsid,
+            capacity,
+            size: client.size_for_display(capacity, true, false),
+            utilization: client.size_for_display(proxy.namespace_utilization().await?, true, false),
+            formatted: lba_formats.iter().position(|format| {
+                format.data_size == data_size && format.metadata_size == metadata_size
+            }),
+            lba_formats,
+            format_percent_remaining: proxy.format_percent_remaining().await?,
+            proxy,
+        }))
+    }
+
+    /// Finds the namespace with the given block device number.
+    pub async fn find(client: &Client, device_number: u64) -> Result<Option<Self>, Error> {
+        for block_path in client
+            .manager()
+            .get_block_devices(udisks2::standard_options(false))
+            .await?
+        {
+            let block = client.object(block_path.clone()).unwrap().block().await?;
+            if block.device_number().await? == device_number {
+                return Self::load(client, block_path).await;
+            }
+        }
+        Ok(None)
+    }
+
+    /// Formats the namespace, waiting for the controller to finish.
+    pub async fn format(
+        &self,
+        format: LbaFormat,
+        secure_erase: Option<&'static str>,
+        reporter: &mut Reporter,
+        size: u64,
+    ) -> Result<(), Error> {
+        let mut options = udisks2::standard_options(false);
+        options.insert("lba_data_size", format.data_size.into());
+        options.insert("metadata_size", format.metadata_size.into());
+        if let Some(secure_erase) = secure_erase {
+            options.insert("secure_erase", secure_erase.into());
+        }
+        let changes = self.proxy.receive_format_percent_remaining_changed().await;
+        track(
+            self.proxy.format_namespace(options),
+            changes,
+            reporter,
+            size,
+        )
+        .await
+    }
+}
+
+/// Waits for a controller operation while following its percentage remaining.
+///
+/// Progress is reported in bytes of the device so the regular progress dialog can show it.
+async fn track(
+    call: impl Future<Output = zbus::Result<()>>,
+    mut changes: zbus::proxy::PropertyStream<'static, i32>,
+    reporter: &mut Reporter,
+    size: u64,
+) -> Result<(), Error> {
+    let mut call = std::pin::pin!(call);
+    loop {
+        match future::select(call, changes.next()).await {
+            Either::Left((result, _)) => return Ok(result?),
+            Either::Right((Some(change), pending)) => {
+                if let Ok(remaining) = change.get().await {
+                    let done = (100 - remaining).clamp(0, 100) as u64;
+                    reporter.update(size / 100 * done);
+                }
+                call = pending;
+            }
+            Either::Right((None, pending)) => return Ok(pending.await?),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SanitizeAction {
+    BlockErase,
+    CryptoErase,
+    Overwrite,
+}
+
+impl SanitizeAction {
+    pub const ALL: [Self; 3] = [Self::BlockErase, Self::CryptoErase, Self::Overwrite];
+    pub const NAMES: [&'static str; 3] = ["Block Erase", "Crypto Erase", "Overwrite"];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::BlockErase => "block",
+            Self::CryptoErase => "crypto",
+            Self::Overwrite => "overwrite",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        Self::NAMES[*self as usize]
+    }
+}
+
+/// Controller and health information of an NVMe drive.
+#[derive(Clone, Debug)]
+pub struct Nvme {
+    pub controller: NvmeControllerProxy<'static>,
+    pub state: String,
+    pub controller_id: u16,
+    pub revision: String,
+    /// Firmware revision, reported by the drive interface
+    pub firmware: String,
+    /// Unix time of the last update, zero when the health log was never read
+    pub updated: u64,
+    pub critical_warnings: Vec<String>,
+    pub power_on_hours: u64,
+    pub temperature: u16,
+    pub selftest_status: String,
+    /// -1 when no self-test is running
+    pub selftest_percent_remaining: i32,
+    pub log: Option<HealthLog>,
+    pub namespaces: Vec<Namespace>,
+    pub sanitize_status: String,
+    /// -1 when no sanitize operation is running
+    pub sanitize_percent_remaining: i32,
+}
+
+impl Nvme {
+    /// Loads controller information, `None` for drives that are not NVMe.
+    pub async fn load(
+        client: &Client,
+        drive_path: OwnedObjectPath,
+        firmware: String,
+    ) -> Result<Option<Self>, Error> {
+        let connection = client.manager().inner().connection();
+        let controller = NvmeControllerProxy::builder(connection)
+            .path(drive_path.clone())?
+            .build()
+            .await?;
+        // The proxy builds for any object, only NVMe drives have the properties
+        let Ok(state) = controller.state().await else {
+            return Ok(None);
+        };
+
+        // Namespaces show up as block devices of the drive, only they have the namespace
+        // interface, so other block devices are never queried
+        let mut namespaces = Vec::new();
+        for (block_path, interfaces) in client.object_manager().get_managed_objects().await? {
+            if !interfaces
+                .keys()
+                .any(|interface| interface.as_str() == NAMESPACE_INTERFACE)
+            {
+                continue;
+            }
+            let block = client.object(block_path.clone()).unwrap().block().await?;
+            if block.drive().await? != drive_path {
+                continue;
+            }
+            if let Some(namespace) = Namespace::load(client, block_path).await? {
+                namespaces.push(namespace);
+            }
+        }
+        namespaces.sort_by_key(|namespace| namespace.nsid);
+
+        let updated = controller.smart_updated().await?;
+        let log = match updated > 0 {
+            true => controller
+                .smart_get_attributes(udisks2::standard_options(false))
+                .await
+                .ok()
+                .map(|attributes| HealthLog::parse(&attributes)),
+            false => None,
+        };
+
+        Ok(Some(Self {
+            state,
+            controller_id: controller.controller_id().await?,
+            revision: controller.nvme_revision().await?,
+            firmware,
+            updated,
+            critical_warnings: controller.smart_critical_warning().await?,
+            power_on_hours: controller.smart_power_on_hours().await?,
+            temperature: controller.smart_temperature().await?,
+            selftest_status: controller.smart_selftest_status().await?,
+            selftest_percent_remaining: controller.smart_selftest_percent_remaining().await?,
+            log,
+            namespaces,
+            sanitize_status: controller.sanitize_status().await?,
+            sanitize_percent_remaining: controller.sanitize_percent_remaining().await?,
+            controller,
+        }))
+    }
+
+    pub fn selftest_running(&self) -> bool {
+        self.selftest_status == "inprogress"
+    }
+
+    /// Rereads only what changes while an operation runs, for polling its progress.
+    pub async fn refresh_progress(&mut self) -> Result<(), Error> {
+        self.selftest_status = self.controller.smart_selftest_status().await?;
+        self.selftest_percent_remaining =
+            self.controller.smart_selftest_percent_remaining().await?;
+        self.sanitize_status = self.controller.sanitize_status().await?;
+        self.sanitize_percent_remaining = self.controller.sanitize_percent_remaining().await?;
+        for namespace in &mut self.namespaces {
+            namespace.format_percent_remaining = namespace.proxy.format_percent_remaining().await?;
+        }
+        Ok(())
+    }
+
+    /// Whether the controller is busy with an operation worth following.
+    pub fn in_progress(&self) -> bool {
+        self.selftest_running()
+            || self.sanitize_status == "inprogress"
+            || self
+                .namespaces
+                .iter()
+                .any(|namespace| namespace.format_percent_remaining >= 0)
+    }
+
+    /// Sanitizes the whole drive, waiting for the controller to finish.
+    pub async fn sanitize(
+        &self,
+        action: SanitizeAction,
+        reporter: &mut Reporter,
+        size: u64,
+    ) -> Result<(), Error> {
+        let changes = self
+            .controller
+            .receive_sanitize_percent_remaining_changed()
+            .await;
+        track(
+            self.controller
+                .sanitize_start(action.id(), udisks2::standard_options(false)),
+            changes,
+            reporter,
+            size,
+        )
+        .await
+    }
+
+    pub async fn start_selftest(&self, kind: SelftestKind) -> Result<(), Error> {
+        self.controller
+            .smart_selftest_start(kind.id(), udisks2::standard_options(false))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn abort_selftest(&self) -> Result<(), Error> {
+        self.controller
+            .smart_selftest_abort(udisks2::standard_options(false))
+            .await?;
+        Ok(())
+    }
+
+    pub fn assessment(&self) -> Assessment {
+        assess(&self.critical_warnings, self.log.as_ref())
+    }
+
+    pub fn view(&self) -> Element<Result<AppMessage, Error>> {
+        let theme = theme::active();
+        let cosmic = theme.cosmic();
+
+        let mut section = widget::settings::section()
+            .title("Health")
+            .add(widget::settings::item(
+                "Assessment",
+                widget::text::heading(self.assessment().name()),
+            ))
+            .add(widget::settings::item(
+                "Controller",
+                widget::text::body(format!(
+                    "{}, ID {}, NVMe {}",
+                    state_for_display(&self.state),
+                    self.controller_id,
+                    self.revision
+                )),
+            ))
+            .add(widget::settings::item(
+                "Firmware",
+                widget::text::body(&self.firmware),
+            ));
+        section = section
+            .add(widget::settings::item(
+                "Temperature",
+                widget::text::body(temperature_for_display(self.temperature as f64)),
+            ))
+            .add(widget::settings::item(
+                "Powered On",
+                widget::text::body(match self.power_on_hours {
+                    0 => "Unknown".to_string(),
+                    hours => duration_for_display(hours * 3600),
+                }),
+            ))
+            .add(widget::settings::item(
+                "Critical Warnings",
+                widget::text::body(match self.critical_warnings.is_empty() {
+                    true => "None".to_string(),
+                    false => self
+                        .critical_warnings
+                        .iter()
+                        .map(|warning| critical_warning_for_display(warning))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                }),
+            ));
+        if let Some(log) = &self.log {
+            section = section
+                .add(widget::settings::item(
+                    "Percentage Used",
+                    widget::text::body(format!("{}%", log.percent_used)),
+                ))
+                .add(widget::settings::item(
+                    "Available Spare",
+                    widget::text::body(format!(
+                        "{}% (Threshold {}%)",
+                        log.available_spare, log.spare_threshold
+                    )),
+                ))
+                .add(widget::settings::item(
+                    "Media Errors",
+                    widget::text::body(log.media_errors.to_string()),
+                ))
+                .add(widget::settings::item(
+                    "Unsafe Shutdowns",
+                    widget::text::body(format!(
+                        "{} of {} power cycles",
+                        log.unsafe_shutdowns, log.power_cycles
+                    )),
+                ));
+            for (index, kelvin) in log.temperature_sensors.iter().enumerate() {
+                section = section.add(widget::settings::item(
+                    format!("Temperature Sensor {}", index + 1),
+                    widget::text::body(temperature_for_display(*kelvin as f64)),
+                ));
+            }
+            if log.warning_temperature > 0 {
+                section = section.add(widget::settings::item(
+                    "Temperature Limits",
+                    widget::text::body(format!(
+                        "Warning {}, Critical {}",
+                        temperature_for_display(log.warning_temperature as f64),
+                        temperature_for_display(log.critical_temperature as f64)
+                    )),
+                ));
+            }
+        }
+        section = section
+            .add(widget::settings::item(
+                "Self-Test",
+                match self.selftest_running() {
+                    true => percent_remaining_view(self.selftest_percent_remaining),
+                    false => widget::text::body(match self.selftest_status.as_str() {
+                        "" => "Never Run",
+                        status => selftest_status_for_display(status),
+                    })
+                    .apply(Element::from),
+                },
+            ))
+            .add(widget::settings::item(
+                "Sanitize",
+                match self.sanitize_status.as_str() {
+                    "inprogress" => percent_remaining_view(self.sanitize_percent_remaining),
+                    status => {
+                        widget::text::body(sanitize_status_for_display(status)).apply(Element::from)
+                    }
+                },
+            ));
+
+        widget::column()
+            .spacing(cosmic.space_xs())
+            .push(section)
+            .extend(self.namespaces.iter().map(|namespace| {
+                let mut section = widget::settings::section()
+                    .title(format!(
+                        "Namespace {} ({})",
+                        namespace.nsid, namespace.device
+                    ))
+                    .add(widget::settings::item(
+                        "Size",
+                        widget::text::body(format!(
+                            "{} of {} used",
+                            namespace.utilization, namespace.size
+                        )),
+                    ));
+                if namespace.format_percent_remaining >= 0 {
+                    section = section.add(widget::settings::item(
+                        "Formatting",
+                        percent_remaining_view(namespace.format_percent_remaining),
+                    ));
+                }
+                namespace
+                    .lba_formats
+                    .iter()
+                    .enumerate()
+                    .fold(section, |section, (index, format)| {
+                        section.add(widget::settings::item(
+                            format!("LBA Format {index}"),
+                            widget::text::body(match namespace.formatted == Some(index) {
+                                true => format!("{} (In Use)", format.name()),
+                                false => format.name(),
+                            }),
+                        ))
+                    })
+                    .into()
+            }))
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attributes(values: Vec<(&str, Value<'static>)>) -> HashMap<String, OwnedValue> {
+        values
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn health_log_values() {
+        let log = HealthLog::parse(&attributes(vec![
+            ("avail_spare", Value::U8(95)),
+            ("spare_thresh", Value::U8(10)),
+            ("percent_used", Value::U8(3)),
+            ("media_errors", Value::U64(1 << 40)),
+            ("unsafe_shutdowns", Value::U32(12)),
+            ("power_cycles", Value::U16(300)),
+            ("temp_sensors", Value::from(vec![310u16, 0, 320, 0])),
+            ("wctemp", Value::U16(343)),
+            ("cctemp", Value::I32(358)),
+        ]));
+        assert_eq!(
+            (log.available_spare, log.spare_threshold, log.percent_used),
+            (95, 10, 3)
+        );
+        assert_eq!(log.media_errors, 1 << 40);
+        assert_eq!((log.unsafe_shutdowns, log.power_cycles), (12, 300));
+        // Sensors reading zero are not implemented
+        assert_eq!(log.temperature_sensors, [310, 320]);
+        assert_eq!(
+            (log.warning_temperature, log.critical_temperature),
+            (343, 358)
+        );
+    }
+
+    #[test]
+    fn health_log_odd_values() {
+        let log = HealthLog::parse(&attributes(Vec::new()));
+        assert_eq!(log.available_spare, 0);
+        assert_eq!(log.media_errors, 0);
+        assert!(log.temperature_sensors.is_empty());
+
+        let log = HealthLog::parse(&attributes(vec![
+            ("percent_used", Value::U32(300)),
+            ("avail_spare", Value::I32(-1)),
+            ("media_errors", Value::from("many")),
+            ("temp_sensors", Value::from(vec![0u16, 0])),
+            ("wctemp", Value::U64(343)),
+        ]));
+        assert_eq!(log.percent_used, u8::MAX);
+        assert_eq!(log.available_spare, 0);
+        assert_eq!(log.media_errors, 0);
+        assert!(log.temperature_sensors.is_empty());
+        assert_eq!(log.warning_temperature, 343);
+    }
+
+    #[test]
+    fn assessments() {
+        let log = |available_spare, spare_threshold, percent_used, media_errors| HealthLog {
+            available_spare,
+            spare_threshold,
+            percent_used,
+            media_errors,
+            ..Default::default()
+        };
+        // critical warnings, health log from spare, spare threshold, percent used, media errors
+        let cases = [
+            (&[][..], Some(log(100, 10, 0, 0)), Assessment::Good),
+            (&[], None, Assessment::Good),
+            (&[], Some(log(10, 10, 89, 0)), Assessment::Good),
+            (&[], Some(log(9, 10, 0, 0)), Assessment::Failing),
+            (&[], Some(log(100, 10, 90, 0)), Assessment::Warning),
+            (&[], Some(log(100, 10, 100, 0)), Assessment::Failing),
+            (&[], Some(log(100, 10, 255, 0)), Assessment::Failing),
+            (&[], Some(log(100, 10, 0, 1)), Assessment::Warning),
+            (
+                &["temperature"],
+                Some(log(100, 10, 0, 0)),
+                Assessment::Warning,
+            ),
+            (&["temperature"], None, Assessment::Warning),
+            (&["spare"], None, Assessment::Failing),
+            (&["temperature", "readonly"], None, Assessment::Failing),
+        ];
+        for (warnings, log, assessment) in cases {
+            let warnings: Vec<String> =
+                warnings.iter().map(|warning| warning.to_string()).collect();
+            assert_eq!(
+                assess(&warnings, log.as_ref()),
+                assessment,
+                "{warnings:?} {log:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use std::fs::File;
+use std::os::fd::OwnedFd;
+use std::os::unix::fs::OpenOptionsExt;
+
+use cosmic::{prelude::*, widget};
+
+use crate::app::benchmark::{self, BenchmarkOptions, History, SAMPLE_COUNTS, SAMPLE_SIZES};
+use crate::app::{error::Error, message::AppMessage, progress};
+
+pub struct Benchmark {
+    client: Option<udisks2::Client>,
+    /// Only drives without mounted file systems may be written to
+    writable: bool,
+    sample_count_names: Vec<String>,
+    sample_size_names: Vec<String>,
+    samples: Option<usize>,
+    sample_size: Option<usize>,
+    write: bool,
+}
+
+impl Benchmark {
+    pub fn new(writable: bool) -> Self {
+        Self {
+            client: None,
+            writable,
+            sample_count_names: SAMPLE_COUNTS
+                .iter()
+                .map(|count| format!("{count} Samples"))
+                .collect(),
+            sample_size_names: SAMPLE_SIZES
+                .iter()
+                .map(|size| format!("{} MiB", size >> 20))
+                .collect(),
+            samples: Some(2),
+            sample_size: Some(1),
+            write: false,
+        }
+    }
+}
+
+impl super::OperationDialog for Benchmark {
+    fn init(&mut self, client: udisks2::Client) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        self.client = Some(client);
+        cosmic::Task::none()
+    }
+
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        match message {
+            AppMessage::OperationBenchmarkSamples(index) => self.samples = Some(index),
+            AppMessage::OperationBenchmarkSampleSize(index) => self.sample_size = Some(index),
+            AppMessage::OperationBenchmarkToggleWrite(write) => self.write = write,
+            AppMessage::PerformOperation(drive) => {
+                let options = BenchmarkOptions {
+                    samples: SAMPLE_COUNTS[self.samples.unwrap_or(0)],
+                    sample_size: SAMPLE_SIZES[self.sample_size.unwrap_or(0)],
+                    write: self.writable && self.write,
+                };
+                let key = drive.results_key();
+                let capacity = drive.capacity;
+                if let Some(path) = drive.image_file {
+                    tasks.push(progress::run(move |mut reporter| async move {
+                        let result = reporter
+                            .blocking(move |reporter| {
+                                // Bypasses the page cache, like udisks opens devices for it
+                                let mut file = std::fs::OpenOptions::new()
+                                    .read(true)
+                                    .write(options.write)
+                                    .custom_flags(libc::O_DIRECT)
+                                    .open(&path)?;
+                                benchmark::run(&mut file, capacity, options, reporter)
+                            })
+                            .await?;
+                        let saved = History::save(&key, &result);
+                        reporter.message(AppMessage::Benchmarked(result)).await;
+                        saved
+                    }));
+                }
+                let (Some(client), Some(block), Some(block_path)) =
+                    (self.client.clone(), drive.block, drive.block_path)
+                else {
+                    return cosmic::app::Task::batch(tasks);
+                };
+                tasks.push(progress::run(move |mut reporter| async move {
+                    // Something may have been mounted since the dialog was opened
+                    if options.write && benchmark::is_mounted(&client, &block_path).await? {
+                        return Err(Error::new(
+                            "A file system of the drive is mounted, unmount it to run the write test",
+                            true,
+                        ));
+                    }
+                    let mut open_options = udisks2::standard_options(false);
+                    open_options.insert("writable", options.write.into());
+                    let mut device =
+                        File::from(OwnedFd::from(block.open_for_benchmark(open_options).await?));
+                    let result = reporter
+                        .blocking(move |reporter| {
+                            benchmark::run(&mut device, capacity, options, reporter)
+                        })
+                        .await?;
+                    // The run is shown either way, drives without a serial can not keep it
+                    let saved = History::save(&key, &result);
+                    reporter.message(AppMessage::Benchmarked(result)).await;
+                    saved
+                }));
+            }
+            _ => {}
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        use widget::settings;
+
+        let mut write = widget::toggler(self.writable && self.write);
+        if self.writable {
+            write = write.on_toggle(|write| Ok(AppMessage::OperationBenchmarkToggleWrite(write)));
+        }
+
+        widget::dialog()
+            .title("Benchmark")
+            .body(match (self.writable, self.write) {
+                (false, _) => {
+                    "Measures how fast the drive reads. Unmount all of its file systems to also measure writing."
+                }
+                (true, false) => "Measures how fast the drive reads.",
+                (true, true) => {
+                    "The write test writes back the data it just read. Back up important data first, a crash or power loss during the test can corrupt the drive."
+                }
+            })
+            .control(
+                settings::section()
+                    .add(settings::item(
+                        "Read Samples",
+                        widget::dropdown(&self.sample_count_names, self.samples, |index| {
+                            Ok(AppMessage::OperationBenchmarkSamples(index))
+                        }),
+                    ))
+                    .add(settings::item(
+                        "Sample Size",
+                        widget::dropdown(&self.sample_size_names, self.sample_size, |index| {
+                            Ok(AppMessage::OperationBenchmarkSampleSize(index))
+                        }),
+                    ))
+                    .add(settings::item("Write Test", write)),
+            )
+            .primary_action(
+                match self.writable && self.write {
+                    true => widget::button::destructive("Start Benchmark"),
+                    false => widget::button::suggested("Start Benchmark"),
+                }
+                .on_press(Ok(AppMessage::ConfirmOperation)),
+            )
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
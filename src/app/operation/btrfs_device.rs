@@ -0,0 +1,107 @@
+use cosmic::{prelude::*, widget};
+
+use super::btrfs_subvolume::find_btrfs;
+use crate::app::{error::Error, message::AppMessage};
+
+pub struct AddDevice {
+    block_offset: u64,
+    candidate: usize,
+}
+
+impl AddDevice {
+    pub fn new(block_offset: u64, candidate: usize) -> Self {
+        Self {
+            block_offset,
+            candidate,
+        }
+    }
+}
+
+impl super::OperationDialog for AddDevice {
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        if let AppMessage::PerformOperation(drive) = message {
+            if let Some(btrfs) = find_btrfs(&drive, self.block_offset) {
+                if let Some(candidate) = btrfs.candidates.get(self.candidate) {
+                    let path = candidate.path.clone();
+                    tasks.push(cosmic::task::future(async move {
+                        btrfs
+                            .proxy
+                            .add_device(&path, udisks2::standard_options(false))
+                            .await?;
+                        Ok(AppMessage::OperationFinish)
+                    }));
+                }
+            }
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        widget::dialog()
+            .title("Add Device")
+            .body("Any data on the added device will be lost! Existing data is not rebalanced onto the new device, run a balance to change the profile afterwards.")
+            .primary_action(
+                widget::button::destructive("Add").on_press(Ok(AppMessage::ConfirmOperation)),
+            )
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
+
+pub struct RemoveDevice {
+    block_offset: u64,
+    member: usize,
+    degrades: bool,
+}
+
+impl RemoveDevice {
+    pub fn new(block_offset: u64, member: usize, degrades: bool) -> Self {
+        Self {
+            block_offset,
+            member,
+            degrades,
+        }
+    }
+}
+
+impl super::OperationDialog for RemoveDevice {
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        if let AppMessage::PerformOperation(drive) = message {
+            if let Some(btrfs) = find_btrfs(&drive, self.block_offset) {
+                if let Some(member) = btrfs.members.get(self.member) {
+                    let path = member.path.clone();
+                    tasks.push(cosmic::task::future(async move {
+                        btrfs
+                            .proxy
+                            .remove_device(&path, udisks2::standard_options(false))
+                            .await?;
+                        Ok(AppMessage::OperationFinish)
+                    }));
+                }
+            }
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        let body = if self.degrades {
+            "The remaining devices are fewer than the profiles in use require, the removal will fail or leave the file system without redundancy! Convert the profile with a balance first."
+        } else {
+            "The data on the device is moved to the remaining devices, this can take a long time."
+        };
+        widget::dialog()
+            .title("Remove Device")
+            .body(body)
+            .primary_action(
+                widget::button::destructive("Remove").on_press(Ok(AppMessage::ConfirmOperation)),
+            )
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
@@ -0,0 +1,217 @@
+use cosmic::{prelude::*, widget};
+
+use crate::app::{btrfs::Btrfs, drive::Drive, error::Error, message::AppMessage};
+
+/// Looks up the btrfs file system of the partition at `offset`.
+pub fn find_btrfs(drive: &Drive, offset: u64) -> Option<Btrfs> {
+    drive
+        .partitions
+        .iter()
+        .find(|block| block.offset == offset)
+        .and_then(|block| block.partition.as_ref())
+        .and_then(|partition| partition.btrfs.clone())
+}
+
+/// Paths are taken relative to the mount point and must stay below it.
+fn is_valid_path(path: &str) -> bool {
+    !path.trim().is_empty()
+        && !path.starts_with('/')
+        && !path.split('/').any(|component| component == "..")
+}
+
+pub struct SubvolumeCreate {
+    block_offset: u64,
+    name: String,
+}
+
+impl SubvolumeCreate {
+    pub fn new(block_offset: u64) -> Self {
+        Self {
+            block_offset,
+            name: "".to_string(),
+        }
+    }
+}
+
+impl super::OperationDialog for SubvolumeCreate {
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        match message {
+            AppMessage::OperationBtrfsNameUpdate(name) => self.name = name,
+            AppMessage::PerformOperation(drive) => {
+                if let (Some(btrfs), true) = (
+                    find_btrfs(&drive, self.block_offset),
+                    is_valid_path(&self.name),
+                ) {
+                    let name = self.name.clone();
+                    tasks.push(cosmic::task::future(async move {
+                        btrfs
+                            .proxy
+                            .create_subvolume(&name, udisks2::standard_options(false))
+                            .await?;
+                        Ok(AppMessage::OperationFinish)
+                    }));
+                }
+            }
+            _ => {}
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        let mut create = widget::button::suggested("Create");
+        if is_valid_path(&self.name) {
+            create = create.on_press(Ok(AppMessage::ConfirmOperation));
+        }
+
+        widget::dialog()
+            .title("Create Subvolume")
+            .body("The path is relative to where the file system is mounted")
+            .control(
+                widget::settings::section().add(widget::settings::item(
+                    "Path",
+                    widget::text_input("", &self.name)
+                        .on_input(|input| Ok(AppMessage::OperationBtrfsNameUpdate(input))),
+                )),
+            )
+            .primary_action(create)
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
+
+pub struct SubvolumeSnapshot {
+    block_offset: u64,
+    subvolume_id: u64,
+    destination: String,
+    read_only: bool,
+}
+
+impl SubvolumeSnapshot {
+    pub fn new(block_offset: u64, subvolume_id: u64) -> Self {
+        Self {
+            block_offset,
+            subvolume_id,
+            destination: "".to_string(),
+            read_only: true,
+        }
+    }
+}
+
+impl super::OperationDialog for SubvolumeSnapshot {
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        match message {
+            AppMessage::OperationBtrfsNameUpdate(destination) => self.destination = destination,
+            AppMessage::OperationBtrfsToggleReadOnly(toggle) => self.read_only = toggle,
+            AppMessage::PerformOperation(drive) => {
+                if let Some(btrfs) = find_btrfs(&drive, self.block_offset) {
+                    let source = btrfs
+                        .subvolume(self.subvolume_id)
+                        .and_then(|subvolume| subvolume.mounted_path.clone());
+                    if let (Some(source), true) = (source, is_valid_path(&self.destination)) {
+                        let destination = self.destination.clone();
+                        let read_only = self.read_only;
+                        tasks.push(cosmic::task::future(async move {
+                            btrfs
+                                .proxy
+                                .create_snapshot(
+                                    &source,
+                                    &destination,
+                                    read_only,
+                                    udisks2::standard_options(false),
+                                )
+                                .await?;
+                            Ok(AppMessage::OperationFinish)
+                        }));
+                    }
+                }
+            }
+            _ => {}
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        use widget::settings;
+        let mut create = widget::button::suggested("Create");
+        if is_valid_path(&self.destination) {
+            create = create.on_press(Ok(AppMessage::ConfirmOperation));
+        }
+
+        widget::dialog()
+            .title("Create Snapshot")
+            .body("The destination path is relative to where the file system is mounted")
+            .control(
+                settings::section()
+                    .add(settings::item(
+                        "Destination",
+                        widget::text_input("", &self.destination)
+                            .on_input(|input| Ok(AppMessage::OperationBtrfsNameUpdate(input))),
+                    ))
+                    .add(settings::item(
+                        "Read Only",
+                        widget::toggler(self.read_only).on_toggle(|toggle| {
+                            Ok(AppMessage::OperationBtrfsToggleReadOnly(toggle))
+                        }),
+                    )),
+            )
+            .primary_action(create)
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
+
+pub struct SubvolumeDelete {
+    block_offset: u64,
+    subvolume_id: u64,
+}
+
+impl SubvolumeDelete {
+    pub fn new(block_offset: u64, subvolume_id: u64) -> Self {
+        Self {
+            block_offset,
+            subvolume_id,
+        }
+    }
+}
+
+impl super::OperationDialog for SubvolumeDelete {
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        if let AppMessage::PerformOperation(drive) = message {
+            if let Some(btrfs) = find_btrfs(&drive, self.block_offset) {
+                if let Some(path) = btrfs
+                    .subvolume(self.subvolume_id)
+                    .and_then(|subvolume| subvolume.mounted_path.clone())
+                {
+                    tasks.push(cosmic::task::future(async move {
+                        btrfs
+                            .proxy
+                            .remove_subvolume(&path, udisks2::standard_options(false))
+                            .await?;
+                        Ok(AppMessage::OperationFinish)
+                    }));
+                }
+            }
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        widget::dialog()
+            .title("Delete Subvolume")
+            .body("This operation is not reversible, all files in the subvolume will be lost!")
+            .primary_action(
+                widget::button::destructive("Delete").on_press(Ok(AppMessage::ConfirmOperation)),
+            )
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
@@ -0,0 +1,378 @@
+use std::fs::File;
+use std::os::fd::OwnedFd;
+use std::time::{Duration, Instant};
+
+use cosmic::{prelude::*, widget};
+use udisks2::{block::BlockProxy, zbus::zvariant::OwnedObjectPath, Client};
+
+use crate::app::{device, error::Error, gpt, image, message::AppMessage, progress};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CloneSource {
+    /// A whole drive, by the device number of its block device
+    Drive(u64),
+    /// A partition of the active drive, by its offset
+    Partition(u64),
+}
+
+/// How long udisks gets to pick up the partitions and file systems of a rescanned device.
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// A device a drive or partition can be cloned onto.
+#[derive(Clone, Debug)]
+pub struct CloneTarget {
+    pub block: BlockProxy<'static>,
+    pub block_path: OwnedObjectPath,
+    /// Offset of free space in the partition table on `block`, a partition is created there
+    /// before cloning
+    pub free_offset: Option<u64>,
+    pub name: String,
+    pub capacity: u64,
+}
+
+pub struct CloneDialog {
+    client: Option<Client>,
+    source: CloneSource,
+    size: u64,
+    targets: Vec<CloneTarget>,
+    names: Vec<String>,
+    target: Option<usize>,
+    grow: bool,
+}
+
+impl CloneDialog {
+    pub fn new(source: CloneSource, size: u64) -> Self {
+        Self {
+            client: None,
+            source,
+            size,
+            targets: Vec::new(),
+            names: Vec::new(),
+            target: None,
+            grow: true,
+        }
+    }
+}
+
+/// Counts the partitions, and the file systems with a UUID, udisks shows on or below a device.
+async fn probe(client: &Client, block_path: &OwnedObjectPath) -> Result<(usize, usize), Error> {
+    let object = client.object(block_path.clone()).unwrap();
+    let mut paths = vec![block_path.clone()];
+    if let Ok(ptable) = object.partition_table().await {
+        paths.extend(ptable.partitions().await?);
+    }
+    let partitions = paths.len() - 1;
+    let mut filesystems = 0;
+    for path in paths {
+        let object = client.object(path).unwrap();
+        if object.filesystem().await.is_ok() && !object.block().await?.id_uuid().await?.is_empty() {
+            filesystems += 1;
+        }
+    }
+    Ok((partitions, filesystems))
+}
+
+/// Waits until udisks shows the partitions and file systems copied onto a device.
+///
+/// A rescan only makes the kernel reread the device, udisks learns about the result from udev
+/// some time later.
+async fn settle(
+    client: &Client,
+    block_path: &OwnedObjectPath,
+    partitions: usize,
+    filesystems: usize,
+) -> Result<(), Error> {
+    let started = Instant::now();
+    loop {
+        let (found_partitions, found_filesystems) = probe(client, block_path).await?;
+        if found_partitions >= partitions && found_filesystems >= filesystems {
+            return Ok(());
+        }
+        if started.elapsed() > SETTLE_TIMEOUT {
+            return Err(Error::new(
+                "The copy was written, but its partitions did not show up in time to give them new UUIDs",
+                true,
+            ));
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+}
+
+/// Gives every file system on or below a block device a new random UUID.
+async fn regenerate_fs_uuids(client: &Client, block_path: OwnedObjectPath) -> Result<(), Error> {
+    let object = client.object(block_path.clone()).unwrap();
+    let mut paths = vec![block_path];
+    if let Ok(ptable) = object.partition_table().await {
+        paths.extend(ptable.partitions().await?);
+    }
+    for path in paths {
+        let object = client.object(path).unwrap();
+        let Ok(fs) = object.filesystem().await else {
+            continue;
+        };
+        let id_type = object.block().await?.id_type().await?;
+        fs.set_uuid(
+            &gpt::random_fs_uuid(&id_type)?,
+            udisks2::standard_options(false),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Grows the partition furthest into the drive, and its file system with it, to the end.
+async fn grow_last_partition(client: &Client, block_path: OwnedObjectPath) -> Result<(), Error> {
+    let ptable = client.object(block_path).unwrap().partition_table().await?;
+    let mut last = None;
+    for path in ptable.partitions().await? {
+        let partition = client.object(path.clone()).unwrap().partition().await?;
+        let offset = partition.offset().await?;
+        if last
+            .as_ref()
+            .map_or(true, |(last_offset, _)| offset > *last_offset)
+        {
+            last = Some((offset, path));
+        }
+    }
+    let Some((_, path)) = last else {
+        return Ok(());
+    };
+    let object = client.object(path).unwrap();
+    // A size of zero takes up all the space there is
+    object
+        .partition()
+        .await?
+        .resize(0, udisks2::standard_options(false))
+        .await?;
+    if let Ok(fs) = object.filesystem().await {
+        fs.resize(0, udisks2::standard_options(false)).await?;
+    }
+    Ok(())
+}
+
+impl super::OperationDialog for CloneDialog {
+    fn init(&mut self, client: Client) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        self.client = Some(client.clone());
+        let source = self.source;
+        let size = self.size;
+        cosmic::task::future(async move {
+            let mut targets = Vec::new();
+            match source {
+                CloneSource::Drive(device_number) => {
+                    for target in device::drive_targets(&client, false).await? {
+                        if target.device_number != device_number && target.capacity >= size {
+                            targets.push(CloneTarget {
+                                block: target.block,
+                                block_path: target.block_path,
+                                free_offset: None,
+                                name: target.name,
+                                capacity: target.capacity,
+                            });
+                        }
+                    }
+                }
+                CloneSource::Partition(_) => {
+                    for device in device::unused(&client).await? {
+                        if device.capacity >= size {
+                            targets.push(CloneTarget {
+                                block: client.object(device.path.clone()).unwrap().block().await?,
+                                block_path: device.path,
+                                free_offset: None,
+                                name: format!("{} ({})", device.device, device.size),
+                                capacity: device.capacity,
+                            });
+                        }
+                    }
+                    // A new partition of the same size is created in free space
+                    for target in device::drive_targets(&client, false).await? {
+                        for (offset, capacity) in
+                            device::free_space(&client, &target.block_path).await?
+                        {
+                            if capacity >= size {
+                                targets.push(CloneTarget {
+                                    block: target.block.clone(),
+                                    block_path: target.block_path.clone(),
+                                    free_offset: Some(offset),
+                                    name: format!(
+                                        "Free Space at {} on {}",
+                                        client.size_for_display(offset, true, false),
+                                        target.name
+                                    ),
+                                    capacity,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+            Ok(AppMessage::OperationCloneTargets(targets))
+        })
+    }
+
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        match message {
+            AppMessage::OperationCloneTargets(targets) => {
+                self.names = targets.iter().map(|target| target.name.clone()).collect();
+                self.targets = targets;
+            }
+            AppMessage::OperationCloneSelectTarget(index) => self.target = Some(index),
+            AppMessage::OperationCloneToggleGrow(toggle) => self.grow = toggle,
+            AppMessage::PerformOperation(drive) => {
+                // The partitions to wait for once the copy is rescanned
+                let partitions = match self.source {
+                    CloneSource::Drive(_) => drive
+                        .partitions
+                        .iter()
+                        .filter(|block| block.partition.is_some())
+                        .count(),
+                    CloneSource::Partition(_) => 0,
+                };
+                let source = match self.source {
+                    CloneSource::Drive(_) => drive.block.clone().map(|block| {
+                        (
+                            block,
+                            drive
+                                .partitions
+                                .iter()
+                                .filter_map(|block| block.partition.as_ref())
+                                .filter_map(|partition| partition.fs.clone())
+                                .collect::<Vec<_>>(),
+                        )
+                    }),
+                    CloneSource::Partition(offset) => drive
+                        .partitions
+                        .iter()
+                        .find(|block| block.offset == offset)
+                        .and_then(|block| block.partition.as_ref())
+                        .and_then(|partition| {
+                            Some((
+                                partition.block.clone()?,
+                                partition.fs.iter().cloned().collect(),
+                            ))
+                        }),
+                };
+                let target = self
+                    .target
+                    .and_then(|index| self.targets.get(index))
+                    .cloned();
+                if let (Some(client), Some((source, filesystems)), Some(target)) =
+                    (self.client.clone(), source, target)
+                {
+                    let whole_drive = matches!(self.source, CloneSource::Drive(_));
+                    let grow = whole_drive && self.grow && target.capacity > self.size;
+                    let size = self.size;
+                    tasks.push(progress::run(move |mut reporter| async move {
+                        // Reading a mounted file system could copy it in an inconsistent state
+                        let filesystem_count = filesystems.len();
+                        for fs in filesystems {
+                            device::unmount(&fs).await?;
+                        }
+
+                        let (block, block_path, capacity) = match target.free_offset {
+                            Some(offset) => {
+                                let path = client
+                                    .object(target.block_path.clone())
+                                    .unwrap()
+                                    .partition_table()
+                                    .await?
+                                    .create_partition(
+                                        offset,
+                                        size,
+                                        "",
+                                        "",
+                                        udisks2::standard_options(false),
+                                    )
+                                    .await?;
+                                let block = client.object(path.clone()).unwrap().block().await?;
+                                let capacity = block.size().await?;
+                                (block, path, capacity)
+                            }
+                            None => (target.block, target.block_path, target.capacity),
+                        };
+
+                        let mut input = File::from(OwnedFd::from(
+                            source
+                                .open_for_backup(udisks2::standard_options(false))
+                                .await?,
+                        ));
+                        let mut output = File::from(OwnedFd::from(
+                            block
+                                .open_for_restore(udisks2::standard_options(false))
+                                .await?,
+                        ));
+                        reporter.stage("Cloning", size);
+                        reporter
+                            .blocking(move |reporter| {
+                                image::restore(&mut input, &mut output, capacity, reporter)
+                            })
+                            .await?;
+
+                        if whole_drive {
+                            let mut device = File::from(OwnedFd::from(
+                                block
+                                    .open_device("rw", udisks2::standard_options(false))
+                                    .await?,
+                            ));
+                            reporter
+                                .blocking(move |_| {
+                                    gpt::regenerate_ids(&mut device, capacity)?;
+                                    Ok(())
+                                })
+                                .await?;
+                        }
+                        block.rescan(udisks2::standard_options(false)).await?;
+                        settle(&client, &block_path, partitions, filesystem_count).await?;
+
+                        if grow {
+                            grow_last_partition(&client, block_path.clone()).await?;
+                        }
+                        regenerate_fs_uuids(&client, block_path).await
+                    }));
+                }
+            }
+            _ => {}
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        use widget::settings;
+
+        let mut clone = widget::button::destructive("Clone");
+        if self.target.is_some() {
+            clone = clone.on_press(Ok(AppMessage::ConfirmOperation));
+        }
+
+        let mut section = settings::section().add(settings::item(
+            "Target",
+            widget::dropdown(&self.names, self.target, |index| {
+                Ok(AppMessage::OperationCloneSelectTarget(index))
+            }),
+        ));
+        if matches!(self.source, CloneSource::Drive(_)) {
+            section = section.add(settings::item(
+                "Grow Last Partition",
+                widget::toggler(self.grow)
+                    .on_toggle(|toggle| Ok(AppMessage::OperationCloneToggleGrow(toggle))),
+            ));
+        }
+
+        widget::dialog()
+            .title(match self.source {
+                CloneSource::Drive(_) => "Clone Drive",
+                CloneSource::Partition(_) => "Clone Partition",
+            })
+            .body(if self.targets.is_empty() {
+                "There is no unused device or free space large enough to clone onto."
+            } else {
+                "All data on the target will be overwritten, the copy gets new UUIDs so both can be attached at once."
+            })
+            .control(section)
+            .primary_action(clone)
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
@@ -1,22 +1,44 @@
-use crate::app::{error::Error, message::AppMessage};
+use crate::app::erase::{EraseMethod, EraseSupport};
+use crate::app::image_table::{self, TableKind};
+use crate::app::{device, error::Error, message::AppMessage, progress};
 use cosmic::{prelude::*, widget};
 
-#[derive(Debug, Clone)]
 pub struct DriveFormat {
+    client: Option<udisks2::Client>,
+    support: EraseSupport,
+    methods: Vec<EraseMethod>,
+    method_names: Vec<String>,
     erase: Option<usize>,
     ptable: Option<usize>,
 }
 
 impl DriveFormat {
-    pub fn new() -> Self {
+    pub fn new(support: EraseSupport) -> Self {
+        let methods = EraseMethod::available(support, true);
         Self {
+            client: None,
+            support,
+            method_names: methods.iter().map(|method| method.name()).collect(),
+            methods,
             erase: Some(0),
             ptable: Some(0),
         }
     }
+
+    fn method(&self) -> EraseMethod {
+        self.erase
+            .and_then(|index| self.methods.get(index))
+            .copied()
+            .unwrap_or(EraseMethod::None)
+    }
 }
 
 impl super::OperationDialog for DriveFormat {
+    fn init(&mut self, client: udisks2::Client) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        self.client = Some(client);
+        cosmic::Task::none()
+    }
+
     fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
         let mut tasks = Vec::new();
         match message {
@@ -25,18 +47,53 @@ impl super::OperationDialog for DriveFormat {
                 self.ptable = Some(table_type)
             }
             AppMessage::PerformOperation(drive) => {
-                let block = drive.block.clone();
-                let erase = self.erase.unwrap();
+                let method = self.method();
                 let ptable = self.ptable.unwrap();
-                tasks.push(cosmic::task::future(async move {
-                    let mut options = udisks2::standard_options(false);
-                    if erase == 1 {
-                        use udisks2::zbus::zvariant;
-                        options.insert(
-                            "erase",
-                            zvariant::Value::Str(zvariant::Str::from_static("zero")),
-                        );
+                if let Some(path) = drive.image_file {
+                    tasks.push(cosmic::task::future(async move {
+                        let mut file = std::fs::OpenOptions::new()
+                            .read(true)
+                            .write(true)
+                            .open(&path)?;
+                        if method == EraseMethod::Zero {
+                            // Truncating and growing again zeroes the image without writing it
+                            let len = file.metadata()?.len();
+                            file.set_len(0)?;
+                            file.set_len(len)?;
+                        }
+                        let kind = match ptable {
+                            0 => Some(TableKind::Gpt),
+                            1 => Some(TableKind::Mbr),
+                            _ => None,
+                        };
+                        image_table::create(&mut file, kind)?;
+                        Ok(AppMessage::OperationFinish)
+                    }));
+                }
+                let (Some(client), Some(block)) = (self.client.clone(), drive.block) else {
+                    return cosmic::app::Task::batch(tasks);
+                };
+                let drive_path = drive.drive_path();
+                let nvme = drive.nvme;
+                let capacity = drive.capacity;
+                tasks.push(progress::run(move |mut reporter| async move {
+                    if let Some(drive_path) = drive_path {
+                        for fs in device::drive_filesystems(&client, &drive_path).await? {
+                            let _ = fs.unmount(udisks2::standard_options(false)).await;
+                        }
+                        method
+                            .erase_drive(
+                                &client,
+                                &drive_path,
+                                nvme.as_ref(),
+                                &mut reporter,
+                                capacity,
+                            )
+                            .await?;
                     }
+
+                    let mut options = udisks2::standard_options(false);
+                    method.format_options(&mut options);
                     let ptype = match ptable {
                         0 => "gpt",
                         1 => "dos",
@@ -44,8 +101,7 @@ impl super::OperationDialog for DriveFormat {
                     };
 
                     block.format(ptype, options).await?;
-
-                    Ok(AppMessage::OperationFinish)
+                    Ok(())
                 }));
             }
             _ => {}
@@ -56,34 +112,41 @@ impl super::OperationDialog for DriveFormat {
     fn dialog(&self) -> Element<Result<AppMessage, crate::app::error::Error>> {
         use widget::settings;
 
+        let mut section = settings::section().add(settings::item(
+            "Erase Mode",
+            widget::dropdown(&self.method_names, self.erase, |index| {
+                Ok(AppMessage::OperationDriveFormatEraseMode(index))
+            }),
+        ));
+        if let Some(estimate) = self.method().estimated_time(self.support) {
+            section = section.add(settings::item(
+                "Estimated Time",
+                widget::text::body(estimate),
+            ));
+        }
+        if let EraseMethod::Sanitize(_) = self.method() {
+            section = section.add(widget::text::caption(
+                "udisks does not report which sanitize methods the drive supports, it rejects the ones it does not.",
+            ));
+        }
+
         widget::dialog()
             .title("Format Drive")
             .body(
                 "This operation is not reversible, make sure you back up any important user data!",
             )
-            .control(
-                settings::section()
-                    .add(settings::item(
-                        "Erase Mode",
-                        widget::dropdown(
-                            &["Quick (less secure, faster)", "Full (more secure, slower)"],
-                            self.erase,
-                            |index| Ok(AppMessage::OperationDriveFormatEraseMode(index)),
-                        ),
-                    ))
-                    .add(settings::item(
-                        "Partitioning Method",
-                        widget::dropdown(
-                            &[
-                                "GUID Partition Table (Modern)",
-                                "Master Boot Record (Legacy)",
-                                "Empty",
-                            ],
-                            self.ptable,
-                            |index| Ok(AppMessage::OperationDriveFormatPTableType(index)),
-                        ),
-                    )),
-            )
+            .control(section.add(settings::item(
+                "Partitioning Method",
+                widget::dropdown(
+                    &[
+                        "GUID Partition Table (Modern)",
+                        "Master Boot Record (Legacy)",
+                        "Empty",
+                    ],
+                    self.ptable,
+                    |index| Ok(AppMessage::OperationDriveFormatPTableType(index)),
+                ),
+            )))
             .primary_action(
                 widget::button::destructive("Confirm").on_press(Ok(AppMessage::ConfirmOperation)),
             )
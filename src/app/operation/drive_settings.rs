@@ -0,0 +1,180 @@
+use cosmic::{prelude::*, widget};
+
+use crate::app::power::{PowerSettings, AAM_LEVELS, APM_LEVELS, STANDBY_TIMEOUTS};
+use crate::app::{error::Error, message::AppMessage};
+
+/// The levels of a table, plus a configured value missing from it so it is kept unless changed.
+fn levels(table: &[(i32, &str)], configured: Option<i32>) -> Vec<(i32, String)> {
+    let mut levels: Vec<_> = table
+        .iter()
+        .map(|(level, name)| (*level, name.to_string()))
+        .collect();
+    if let Some(value) = configured.filter(|value| table.iter().all(|(level, _)| level != value)) {
+        levels.push((value, format!("Custom ({value})")));
+    }
+    levels
+}
+
+/// Names for a dropdown with the drive default in front of the given levels.
+fn names(levels: &[(i32, String)]) -> Vec<String> {
+    std::iter::once("Drive Default".to_string())
+        .chain(levels.iter().map(|(_, name)| name.clone()))
+        .collect()
+}
+
+fn index(levels: &[(i32, String)], value: Option<i32>) -> usize {
+    value
+        .and_then(|value| levels.iter().position(|(level, _)| *level == value))
+        .map_or(0, |index| index + 1)
+}
+
+fn value(levels: &[(i32, String)], index: usize) -> Option<i32> {
+    index.checked_sub(1).map(|index| levels[index].0)
+}
+
+pub struct DriveSettings {
+    settings: PowerSettings,
+    standby_levels: Vec<(i32, String)>,
+    apm_levels: Vec<(i32, String)>,
+    aam_levels: Vec<(i32, String)>,
+    standby_names: Vec<String>,
+    apm_names: Vec<String>,
+    aam_names: Vec<String>,
+}
+
+impl DriveSettings {
+    pub fn new(settings: PowerSettings) -> Self {
+        let standby_levels = levels(&STANDBY_TIMEOUTS, settings.standby);
+        let apm_levels = levels(&APM_LEVELS, settings.apm);
+        let aam_levels = levels(&AAM_LEVELS, settings.aam);
+        Self {
+            settings,
+            standby_names: names(&standby_levels),
+            apm_names: names(&apm_levels),
+            aam_names: names(&aam_levels),
+            standby_levels,
+            apm_levels,
+            aam_levels,
+        }
+    }
+}
+
+impl super::OperationDialog for DriveSettings {
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        match message {
+            AppMessage::OperationDriveSettingsStandby(index) => {
+                self.settings.standby = value(&self.standby_levels, index)
+            }
+            AppMessage::OperationDriveSettingsApm(index) => {
+                self.settings.apm = value(&self.apm_levels, index)
+            }
+            AppMessage::OperationDriveSettingsAam(index) => {
+                self.settings.aam = value(&self.aam_levels, index)
+            }
+            AppMessage::OperationDriveSettingsWriteCache(index) => {
+                self.settings.write_cache = match index {
+                    1 => Some(true),
+                    2 => Some(false),
+                    _ => None,
+                }
+            }
+            AppMessage::PerformOperation(drive) => {
+                if let Some(proxy) = drive.drive {
+                    let settings = self.settings;
+                    tasks.push(cosmic::task::future(async move {
+                        settings.apply(&proxy).await?;
+                        Ok(AppMessage::OperationFinish)
+                    }));
+                }
+            }
+            _ => {}
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        use widget::settings;
+
+        let mut section = settings::section();
+        if self.settings.standby_supported {
+            section = section.add(settings::item(
+                "Standby Timeout",
+                widget::dropdown(
+                    &self.standby_names,
+                    Some(index(&self.standby_levels, self.settings.standby)),
+                    |index| Ok(AppMessage::OperationDriveSettingsStandby(index)),
+                ),
+            ));
+        }
+        if self.settings.apm_supported {
+            section = section.add(settings::item(
+                "Advanced Power Management",
+                widget::dropdown(
+                    &self.apm_names,
+                    Some(index(&self.apm_levels, self.settings.apm)),
+                    |index| Ok(AppMessage::OperationDriveSettingsApm(index)),
+                ),
+            ));
+        }
+        if self.settings.aam_supported {
+            section = section.add(settings::item(
+                "Acoustic Management",
+                widget::dropdown(
+                    &self.aam_names,
+                    Some(index(&self.aam_levels, self.settings.aam)),
+                    |index| Ok(AppMessage::OperationDriveSettingsAam(index)),
+                ),
+            ));
+        }
+        if self.settings.write_cache_supported {
+            section = section.add(settings::item(
+                "Write Cache",
+                widget::dropdown(
+                    &["Drive Default", "Enabled", "Disabled"],
+                    Some(match self.settings.write_cache {
+                        None => 0,
+                        Some(true) => 1,
+                        Some(false) => 2,
+                    }),
+                    |index| Ok(AppMessage::OperationDriveSettingsWriteCache(index)),
+                ),
+            ));
+        }
+
+        widget::dialog()
+            .title("Drive Settings")
+            .body("The settings are applied now and every time the drive is connected.")
+            .control(section)
+            .primary_action(
+                widget::button::suggested("Apply").on_press(Ok(AppMessage::ConfirmOperation)),
+            )
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_levels() {
+        let apm = levels(&APM_LEVELS, Some(200));
+        assert_eq!(apm.len(), APM_LEVELS.len() + 1);
+        assert_eq!(names(&apm).last().unwrap(), "Custom (200)");
+        // The custom value is selected and survives picking it again
+        let selected = index(&apm, Some(200));
+        assert_eq!(selected, apm.len());
+        assert_eq!(value(&apm, selected), Some(200));
+
+        for configured in [None, Some(128)] {
+            let apm = levels(&APM_LEVELS, configured);
+            assert_eq!(apm.len(), APM_LEVELS.len());
+            assert_eq!(value(&apm, index(&apm, configured)), configured);
+        }
+        assert_eq!(value(&apm, 0), None);
+    }
+}
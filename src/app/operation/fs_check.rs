@@ -0,0 +1,87 @@
+use cosmic::{prelude::*, widget};
+
+use crate::app::filesystem::{self, CheckResult};
+use crate::app::{error::Error, message::AppMessage};
+
+pub struct FilesystemCheck {
+    offset: u64,
+    repair: bool,
+}
+
+impl FilesystemCheck {
+    pub fn new(offset: u64, repair: bool) -> Self {
+        Self { offset, repair }
+    }
+}
+
+impl super::OperationDialog for FilesystemCheck {
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        if let AppMessage::PerformOperation(drive) = message {
+            let fs = drive
+                .partitions
+                .iter()
+                .find(|block| block.offset == self.offset)
+                .and_then(|block| block.partition.as_ref())
+                .and_then(|partition| partition.fs.clone());
+            let repair = self.repair;
+            if let Some(fs) = fs {
+                tasks.push(cosmic::task::future(async move {
+                    let (result, remount) = filesystem::check(&fs, repair).await?;
+                    Ok(AppMessage::FilesystemChecked(result, remount))
+                }));
+            }
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        let (title, body, confirm) = match self.repair {
+            false => (
+                "Check Filesystem",
+                "The file system is unmounted while it is checked and mounted again afterwards.",
+                widget::button::suggested("Check"),
+            ),
+            true => (
+                "Repair Filesystem",
+                "The file system is unmounted while it is repaired and mounted again afterwards. Repairing can lose data in damaged areas, back up important data first.",
+                widget::button::destructive("Repair"),
+            ),
+        };
+        widget::dialog()
+            .title(title)
+            .body(body)
+            .primary_action(confirm.on_press(Ok(AppMessage::ConfirmOperation)))
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
+
+/// Shows the outcome of a check or repair once it finished.
+pub struct FilesystemCheckResult {
+    result: CheckResult,
+}
+
+impl FilesystemCheckResult {
+    pub fn new(result: CheckResult) -> Self {
+        Self { result }
+    }
+}
+
+impl super::OperationDialog for FilesystemCheckResult {
+    fn update(&mut self, _message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        cosmic::Task::none()
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        widget::dialog()
+            .title(self.result.title())
+            .body(self.result.description())
+            .primary_action(
+                widget::button::suggested("Close").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
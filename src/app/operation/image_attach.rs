@@ -0,0 +1,141 @@
+use std::path::PathBuf;
+
+use cosmic::dialog::file_chooser;
+use cosmic::{prelude::*, widget};
+
+use crate::app::{error::Error, loop_device::AttachOptions, message::AppMessage};
+
+pub struct ImageAttach {
+    path: Option<PathBuf>,
+    read_only: bool,
+    offset: String,
+    size: String,
+    autoclear: bool,
+    /// Edit the partition table of the file directly instead of attaching it
+    offline: bool,
+}
+
+impl ImageAttach {
+    pub fn new() -> Self {
+        Self {
+            path: None,
+            read_only: true,
+            offset: "0".to_string(),
+            size: "0".to_string(),
+            autoclear: false,
+            offline: false,
+        }
+    }
+
+    fn options(&self) -> Option<AttachOptions> {
+        Some(AttachOptions {
+            path: self.path.clone()?,
+            read_only: self.read_only,
+            offset: self.offset.trim().parse().ok()?,
+            size: self.size.trim().parse().ok()?,
+            autoclear: self.autoclear,
+        })
+    }
+}
+
+impl super::OperationDialog for ImageAttach {
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        match message {
+            AppMessage::OperationImageChoosePath => {
+                tasks.push(cosmic::task::future(async move {
+                    let dialog = file_chooser::open::Dialog::new().title("Attach Disk Image");
+                    match dialog.open_file().await {
+                        Ok(response) => Ok(AppMessage::OperationImagePath(
+                            response.url().to_file_path().ok(),
+                        )),
+                        Err(file_chooser::Error::Cancelled) => Ok(AppMessage::NoOp),
+                        Err(err) => Err(Error::new(err.to_string(), true)),
+                    }
+                }));
+            }
+            AppMessage::OperationImagePath(path) => self.path = path,
+            AppMessage::OperationImageToggleReadOnly(toggle) => self.read_only = toggle,
+            AppMessage::OperationImageOffsetUpdate(input) => self.offset = input,
+            AppMessage::OperationImageSizeUpdate(input) => self.size = input,
+            AppMessage::OperationImageToggleAutoclear(toggle) => self.autoclear = toggle,
+            AppMessage::OperationImageToggleOffline(toggle) => self.offline = toggle,
+            _ => {}
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        use widget::settings;
+
+        let mut section = settings::section()
+            .add(settings::item(
+                "Image",
+                widget::button::standard(match &self.path {
+                    Some(path) => path.to_string_lossy().to_string(),
+                    None => "Choose...".to_string(),
+                })
+                .on_press(Ok(AppMessage::OperationImageChoosePath)),
+            ))
+            .add(settings::item(
+                "Edit Partition Table Only",
+                widget::toggler(self.offline)
+                    .on_toggle(|toggle| Ok(AppMessage::OperationImageToggleOffline(toggle))),
+            ));
+
+        if self.offline {
+            let mut open = widget::button::suggested("Open");
+            if let Some(path) = self.path.clone() {
+                open = open.on_press(Ok(AppMessage::OpenImageFile(path)));
+            }
+            return widget::dialog()
+                .title("Open Disk Image")
+                .body("Read and change the partition table inside the image file, without a loop device.")
+                .control(section)
+                .primary_action(open)
+                .secondary_action(
+                    widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+                )
+                .into();
+        }
+
+        let mut attach = widget::button::suggested("Attach");
+        if let Some(options) = self.options() {
+            attach = attach.on_press(Ok(AppMessage::AttachImage(options)));
+        }
+
+        widget::dialog()
+            .title("Attach Disk Image")
+            .body("Set up a loop device so the image can be inspected like a drive.")
+            .control(
+                section
+                    .add(settings::item(
+                        "Read Only",
+                        widget::toggler(self.read_only).on_toggle(|toggle| {
+                            Ok(AppMessage::OperationImageToggleReadOnly(toggle))
+                        }),
+                    ))
+                    .add(settings::item(
+                        "Offset (Bytes)",
+                        widget::text_input("0", &self.offset)
+                            .on_input(|input| Ok(AppMessage::OperationImageOffsetUpdate(input))),
+                    ))
+                    .add(settings::item(
+                        "Size (Bytes, 0 for the Rest)",
+                        widget::text_input("0", &self.size)
+                            .on_input(|input| Ok(AppMessage::OperationImageSizeUpdate(input))),
+                    ))
+                    .add(settings::item(
+                        "Detach When Unused",
+                        widget::toggler(self.autoclear).on_toggle(|toggle| {
+                            Ok(AppMessage::OperationImageToggleAutoclear(toggle))
+                        }),
+                    )),
+            )
+            .primary_action(attach)
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
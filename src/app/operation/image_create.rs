@@ -0,0 +1,188 @@
+use std::fs::File;
+use std::os::fd::OwnedFd;
+use std::path::PathBuf;
+
+use cosmic::dialog::file_chooser;
+use cosmic::{prelude::*, widget};
+
+use crate::app::{error::Error, image, message::AppMessage, progress};
+
+pub struct ImageCreate {
+    /// Offset of the partition to image, the whole drive when empty
+    block_offset: Option<u64>,
+    path: Option<PathBuf>,
+    compression: image::Compression,
+    compression_names: Vec<&'static str>,
+    sparse: bool,
+    checksum: bool,
+}
+
+impl ImageCreate {
+    pub fn new(block_offset: Option<u64>) -> Self {
+        Self {
+            block_offset,
+            path: None,
+            compression: image::Compression::None,
+            compression_names: image::Compression::ALL
+                .iter()
+                .map(|compression| compression.name())
+                .collect(),
+            sparse: true,
+            checksum: true,
+        }
+    }
+}
+
+impl super::OperationDialog for ImageCreate {
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        match message {
+            AppMessage::OperationImageChoosePath => {
+                let file_name = self
+                    .compression
+                    .with_extension(PathBuf::from("disk.img"))
+                    .to_string_lossy()
+                    .to_string();
+                tasks.push(cosmic::task::future(async move {
+                    let dialog = file_chooser::save::Dialog::new()
+                        .title("Create Disk Image")
+                        .file_name(file_name);
+                    match dialog.save_file().await {
+                        Ok(response) => Ok(match response.url() {
+                            Some(url) => AppMessage::OperationImagePath(url.to_file_path().ok()),
+                            None => AppMessage::NoOp,
+                        }),
+                        Err(file_chooser::Error::Cancelled) => Ok(AppMessage::NoOp),
+                        Err(err) => Err(Error::new(err.to_string(), true)),
+                    }
+                }));
+            }
+            AppMessage::OperationImagePath(path) => {
+                self.path = path.map(|path| self.compression.with_extension(path));
+            }
+            AppMessage::OperationImageSelectCompression(index) => {
+                self.compression = image::Compression::ALL[index];
+                self.path = self.path.take().map(|path| {
+                    // Swap the extension of the previous choice for the new one
+                    let path = match image::Compression::for_path(&path) {
+                        image::Compression::None => path,
+                        _ => path.with_extension(""),
+                    };
+                    self.compression.with_extension(path)
+                });
+            }
+            AppMessage::OperationImageToggleSparse(toggle) => self.sparse = toggle,
+            AppMessage::OperationImageToggleChecksum(toggle) => self.checksum = toggle,
+            AppMessage::PerformOperation(drive) => {
+                let block = match self.block_offset {
+                    Some(offset) => drive
+                        .partitions
+                        .iter()
+                        .find(|block| block.offset == offset)
+                        .and_then(|block| block.partition.as_ref())
+                        .and_then(|partition| partition.block.clone()),
+                    None => drive.block.clone(),
+                };
+                if let (Some(block), Some(path)) = (block, self.path.clone()) {
+                    let compression = self.compression;
+                    let sparse = self.sparse;
+                    let checksum = self.checksum;
+                    tasks.push(progress::run(move |mut reporter| async move {
+                        let size = block.size().await?;
+                        let mut device = File::from(OwnedFd::from(
+                            block
+                                .open_for_backup(udisks2::standard_options(false))
+                                .await?,
+                        ));
+                        reporter.stage("Creating Disk Image", size);
+                        reporter
+                            .blocking(move |reporter| {
+                                let mut file = File::create(&path)?;
+                                let result = match compression {
+                                    image::Compression::None => image::create(
+                                        &mut device,
+                                        &mut file,
+                                        size,
+                                        sparse,
+                                        reporter,
+                                    ),
+                                    compression => image::compress(
+                                        &mut device,
+                                        file,
+                                        size,
+                                        compression,
+                                        reporter,
+                                    ),
+                                };
+                                match result {
+                                    Ok(digest) if checksum => image::write_checksum(&path, &digest),
+                                    Ok(_) => Ok(()),
+                                    Err(err) => {
+                                        // Do not leave a truncated image behind
+                                        let _ = std::fs::remove_file(&path);
+                                        Err(err)
+                                    }
+                                }
+                            })
+                            .await
+                    }));
+                }
+            }
+            _ => {}
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        use widget::settings;
+
+        let mut create = widget::button::suggested("Create");
+        if self.path.is_some() {
+            create = create.on_press(Ok(AppMessage::ConfirmOperation));
+        }
+
+        let mut section = settings::section()
+            .add(settings::item(
+                "Destination",
+                widget::button::standard(match &self.path {
+                    Some(path) => path.to_string_lossy().to_string(),
+                    None => "Choose...".to_string(),
+                })
+                .on_press(Ok(AppMessage::OperationImageChoosePath)),
+            ))
+            .add(settings::item(
+                "Compression",
+                widget::dropdown(
+                    &self.compression_names,
+                    image::Compression::ALL
+                        .iter()
+                        .position(|compression| *compression == self.compression),
+                    |index| Ok(AppMessage::OperationImageSelectCompression(index)),
+                ),
+            ));
+        // Compressed streams have no holes to skip, zeros compress to almost nothing anyway
+        if self.compression == image::Compression::None {
+            section = section.add(settings::item(
+                "Skip Empty Regions",
+                widget::toggler(self.sparse)
+                    .on_toggle(|toggle| Ok(AppMessage::OperationImageToggleSparse(toggle))),
+            ));
+        }
+
+        widget::dialog()
+            .title("Create Disk Image")
+            .body("Copy every byte of the device into an image file.")
+            .control(
+                section.add(settings::item(
+                    "Write SHA-256 Checksum",
+                    widget::toggler(self.checksum)
+                        .on_toggle(|toggle| Ok(AppMessage::OperationImageToggleChecksum(toggle))),
+                )),
+            )
+            .primary_action(create)
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
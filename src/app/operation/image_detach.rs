@@ -0,0 +1,51 @@
+use cosmic::{prelude::*, widget};
+
+use crate::app::{device, error::Error, message::AppMessage};
+
+pub struct ImageDetach;
+
+impl ImageDetach {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl super::OperationDialog for ImageDetach {
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        if let AppMessage::PerformOperation(drive) = message {
+            tasks.push(cosmic::task::future(async move {
+                let Some(r#loop) = drive.r#loop else {
+                    return Err(Error::new("Only attached images can be detached", true));
+                };
+                for fs in drive
+                    .partitions
+                    .iter()
+                    .filter_map(|block| block.partition.as_ref())
+                    .filter_map(|partition| partition.fs.as_ref())
+                {
+                    device::unmount(fs).await?;
+                }
+                r#loop.delete(udisks2::standard_options(false)).await?;
+                Ok(match drive.block_path {
+                    Some(block_path) => AppMessage::DriveRemoved(block_path),
+                    None => AppMessage::OperationFinish,
+                })
+            }));
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        widget::dialog()
+            .title("Detach Disk Image")
+            .body("File systems of the image are unmounted and its loop device is removed, the image file is kept.")
+            .primary_action(
+                widget::button::suggested("Detach").on_press(Ok(AppMessage::ConfirmOperation)),
+            )
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
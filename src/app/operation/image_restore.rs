@@ -0,0 +1,227 @@
+use std::fs::File;
+use std::os::fd::OwnedFd;
+use std::path::PathBuf;
+
+use cosmic::dialog::file_chooser;
+use cosmic::{prelude::*, widget};
+
+use crate::app::image::{self, ImageSize};
+use crate::app::{device, error::Error, message::AppMessage, progress};
+
+pub struct ImageRestore {
+    client: Option<udisks2::Client>,
+    /// Offset of the partition to restore onto, the whole drive when empty
+    block_offset: Option<u64>,
+    capacity: u64,
+    path: Option<PathBuf>,
+    /// Size of the decompressed image, unknown for some compressed formats
+    image_size: Option<ImageSize>,
+    verify: bool,
+}
+
+impl ImageRestore {
+    pub fn new(block_offset: Option<u64>, capacity: u64) -> Self {
+        Self {
+            client: None,
+            block_offset,
+            capacity,
+            path: None,
+            image_size: None,
+            verify: true,
+        }
+    }
+
+    fn size_for_display(&self, size: u64) -> String {
+        match &self.client {
+            Some(client) => client.size_for_display(size, true, false),
+            None => format!("{size} B"),
+        }
+    }
+
+    fn fits(&self) -> bool {
+        self.image_size
+            .map_or(true, |size| size.bytes() <= self.capacity)
+    }
+}
+
+impl super::OperationDialog for ImageRestore {
+    fn init(&mut self, client: udisks2::Client) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        self.client = Some(client);
+        cosmic::Task::none()
+    }
+
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        match message {
+            AppMessage::OperationImageChoosePath => {
+                tasks.push(cosmic::task::future(async move {
+                    let dialog = file_chooser::open::Dialog::new().title("Restore Disk Image");
+                    match dialog.open_file().await {
+                        Ok(response) => Ok(AppMessage::OperationImagePath(
+                            response.url().to_file_path().ok(),
+                        )),
+                        Err(file_chooser::Error::Cancelled) => Ok(AppMessage::NoOp),
+                        Err(err) => Err(Error::new(err.to_string(), true)),
+                    }
+                }));
+            }
+            AppMessage::OperationImagePath(path) => {
+                self.image_size = path.as_deref().and_then(image::uncompressed_size);
+                self.path = path;
+            }
+            AppMessage::OperationImageToggleVerify(toggle) => self.verify = toggle,
+            AppMessage::PerformOperation(drive) => {
+                let target = match self.block_offset {
+                    Some(offset) => drive
+                        .partitions
+                        .iter()
+                        .find(|block| block.offset == offset)
+                        .and_then(|block| block.partition.as_ref())
+                        .and_then(|partition| {
+                            Some((
+                                partition.block.clone()?,
+                                partition.fs.iter().cloned().collect(),
+                            ))
+                        }),
+                    None => drive.block.clone().map(|block| {
+                        (
+                            block,
+                            drive
+                                .partitions
+                                .iter()
+                                .filter_map(|block| block.partition.as_ref())
+                                .filter_map(|partition| partition.fs.clone())
+                                .collect::<Vec<_>>(),
+                        )
+                    }),
+                };
+                if let (Some((block, filesystems)), Some(path)) = (target, self.path.clone()) {
+                    let verify = self.verify;
+                    tasks.push(progress::run(move |mut reporter| async move {
+                        let capacity = block.size().await?;
+                        let image_size = image::uncompressed_size(&path).map(ImageSize::bytes);
+                        if image_size.is_some_and(|size| size > capacity) {
+                            return Err(Error::new(
+                                "The image is larger than the target device",
+                                true,
+                            ));
+                        }
+
+                        // A corrupted image must be caught before it overwrites the device
+                        if let Some(expected) = image::read_checksum(&path) {
+                            reporter.stage("Checking Disk Image", image_size.unwrap_or(capacity));
+                            let image_path = path.clone();
+                            let digest = reporter
+                                .blocking(move |reporter| {
+                                    let mut image = image::open(&image_path)?;
+                                    image::checksum(&mut image, capacity, reporter)
+                                })
+                                .await?;
+                            if digest != expected {
+                                return Err(Error::new(
+                                    "The image does not match its checksum file, it may be corrupted! The device was not changed.",
+                                    true,
+                                ));
+                            }
+                        }
+
+                        for fs in filesystems {
+                            device::unmount(&fs).await?;
+                        }
+
+                        let mut device = File::from(OwnedFd::from(
+                            block
+                                .open_for_restore(udisks2::standard_options(false))
+                                .await?,
+                        ));
+                        // Without a recorded size the capacity is the best estimate there is
+                        reporter.stage("Restoring Disk Image", image_size.unwrap_or(capacity));
+                        let (written, total) = reporter
+                            .blocking(move |reporter| {
+                                let mut image = image::open(&path)?;
+                                image::restore(&mut image, &mut device, capacity, reporter)
+                            })
+                            .await?;
+
+                        if verify {
+                            let mut device = File::from(OwnedFd::from(
+                                block
+                                    .open_for_backup(udisks2::standard_options(false))
+                                    .await?,
+                            ));
+                            reporter.stage("Verifying Disk Image", total);
+                            let read_back = reporter
+                                .blocking(move |reporter| {
+                                    image::digest(&mut device, total, reporter)
+                                })
+                                .await?;
+                            if read_back != written {
+                                return Err(Error::new(
+                                    "Verification failed, the data read back from the device does not match the image!",
+                                    true,
+                                ));
+                            }
+                        }
+
+                        // Pick up the restored partition table
+                        block.rescan(udisks2::standard_options(false)).await?;
+                        Ok(())
+                    }));
+                }
+            }
+            _ => {}
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        use widget::settings;
+
+        let mut restore = widget::button::destructive("Restore");
+        if self.path.is_some() && self.fits() {
+            restore = restore.on_press(Ok(AppMessage::ConfirmOperation));
+        }
+
+        let mut section = settings::section().add(settings::item(
+            "Image",
+            widget::button::standard(match &self.path {
+                Some(path) => path.to_string_lossy().to_string(),
+                None => "Choose...".to_string(),
+            })
+            .on_press(Ok(AppMessage::OperationImageChoosePath)),
+        ));
+        if self.path.is_some() {
+            section = section.add(settings::item(
+                "Image Size",
+                widget::text::body(match self.image_size {
+                    Some(ImageSize::Exact(size)) => self.size_for_display(size),
+                    Some(ImageSize::AtLeast(size)) => {
+                        format!("At Least {}", self.size_for_display(size))
+                    }
+                    // Gzip does not record the size, it is enforced while writing instead
+                    None => "Unknown".to_string(),
+                }),
+            ));
+        }
+
+        widget::dialog()
+            .title("Restore Disk Image")
+            .body(if self.fits() {
+                "All data on the device will be overwritten by the image, this operation is not reversible!"
+            } else {
+                "The image is larger than the device, choose a larger target."
+            })
+            .control(
+                section.add(settings::item(
+                    "Verify After Writing",
+                    widget::toggler(self.verify)
+                        .on_toggle(|toggle| Ok(AppMessage::OperationImageToggleVerify(toggle))),
+                )),
+            )
+            .primary_action(restore)
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
@@ -0,0 +1,203 @@
+use std::fs::File;
+use std::os::fd::OwnedFd;
+use std::path::PathBuf;
+
+use cosmic::dialog::file_chooser;
+use cosmic::{prelude::*, widget};
+
+use crate::app::device::{self, DriveTarget};
+use crate::app::{error::Error, image, message::AppMessage, progress};
+
+pub struct IsoWrite {
+    client: Option<udisks2::Client>,
+    path: Option<PathBuf>,
+    label: Option<String>,
+    size: u64,
+    targets: Vec<DriveTarget>,
+    names: Vec<String>,
+    target: Option<usize>,
+}
+
+impl IsoWrite {
+    pub fn new() -> Self {
+        Self {
+            client: None,
+            path: None,
+            label: None,
+            size: 0,
+            targets: Vec::new(),
+            names: Vec::new(),
+            target: None,
+        }
+    }
+
+    fn size_for_display(&self, size: u64) -> String {
+        match &self.client {
+            Some(client) => client.size_for_display(size, true, false),
+            None => format!("{size} B"),
+        }
+    }
+
+    fn fits(&self) -> bool {
+        self.target
+            .and_then(|index| self.targets.get(index))
+            .map_or(true, |target| self.size <= target.capacity)
+    }
+}
+
+impl super::OperationDialog for IsoWrite {
+    fn init(&mut self, client: udisks2::Client) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        self.client = Some(client.clone());
+        cosmic::task::future(async move {
+            Ok(AppMessage::OperationIsoTargets(
+                device::drive_targets(&client, true).await?,
+            ))
+        })
+    }
+
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        match message {
+            AppMessage::OperationIsoTargets(targets) => {
+                self.names = targets.iter().map(|target| target.name.clone()).collect();
+                self.target = (!targets.is_empty()).then_some(0);
+                self.targets = targets;
+            }
+            AppMessage::OperationIsoSelectTarget(index) => self.target = Some(index),
+            AppMessage::OperationImageChoosePath => {
+                tasks.push(cosmic::task::future(async move {
+                    let dialog = file_chooser::open::Dialog::new()
+                        .title("Write ISO to Drive")
+                        .filter(file_chooser::FileFilter::new("ISO Images").glob("*.iso"));
+                    match dialog.open_file().await {
+                        Ok(response) => Ok(AppMessage::OperationImagePath(
+                            response.url().to_file_path().ok(),
+                        )),
+                        Err(file_chooser::Error::Cancelled) => Ok(AppMessage::NoOp),
+                        Err(err) => Err(Error::new(err.to_string(), true)),
+                    }
+                }));
+            }
+            AppMessage::OperationImagePath(path) => {
+                self.label = path.as_deref().and_then(image::iso_label);
+                self.size = path
+                    .as_ref()
+                    .and_then(|path| std::fs::metadata(path).ok())
+                    .map_or(0, |metadata| metadata.len());
+                self.path = path;
+            }
+            AppMessage::PerformOperation(_) => {
+                let target = self
+                    .target
+                    .and_then(|index| self.targets.get(index))
+                    .cloned();
+                if let (Some(client), Some(target), Some(path)) =
+                    (self.client.clone(), target, self.path.clone())
+                {
+                    tasks.push(progress::run(move |mut reporter| async move {
+                        for fs in device::drive_filesystems(&client, &target.drive_path).await? {
+                            device::unmount(&fs).await?;
+                        }
+
+                        let mut device = File::from(OwnedFd::from(
+                            target
+                                .block
+                                .open_for_restore(udisks2::standard_options(false))
+                                .await?,
+                        ));
+                        let size = std::fs::metadata(&path)?.len();
+                        let capacity = target.capacity;
+                        reporter.stage("Writing ISO Image", size);
+                        let (written, total) = reporter
+                            .blocking(move |reporter| {
+                                let mut image = File::open(&path)?;
+                                image::restore(&mut image, &mut device, capacity, reporter)
+                            })
+                            .await?;
+
+                        let mut device = File::from(OwnedFd::from(
+                            target
+                                .block
+                                .open_for_backup(udisks2::standard_options(false))
+                                .await?,
+                        ));
+                        reporter.stage("Verifying ISO Image", total);
+                        let read_back = reporter
+                            .blocking(move |reporter| image::digest(&mut device, total, reporter))
+                            .await?;
+                        if read_back != written {
+                            return Err(Error::new(
+                                "Verification failed, the data read back from the drive does not match the image!",
+                                true,
+                            ));
+                        }
+
+                        // The drive is ready to be unplugged, once the desktop's auto-mounts are gone
+                        device::release_drive(&client, &target.drive_path).await?;
+                        target
+                            .drive
+                            .power_off(udisks2::standard_options(false))
+                            .await?;
+                        reporter
+                            .message(AppMessage::DriveRemoved(target.block_path))
+                            .await;
+                        Ok(())
+                    }));
+                }
+            }
+            _ => {}
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        use widget::settings;
+
+        let mut write = widget::button::destructive("Write");
+        if self.path.is_some() && self.target.is_some() && self.fits() {
+            write = write.on_press(Ok(AppMessage::ConfirmOperation));
+        }
+
+        let mut section = settings::section().add(settings::item(
+            "Image",
+            widget::button::standard(match &self.path {
+                Some(path) => path.to_string_lossy().to_string(),
+                None => "Choose...".to_string(),
+            })
+            .on_press(Ok(AppMessage::OperationImageChoosePath)),
+        ));
+        if self.path.is_some() {
+            section = section
+                .add(settings::item(
+                    "Volume Label",
+                    widget::text::body(self.label.as_deref().unwrap_or("None")),
+                ))
+                .add(settings::item(
+                    "Size",
+                    widget::text::body(self.size_for_display(self.size)),
+                ));
+        }
+        section = section.add(settings::item(
+            "Drive",
+            widget::dropdown(&self.names, self.target, |index| {
+                Ok(AppMessage::OperationIsoSelectTarget(index))
+            }),
+        ));
+
+        widget::dialog()
+            .title("Write ISO to Drive")
+            .body(if self.targets.is_empty() {
+                "No removable drive is connected, system drives can not be written to."
+            } else if !self.fits() {
+                "The image is larger than the drive, choose a larger one."
+            } else {
+                "All data on the drive will be overwritten by the image, it is powered off once verified."
+            })
+            .control(section)
+            .primary_action(write)
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
@@ -0,0 +1,134 @@
+use cosmic::{prelude::*, widget};
+
+use crate::app::{error::Error, lvm, message::AppMessage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogicalVolumeCreateKind {
+    Linear,
+    ThinPool,
+    /// Thin volume inside the pool at the given index of the volume group
+    Thin(usize),
+}
+
+pub struct LogicalVolumeCreate {
+    kind: LogicalVolumeCreateKind,
+    name: String,
+    /// `None` while the entered size is invalid
+    size: Option<u64>,
+    max_size: u64,
+    size_string: String,
+}
+
+impl LogicalVolumeCreate {
+    pub fn new(kind: LogicalVolumeCreateKind, max_size: u64) -> Self {
+        Self {
+            kind,
+            name: "".to_string(),
+            size: None,
+            max_size,
+            size_string: String::new(),
+        }
+    }
+}
+
+impl super::OperationDialog for LogicalVolumeCreate {
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        match message {
+            AppMessage::OperationLogicalVolumeNameUpdate(name) => self.name = name,
+            AppMessage::OperationLogicalVolumeSizeUpdate(input) => {
+                self.size = super::parse_size(&input, 512, self.max_size);
+                self.size_string = input;
+            }
+            AppMessage::OperationLogicalVolumeSizeSave => {
+                if let Some(size) = self.size {
+                    self.size_string = size.to_string();
+                }
+            }
+            AppMessage::PerformVolumeGroupOperation(vg) => {
+                let (Some(size), true) = (self.size, lvm::is_valid_name(&self.name)) else {
+                    return cosmic::Task::none();
+                };
+                let name = self.name.clone();
+                let pool = match self.kind {
+                    LogicalVolumeCreateKind::Thin(index) => {
+                        vg.volumes.get(index).map(|pool| pool.lv_path.clone())
+                    }
+                    _ => None,
+                };
+                let kind = self.kind;
+                tasks.push(cosmic::task::future(async move {
+                    let options = udisks2::standard_options(false);
+                    match (kind, pool) {
+                        (LogicalVolumeCreateKind::Linear, _) => {
+                            vg.vg.create_plain_volume(&name, size, options).await?;
+                        }
+                        (LogicalVolumeCreateKind::ThinPool, _) => {
+                            vg.vg.create_thin_pool_volume(&name, size, options).await?;
+                        }
+                        (LogicalVolumeCreateKind::Thin(_), Some(pool)) => {
+                            vg.vg
+                                .create_thin_volume(&name, size, &pool, options)
+                                .await?;
+                        }
+                        (LogicalVolumeCreateKind::Thin(_), None) => {
+                            return Err(Error::new("The thin pool no longer exists", true));
+                        }
+                    }
+                    Ok(AppMessage::OperationFinish)
+                }));
+            }
+            _ => {}
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        use widget::settings;
+        let (title, body) = match self.kind {
+            LogicalVolumeCreateKind::Linear => (
+                "Create Logical Volume",
+                "Create a linear logical volume in the free space of the volume group",
+            ),
+            LogicalVolumeCreateKind::ThinPool => (
+                "Create Thin Pool",
+                "Create a pool that thin volumes can allocate space from on demand",
+            ),
+            LogicalVolumeCreateKind::Thin(_) => (
+                "Create Thin Volume",
+                "Create a thin volume, its size may exceed the space left in the pool",
+            ),
+        };
+
+        let mut create = widget::button::suggested("Create");
+        if self.size.is_some() && lvm::is_valid_name(&self.name) {
+            create = create.on_press(Ok(AppMessage::ConfirmOperation));
+        }
+
+        widget::dialog()
+            .title(title)
+            .body(body)
+            .control(
+                settings::section()
+                    .add(settings::item(
+                        "Name",
+                        widget::text_input("", &self.name).on_input(|input| {
+                            Ok(AppMessage::OperationLogicalVolumeNameUpdate(input))
+                        }),
+                    ))
+                    .add(settings::item(
+                        "Size",
+                        widget::text_input("", &self.size_string)
+                            .on_input(|input| {
+                                Ok(AppMessage::OperationLogicalVolumeSizeUpdate(input))
+                            })
+                            .on_submit(Ok(AppMessage::OperationLogicalVolumeSizeSave)),
+                    )),
+            )
+            .primary_action(create)
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
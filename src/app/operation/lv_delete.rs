@@ -0,0 +1,46 @@
+use cosmic::{prelude::*, widget};
+
+use crate::app::{error::Error, message::AppMessage};
+
+pub struct LogicalVolumeDelete {
+    index: usize,
+}
+
+impl LogicalVolumeDelete {
+    pub fn new(index: usize) -> Self {
+        Self { index }
+    }
+}
+
+impl super::OperationDialog for LogicalVolumeDelete {
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        if let AppMessage::PerformVolumeGroupOperation(vg) = message {
+            if let Some(volume) = vg.volumes.get(self.index) {
+                let lv = volume.lv.clone();
+                let fs = volume.fs.clone();
+                tasks.push(cosmic::task::future(async move {
+                    if let Some(fs) = fs {
+                        let _ = fs.unmount(udisks2::standard_options(false)).await;
+                    }
+                    lv.delete(udisks2::standard_options(false)).await?;
+                    Ok(AppMessage::OperationFinish)
+                }));
+            }
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        widget::dialog()
+            .title("Delete Logical Volume")
+            .body("This operation is not reversible, all data on the volume will be lost!")
+            .primary_action(
+                widget::button::destructive("Delete").on_press(Ok(AppMessage::ConfirmOperation)),
+            )
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
@@ -0,0 +1,93 @@
+use cosmic::{prelude::*, widget};
+
+use crate::app::{error::Error, message::AppMessage};
+
+pub struct LogicalVolumeResize {
+    index: usize,
+    /// `None` while the entered size is invalid
+    size: Option<u64>,
+    max_size: u64,
+    size_string: String,
+    resize_fs: bool,
+}
+
+impl LogicalVolumeResize {
+    pub fn new(index: usize, size: u64, max_size: u64) -> Self {
+        Self {
+            index,
+            size: Some(size),
+            max_size,
+            size_string: size.to_string(),
+            resize_fs: true,
+        }
+    }
+}
+
+impl super::OperationDialog for LogicalVolumeResize {
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        match message {
+            AppMessage::OperationLogicalVolumeSizeUpdate(input) => {
+                self.size = super::parse_size(&input, 512, self.max_size);
+                self.size_string = input;
+            }
+            AppMessage::OperationLogicalVolumeSizeSave => {
+                if let Some(size) = self.size {
+                    self.size_string = size.to_string();
+                }
+            }
+            AppMessage::OperationLogicalVolumeToggleResizeFs(toggle) => self.resize_fs = toggle,
+            AppMessage::PerformVolumeGroupOperation(vg) => {
+                if let (Some(volume), Some(size)) = (vg.volumes.get(self.index), self.size) {
+                    let lv = volume.lv.clone();
+                    let resize_fs = self.resize_fs && volume.fs.is_some();
+                    tasks.push(cosmic::task::future(async move {
+                        let mut options = udisks2::standard_options(false);
+                        if resize_fs {
+                            options.insert("resize_fsys", true.into());
+                        }
+                        lv.resize(size, options).await?;
+                        Ok(AppMessage::OperationFinish)
+                    }));
+                }
+            }
+            _ => {}
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        use widget::settings;
+
+        let mut resize = widget::button::destructive("Resize");
+        if self.size.is_some() {
+            resize = resize.on_press(Ok(AppMessage::ConfirmOperation));
+        }
+
+        widget::dialog()
+            .title("Resize Logical Volume")
+            .body("Shrinking a volume without resizing its file system will destroy data!")
+            .control(
+                settings::section()
+                    .add(settings::item(
+                        "Size",
+                        widget::text_input("", &self.size_string)
+                            .on_input(|input| {
+                                Ok(AppMessage::OperationLogicalVolumeSizeUpdate(input))
+                            })
+                            .on_submit(Ok(AppMessage::OperationLogicalVolumeSizeSave)),
+                    ))
+                    .add(settings::item(
+                        "Resize File System",
+                        widget::toggler(self.resize_fs).on_toggle(|toggle| {
+                            Ok(AppMessage::OperationLogicalVolumeToggleResizeFs(toggle))
+                        }),
+                    )),
+            )
+            .primary_action(resize)
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
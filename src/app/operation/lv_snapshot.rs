@@ -0,0 +1,103 @@
+use cosmic::{prelude::*, widget};
+
+use crate::app::{error::Error, lvm, message::AppMessage};
+
+pub struct LogicalVolumeSnapshot {
+    index: usize,
+    name: String,
+    /// `None` while the entered size is invalid
+    size: Option<u64>,
+    /// Space left for the snapshot, `None` for thin snapshots which take no size
+    max_size: Option<u64>,
+    size_string: String,
+}
+
+impl LogicalVolumeSnapshot {
+    pub fn new(index: usize, max_size: Option<u64>) -> Self {
+        Self {
+            index,
+            name: "".to_string(),
+            size: None,
+            max_size,
+            size_string: String::new(),
+        }
+    }
+}
+
+impl super::OperationDialog for LogicalVolumeSnapshot {
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        match message {
+            AppMessage::OperationLogicalVolumeNameUpdate(name) => self.name = name,
+            AppMessage::OperationLogicalVolumeSizeUpdate(input) => {
+                self.size = self
+                    .max_size
+                    .and_then(|max_size| super::parse_size(&input, 512, max_size));
+                self.size_string = input;
+            }
+            AppMessage::OperationLogicalVolumeSizeSave => {
+                if let Some(size) = self.size {
+                    self.size_string = size.to_string();
+                }
+            }
+            AppMessage::PerformVolumeGroupOperation(vg) => {
+                // Snapshots of thin volumes are thin as well and must not be given a size
+                let size = match self.max_size {
+                    Some(_) => self.size,
+                    None => Some(0),
+                };
+                if let (Some(volume), Some(size), true) = (
+                    vg.volumes.get(self.index),
+                    size,
+                    lvm::is_valid_name(&self.name),
+                ) {
+                    let lv = volume.lv.clone();
+                    let name = self.name.clone();
+                    tasks.push(cosmic::task::future(async move {
+                        lv.create_snapshot(&name, size, udisks2::standard_options(false))
+                            .await?;
+                        Ok(AppMessage::OperationFinish)
+                    }));
+                }
+            }
+            _ => {}
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        use widget::settings;
+
+        let mut section = settings::section().add(settings::item(
+            "Name",
+            widget::text_input("", &self.name)
+                .on_input(|input| Ok(AppMessage::OperationLogicalVolumeNameUpdate(input))),
+        ));
+        if self.max_size.is_some() {
+            section = section.add(settings::item(
+                "Size",
+                widget::text_input("", &self.size_string)
+                    .on_input(|input| Ok(AppMessage::OperationLogicalVolumeSizeUpdate(input)))
+                    .on_submit(Ok(AppMessage::OperationLogicalVolumeSizeSave)),
+            ));
+        }
+
+        let mut create = widget::button::suggested("Create");
+        if (self.max_size.is_none() || self.size.is_some()) && lvm::is_valid_name(&self.name) {
+            create = create.on_press(Ok(AppMessage::ConfirmOperation));
+        }
+
+        widget::dialog()
+            .title("Create Snapshot")
+            .body(match self.max_size {
+                Some(_) => "The snapshot size is the space reserved for changes to the origin",
+                None => "The snapshot is a thin volume allocating space from the same pool",
+            })
+            .control(section)
+            .primary_action(create)
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
@@ -1,32 +1,149 @@
+pub mod benchmark;
+pub mod btrfs_device;
+pub mod btrfs_subvolume;
+pub mod clone;
 pub mod drive_format;
+pub mod drive_settings;
+pub mod fs_check;
+pub mod image_attach;
+pub mod image_create;
+pub mod image_detach;
+pub mod image_restore;
+pub mod iso_write;
+pub mod lv_create;
+pub mod lv_delete;
+pub mod lv_resize;
+pub mod lv_snapshot;
+pub mod nvme_format;
+pub mod nvme_sanitize;
 pub mod partition_create;
+pub mod partition_delete;
 pub mod partition_format;
+pub mod raid_command;
+pub mod raid_create;
+pub mod raid_spare;
+pub mod surface_check;
 
+use super::erase::EraseSupport;
+use super::filesystem::CheckResult;
+use super::power::PowerSettings;
 use super::{error::Error, message::AppMessage};
 use cosmic::prelude::*;
 
 #[derive(Debug, Clone)]
 pub enum Operation {
-    DriveFormat,
+    DriveFormat(EraseSupport),
     AddPartition(u64, u64),
-    PartitionFormat(u64),
+    PartitionDelete(u64),
+    PartitionFormat(u64, EraseSupport),
+    LogicalVolumeCreate(lv_create::LogicalVolumeCreateKind, u64),
+    LogicalVolumeResize(usize, u64, u64),
+    LogicalVolumeSnapshot(usize, Option<u64>),
+    LogicalVolumeDelete(usize),
+    BtrfsSubvolumeCreate(u64),
+    BtrfsSnapshot(u64, u64),
+    BtrfsSubvolumeDelete(u64, u64),
+    BtrfsAddDevice(u64, usize),
+    BtrfsRemoveDevice(u64, usize, bool),
+    RaidCreate,
+    RaidCommand(raid_command::RaidCommand),
+    RaidAddSpare,
+    ImageCreate(Option<u64>),
+    ImageRestore(Option<u64>, u64),
+    ImageAttach,
+    ImageDetach,
+    IsoWrite,
+    Clone(clone::CloneSource, u64),
+    NamespaceFormat(u64),
+    Sanitize,
+    DriveSettings(PowerSettings),
+    Benchmark(bool),
+    SurfaceCheck,
+    FilesystemCheck(u64, bool),
+    FilesystemCheckResult(CheckResult),
 }
 
 impl Into<Box<dyn OperationDialog>> for Operation {
     fn into(self) -> Box<dyn OperationDialog> {
         match self {
-            Self::DriveFormat => Box::new(drive_format::DriveFormat::new()),
+            Self::DriveFormat(erase) => Box::new(drive_format::DriveFormat::new(erase)),
             Self::AddPartition(offset, max_size) => {
                 Box::new(partition_create::AddPartition::new(offset, max_size))
             }
-            Self::PartitionFormat(offset) => {
-                Box::new(partition_format::PartitionFormat::new(offset))
+            Self::PartitionDelete(offset) => {
+                Box::new(partition_delete::PartitionDelete::new(offset))
+            }
+            Self::PartitionFormat(offset, erase) => {
+                Box::new(partition_format::PartitionFormat::new(offset, erase))
+            }
+            Self::LogicalVolumeCreate(kind, max_size) => {
+                Box::new(lv_create::LogicalVolumeCreate::new(kind, max_size))
+            }
+            Self::LogicalVolumeResize(index, size, max_size) => {
+                Box::new(lv_resize::LogicalVolumeResize::new(index, size, max_size))
+            }
+            Self::LogicalVolumeSnapshot(index, max_size) => {
+                Box::new(lv_snapshot::LogicalVolumeSnapshot::new(index, max_size))
+            }
+            Self::LogicalVolumeDelete(index) => {
+                Box::new(lv_delete::LogicalVolumeDelete::new(index))
+            }
+            Self::BtrfsSubvolumeCreate(offset) => {
+                Box::new(btrfs_subvolume::SubvolumeCreate::new(offset))
+            }
+            Self::BtrfsSnapshot(offset, id) => {
+                Box::new(btrfs_subvolume::SubvolumeSnapshot::new(offset, id))
+            }
+            Self::BtrfsSubvolumeDelete(offset, id) => {
+                Box::new(btrfs_subvolume::SubvolumeDelete::new(offset, id))
+            }
+            Self::BtrfsAddDevice(offset, index) => {
+                Box::new(btrfs_device::AddDevice::new(offset, index))
+            }
+            Self::BtrfsRemoveDevice(offset, index, degrades) => {
+                Box::new(btrfs_device::RemoveDevice::new(offset, index, degrades))
+            }
+            Self::RaidCreate => Box::new(raid_create::RaidCreate::new()),
+            Self::RaidCommand(command) => Box::new(raid_command::RaidCommandDialog::new(command)),
+            Self::RaidAddSpare => Box::new(raid_spare::RaidAddSpare::new()),
+            Self::ImageCreate(offset) => Box::new(image_create::ImageCreate::new(offset)),
+            Self::ImageRestore(offset, capacity) => {
+                Box::new(image_restore::ImageRestore::new(offset, capacity))
+            }
+            Self::ImageAttach => Box::new(image_attach::ImageAttach::new()),
+            Self::ImageDetach => Box::new(image_detach::ImageDetach::new()),
+            Self::IsoWrite => Box::new(iso_write::IsoWrite::new()),
+            Self::Clone(source, size) => Box::new(clone::CloneDialog::new(source, size)),
+            Self::NamespaceFormat(device_number) => {
+                Box::new(nvme_format::NamespaceFormat::new(device_number))
+            }
+            Self::Sanitize => Box::new(nvme_sanitize::Sanitize::new()),
+            Self::DriveSettings(settings) => Box::new(drive_settings::DriveSettings::new(settings)),
+            Self::Benchmark(writable) => Box::new(benchmark::Benchmark::new(writable)),
+            Self::SurfaceCheck => Box::new(surface_check::SurfaceCheckDialog::new()),
+            Self::FilesystemCheck(offset, repair) => {
+                Box::new(fs_check::FilesystemCheck::new(offset, repair))
+            }
+            Self::FilesystemCheckResult(result) => {
+                Box::new(fs_check::FilesystemCheckResult::new(result))
             }
         }
     }
 }
 
 pub trait OperationDialog {
+    /// Called once when the dialog is opened, for dialogs that need to query udisks first.
+    fn init(&mut self, _client: udisks2::Client) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        cosmic::Task::none()
+    }
     fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>>;
     fn dialog(&self) -> Element<Result<AppMessage, Error>>;
 }
+
+/// Parses a size in bytes typed into a dialog, rounded down to whole sectors.
+///
+/// Returns `None` while the input is not a number or out of range, so confirming can be disabled.
+fn parse_size(input: &str, min: u64, max: u64) -> Option<u64> {
+    let size = input.trim().parse::<u64>().ok()? / 512 * 512;
+    (min..=max).contains(&size).then_some(size)
+}
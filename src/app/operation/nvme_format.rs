@@ -0,0 +1,129 @@
+use cosmic::{prelude::*, widget};
+
+use crate::app::nvme::Namespace;
+use crate::app::{error::Error, message::AppMessage, progress};
+
+/// Secure erase settings of the NVMe Format command, `None` only reformats.
+const SECURE_ERASE: [Option<&str>; 3] = [None, Some("user_data"), Some("crypto_erase")];
+const SECURE_ERASE_NAMES: [&str; 3] = ["None", "User Data Erase", "Cryptographic Erase"];
+
+pub struct NamespaceFormat {
+    client: Option<udisks2::Client>,
+    /// Block device number of the namespace
+    device_number: u64,
+    namespace: Option<Namespace>,
+    format_names: Vec<String>,
+    format: Option<usize>,
+    secure_erase: usize,
+}
+
+impl NamespaceFormat {
+    pub fn new(device_number: u64) -> Self {
+        Self {
+            client: None,
+            device_number,
+            namespace: None,
+            format_names: Vec::new(),
+            format: None,
+            secure_erase: 0,
+        }
+    }
+}
+
+impl super::OperationDialog for NamespaceFormat {
+    fn init(&mut self, client: udisks2::Client) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        self.client = Some(client.clone());
+        let device_number = self.device_number;
+        cosmic::task::future(async move {
+            match Namespace::find(&client, device_number).await? {
+                Some(namespace) => Ok(AppMessage::OperationNamespaceLoaded(namespace)),
+                None => Err(Error::new("The namespace no longer exists", true)),
+            }
+        })
+    }
+
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        match message {
+            AppMessage::OperationNamespaceLoaded(namespace) => {
+                self.format_names = namespace
+                    .lba_formats
+                    .iter()
+                    .map(|format| format.name())
+                    .collect();
+                self.format = namespace.formatted;
+                self.namespace = Some(namespace);
+            }
+            AppMessage::OperationNamespaceSelectFormat(index) => self.format = Some(index),
+            AppMessage::OperationNamespaceSelectSecureErase(index) => self.secure_erase = index,
+            AppMessage::PerformOperation(drive) => {
+                let format = self
+                    .namespace
+                    .as_ref()
+                    .and_then(|namespace| namespace.lba_formats.get(self.format?).copied());
+                let secure_erase = SECURE_ERASE[self.secure_erase];
+                let drive_path = drive.drive_path();
+                if let (Some(client), Some(namespace), Some(format), Some(drive_path)) = (
+                    self.client.clone(),
+                    self.namespace.clone(),
+                    format,
+                    drive_path,
+                ) {
+                    tasks.push(progress::run(move |mut reporter| async move {
+                        for fs in
+                            crate::app::device::drive_filesystems(&client, &drive_path).await?
+                        {
+                            let _ = fs.unmount(udisks2::standard_options(false)).await;
+                        }
+                        reporter.stage("Formatting Namespace", namespace.capacity);
+                        namespace
+                            .format(format, secure_erase, &mut reporter, namespace.capacity)
+                            .await
+                    }));
+                }
+            }
+            _ => {}
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        use widget::settings;
+
+        let mut format = widget::button::destructive("Format");
+        if self.format.is_some() {
+            format = format.on_press(Ok(AppMessage::ConfirmOperation));
+        }
+
+        let section = match &self.namespace {
+            Some(namespace) => settings::section()
+                .add(settings::item(
+                    "Namespace",
+                    widget::text::body(format!("{} ({})", namespace.nsid, namespace.device)),
+                ))
+                .add(settings::item(
+                    "LBA Format",
+                    widget::dropdown(&self.format_names, self.format, |index| {
+                        Ok(AppMessage::OperationNamespaceSelectFormat(index))
+                    }),
+                ))
+                .add(settings::item(
+                    "Secure Erase",
+                    widget::dropdown(&SECURE_ERASE_NAMES, Some(self.secure_erase), |index| {
+                        Ok(AppMessage::OperationNamespaceSelectSecureErase(index))
+                    }),
+                )),
+            None => settings::section().add(widget::text::body("Reading namespace...")),
+        };
+
+        widget::dialog()
+            .title("Format Namespace")
+            .body("All data in the namespace will be lost, this operation is not reversible!")
+            .control(section)
+            .primary_action(format)
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
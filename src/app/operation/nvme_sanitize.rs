@@ -0,0 +1,77 @@
+use cosmic::{prelude::*, widget};
+
+use crate::app::device;
+use crate::app::nvme::SanitizeAction;
+use crate::app::{error::Error, message::AppMessage, progress};
+
+pub struct Sanitize {
+    client: Option<udisks2::Client>,
+    action: usize,
+}
+
+impl Sanitize {
+    pub fn new() -> Self {
+        Self {
+            client: None,
+            action: 0,
+        }
+    }
+}
+
+impl super::OperationDialog for Sanitize {
+    fn init(&mut self, client: udisks2::Client) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        self.client = Some(client);
+        cosmic::Task::none()
+    }
+
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        match message {
+            AppMessage::OperationSanitizeSelectAction(index) => self.action = index,
+            AppMessage::PerformOperation(drive) => {
+                let action = SanitizeAction::ALL[self.action];
+                let drive_path = drive.drive_path();
+                if let (Some(client), Some(nvme), Some(drive_path)) =
+                    (self.client.clone(), drive.nvme, drive_path)
+                {
+                    let capacity = drive.capacity;
+                    tasks.push(progress::run(move |mut reporter| async move {
+                        for fs in device::drive_filesystems(&client, &drive_path).await? {
+                            let _ = fs.unmount(udisks2::standard_options(false)).await;
+                        }
+                        // The controller can not be interrupted once it started
+                        reporter.stage(format!("Sanitizing, {}", action.name()), capacity);
+                        nvme.sanitize(action, &mut reporter, capacity).await
+                    }));
+                }
+            }
+            _ => {}
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        use widget::settings;
+
+        widget::dialog()
+            .title("Sanitize Drive")
+            .body(
+                "Every namespace of the drive, its caches and spare blocks are erased. This operation is not reversible and can not be cancelled!\n\nudisks does not report which methods the drive supports, it rejects the ones it does not.",
+            )
+            .control(settings::section().add(settings::item(
+                "Method",
+                widget::dropdown(
+                    &SanitizeAction::NAMES,
+                    Some(self.action),
+                    |index| Ok(AppMessage::OperationSanitizeSelectAction(index)),
+                ),
+            )))
+            .primary_action(
+                widget::button::destructive("Sanitize").on_press(Ok(AppMessage::ConfirmOperation)),
+            )
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
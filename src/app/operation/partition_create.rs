@@ -1,6 +1,6 @@
 use cosmic::{prelude::*, widget};
 
-use crate::app::{error::Error, message::AppMessage};
+use crate::app::{error::Error, image_table, message::AppMessage};
 
 pub struct AddPartition {
     offset: u64,
@@ -18,6 +18,15 @@ impl AddPartition {
             size_string: "0".to_string(),
         }
     }
+
+    /// Takes the entered size, rounded down to whole sectors and kept within the free space.
+    ///
+    /// Input that is not a number leaves the previous size.
+    fn save_size(&mut self) {
+        let size = self.size_string.parse().unwrap_or(self.size) / 512 * 512;
+        self.size = size.max(512).min(self.max_size);
+        self.size_string = self.size.to_string();
+    }
 }
 
 impl super::OperationDialog for AddPartition {
@@ -25,18 +34,22 @@ impl super::OperationDialog for AddPartition {
         let mut tasks = Vec::new();
         match message {
             AppMessage::OperationAddPartitionSizeUpdate(input) => self.size_string = input,
-            AppMessage::OperationAddPartitionSizeSave => {
-                let mut size: u64 = (if let Ok(size) = self.size_string.parse() {
-                    size
-                } else {
-                    self.size
-                } / 512)
-                    * 512;
-                size = size.max(512).min(self.max_size);
-                self.size_string = size.to_string();
-            }
+            AppMessage::OperationAddPartitionSizeSave => self.save_size(),
             AppMessage::PerformOperation(drive) => {
-                if let Some(ptable) = drive.ptable {
+                // The size may have been typed without confirming it
+                self.save_size();
+                if let Some(path) = drive.image_file {
+                    let size = self.size;
+                    let offset = self.offset;
+                    tasks.push(cosmic::task::future(async move {
+                        let mut file = std::fs::OpenOptions::new()
+                            .read(true)
+                            .write(true)
+                            .open(&path)?;
+                        image_table::add_partition(&mut file, offset, size)?;
+                        Ok(AppMessage::OperationFinish)
+                    }));
+                } else if let Some(ptable) = drive.ptable {
                     let size = self.size;
                     let offset = self.offset;
                     tasks.push(cosmic::task::future(async move {
@@ -78,3 +91,34 @@ impl super::OperationDialog for AddPartition {
             .into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::operation::OperationDialog;
+
+    #[test]
+    fn size_input() {
+        let mut dialog = AddPartition::new(1024 * 1024, 10 * 1024 * 1024);
+        let _ = dialog.update(AppMessage::OperationAddPartitionSizeUpdate(
+            "1000000".to_string(),
+        ));
+        let _ = dialog.update(AppMessage::OperationAddPartitionSizeSave);
+        assert_eq!(dialog.size, 999_936);
+        assert_eq!(dialog.size_string, "999936");
+
+        // Garbage keeps the last size, the free space and a sector bound it
+        for (input, size) in [
+            ("lots", 999_936),
+            ("100", 512),
+            ("20000000", 10 * 1024 * 1024),
+        ] {
+            let _ = dialog.update(AppMessage::OperationAddPartitionSizeUpdate(
+                input.to_string(),
+            ));
+            let _ = dialog.update(AppMessage::OperationAddPartitionSizeSave);
+            assert_eq!(dialog.size, size, "{input}");
+            assert_eq!(dialog.size_string, size.to_string());
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use cosmic::{prelude::*, widget};
+
+use crate::app::{error::Error, image_table, message::AppMessage};
+
+pub struct PartitionDelete {
+    block_offset: u64,
+}
+
+impl PartitionDelete {
+    pub fn new(block_offset: u64) -> Self {
+        Self { block_offset }
+    }
+}
+
+impl super::OperationDialog for PartitionDelete {
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        if let AppMessage::PerformOperation(drive) = message {
+            let offset = self.block_offset;
+            // Only offered for image files edited offline
+            if let Some(path) = drive.image_file {
+                tasks.push(cosmic::task::future(async move {
+                    let mut file = std::fs::OpenOptions::new()
+                        .read(true)
+                        .write(true)
+                        .open(&path)?;
+                    image_table::delete_partition(&mut file, offset)?;
+                    Ok(AppMessage::OperationFinish)
+                }));
+            }
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        widget::dialog()
+            .title("Delete Partition")
+            .body("All data on the partition will be lost, this operation is not reversible!")
+            .primary_action(
+                widget::button::destructive("Delete").on_press(Ok(AppMessage::ConfirmOperation)),
+            )
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
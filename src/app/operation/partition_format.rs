@@ -1,3 +1,6 @@
+use crate::app::erase::{EraseMethod, EraseSupport};
+use crate::app::filesystem::{self, Availability, FormatType};
+use crate::app::format_options::{self, OptionKind, OptionValue};
 use crate::app::{error::Error, message::AppMessage};
 use cosmic::{prelude::*, widget};
 
@@ -5,37 +8,165 @@ pub struct PartitionFormat {
     block_offset: u64,
 
     name: String,
-    erase: bool,
-    type_: String,
-    type_index: Option<usize>,
+    methods: Vec<EraseMethod>,
+    method_names: Vec<String>,
+    erase: usize,
+    types: Vec<FormatType>,
+    type_names: Vec<String>,
+    /// Id of the selected file system, kept when the list of types changes
+    type_: &'static str,
+    /// udisks is new enough to pass advanced options on to mkfs
+    mkfs_args: bool,
+    advanced: bool,
+    /// Values of the advanced options of the selected file system
+    option_values: Vec<OptionValue>,
 }
 
 impl PartitionFormat {
-    pub fn new(block_offset: u64) -> Self {
+    pub fn new(block_offset: u64, support: EraseSupport) -> Self {
+        let methods = EraseMethod::available(support, false);
         Self {
             block_offset,
             name: "".to_string(),
-            erase: false,
-            type_: "ext4".to_string(),
-            type_index: Some(0),
+            method_names: methods.iter().map(|method| method.name()).collect(),
+            methods,
+            erase: 0,
+            types: Vec::new(),
+            type_names: Vec::new(),
+            type_: "ext4",
+            mkfs_args: false,
+            advanced: false,
+            option_values: default_values("ext4"),
         }
     }
+
+    fn selected_type(&self) -> Option<&FormatType> {
+        self.types.iter().find(|type_| type_.id == self.type_)
+    }
+
+    fn options_valid(&self) -> bool {
+        !self.mkfs_args
+            || format_options::options(self.type_)
+                .iter()
+                .zip(&self.option_values)
+                .all(|(option, value)| option.validate(value).is_ok())
+    }
+
+    fn advanced_section(&self) -> Element<Result<AppMessage, Error>> {
+        use widget::settings;
+
+        let options = format_options::options(self.type_);
+        let mut section = settings::section();
+        if options.is_empty() {
+            return section
+                .add(settings::item(
+                    "Advanced Options",
+                    widget::text::body("None for this file system"),
+                ))
+                .into();
+        }
+        if !self.mkfs_args {
+            section = section.add(settings::item(
+                "Advanced Options",
+                widget::text::caption(
+                    "Unavailable, udisks 2.10 or newer is needed to pass them on",
+                ),
+            ));
+        }
+        for (index, (option, value)) in options.iter().zip(&self.option_values).enumerate() {
+            if !self.mkfs_args {
+                section = section.add(settings::item(
+                    option.name,
+                    widget::text::body("Unavailable"),
+                ));
+                continue;
+            }
+            let control: Element<_> = match (&option.kind, value) {
+                (OptionKind::Choice { names, .. }, OptionValue::Choice(choice)) => {
+                    widget::dropdown(*names, Some(*choice), move |choice| {
+                        Ok(AppMessage::OperationPartitionFormatOptionChoice(
+                            index, choice,
+                        ))
+                    })
+                    .into()
+                }
+                (OptionKind::Number { .. }, OptionValue::Number(number)) => {
+                    let input = widget::text_input("Default", number).on_input(move |input| {
+                        Ok(AppMessage::OperationPartitionFormatOptionNumber(
+                            index, input,
+                        ))
+                    });
+                    widget::column()
+                        .push(input)
+                        .push_maybe(option.validate(value).err().map(widget::text::caption))
+                        .into()
+                }
+                (OptionKind::Flag, OptionValue::Flag(flag)) => widget::toggler(*flag)
+                    .on_toggle(move |flag| {
+                        Ok(AppMessage::OperationPartitionFormatOptionFlag(index, flag))
+                    })
+                    .into(),
+                _ => continue,
+            };
+            section = section.add(settings::item(option.name, control));
+        }
+        section.into()
+    }
+}
+
+fn default_values(fs_type: &str) -> Vec<OptionValue> {
+    format_options::options(fs_type)
+        .iter()
+        .map(|option| option.default_value())
+        .collect()
 }
 
 impl super::OperationDialog for PartitionFormat {
+    fn init(&mut self, client: udisks2::Client) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        cosmic::task::future(async move {
+            Ok(AppMessage::OperationPartitionFormatTypes(
+                filesystem::format_types(&client).await?,
+                format_options::supported(&client.manager().version().await?),
+            ))
+        })
+    }
+
     fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
         let mut tasks = Vec::new();
         match message {
-            AppMessage::OperationPartitionFormatNameUpdate(name) => self.name = name,
-            AppMessage::OperationPartitionFormatToggleErase(toggle) => self.erase = toggle,
+            AppMessage::OperationPartitionFormatNameUpdate(name) => {
+                self.name = filesystem::normalize_label(self.type_, &name)
+            }
+            AppMessage::OperationPartitionFormatEraseMode(index) => self.erase = index,
+            AppMessage::OperationPartitionFormatTypes(types, mkfs_args) => {
+                self.type_names = types.iter().map(FormatType::name_for_display).collect();
+                self.types = types;
+                self.mkfs_args = mkfs_args;
+            }
             AppMessage::OperationPartitionFormatSelectFS(index) => {
-                self.type_ = match index {
-                    0 => "ext4".to_string(),
-                    1 => "ntfs".to_string(),
-                    2 => "vfat".to_string(),
-                    _ => unreachable!(),
-                };
-                self.type_index = Some(index)
+                if let Some(type_) = self.types.get(index) {
+                    if self.type_ != type_.id {
+                        self.type_ = type_.id;
+                        self.option_values = default_values(type_.id);
+                        self.name = filesystem::normalize_label(type_.id, &self.name);
+                    }
+                }
+            }
+            AppMessage::OperationPartitionFormatToggleAdvanced => self.advanced = !self.advanced,
+            AppMessage::OperationPartitionFormatOptionChoice(index, choice) => {
+                if let Some(value) = self.option_values.get_mut(index) {
+                    *value = OptionValue::Choice(choice);
+                }
+            }
+            AppMessage::OperationPartitionFormatOptionNumber(index, number) => {
+                if let Some(value) = self.option_values.get_mut(index) {
+                    *value = OptionValue::Number(number);
+                }
+            }
+            AppMessage::OperationPartitionFormatOptionFlag(index, flag) => {
+                if let Some(value) = self.option_values.get_mut(index) {
+                    *value = OptionValue::Flag(flag);
+                }
             }
             AppMessage::PerformOperation(drive) => {
                 let partition = drive
@@ -43,11 +174,21 @@ impl super::OperationDialog for PartitionFormat {
                     .iter()
                     .find(|partition| partition.offset == self.block_offset);
                 let name = self.name.clone();
-                let erase = self.erase;
-                let type_ = self.type_.clone();
+                let erase = self.methods[self.erase];
+                let type_ = self.type_;
+                let mkfs_args = match self.mkfs_args {
+                    true => format_options::mkfs_args(
+                        format_options::options(type_),
+                        &self.option_values,
+                    ),
+                    false => Vec::new(),
+                };
                 if let Some(block) = partition {
-                    if let Some(partition) = &block.partition {
-                        let block = partition.block.clone();
+                    if let Some((partition, block)) = block
+                        .partition
+                        .as_ref()
+                        .and_then(|partition| Some((partition, partition.block.clone()?)))
+                    {
                         let fs = partition.fs.clone();
                         tasks.push(cosmic::task::future(async move {
                             if let Some(fs) = fs {
@@ -56,14 +197,13 @@ impl super::OperationDialog for PartitionFormat {
 
                             let mut options = udisks2::standard_options(false);
                             options.insert("update-partition-type", true.into());
-                            if erase {
-                                options.insert("erase", "zeros".into());
-                            }
-                            if type_ != "vfat" {
-                                options.insert("label", name.into());
+                            erase.format_options(&mut options);
+                            options.insert("label", name.into());
+                            if !mkfs_args.is_empty() {
+                                options.insert("mkfs-args", mkfs_args.into());
                             }
 
-                            block.format(type_.as_str(), options).await?;
+                            block.format(type_, options).await?;
                             Ok(AppMessage::OperationFinish)
                         }));
                     }
@@ -75,27 +215,74 @@ impl super::OperationDialog for PartitionFormat {
     }
 
     fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        let selected = self.selected_type();
+        let mut section = widget::settings::section()
+            .add(widget::settings::item(
+                "Volume Name",
+                widget::column()
+                    .push(widget::text_input("", &self.name).on_input(|input| {
+                        Ok(AppMessage::OperationPartitionFormatNameUpdate(input))
+                    }))
+                    .push_maybe(
+                        filesystem::validate_label(self.type_, &self.name)
+                            .err()
+                            .map(widget::text::caption),
+                    ),
+            ))
+            .add(widget::settings::item(
+                "Erase Mode",
+                widget::dropdown(&self.method_names, Some(self.erase), |index| {
+                    Ok(AppMessage::OperationPartitionFormatEraseMode(index))
+                }),
+            ))
+            .add(widget::settings::item(
+                "Filesystem Type",
+                widget::dropdown(
+                    &self.type_names,
+                    self.types.iter().position(|type_| type_.id == self.type_),
+                    |index| Ok(AppMessage::OperationPartitionFormatSelectFS(index)),
+                ),
+            ));
+        if let Some(FormatType {
+            name,
+            availability: Availability::Missing(tool),
+            ..
+        }) = selected
+        {
+            section = section.add(widget::settings::item(
+                "Unavailable",
+                widget::text::caption(format!("Install {tool} to format with {name}")),
+            ));
+        }
+
+        let mut confirm = widget::button::destructive("Confirm");
+        if selected.is_some_and(|type_| type_.availability == Availability::Available)
+            && self.options_valid()
+            && filesystem::validate_label(self.type_, &self.name).is_ok()
+        {
+            confirm = confirm.on_press(Ok(AppMessage::ConfirmOperation));
+        }
+
+        let mut controls = widget::column().push(section).push(
+            widget::button::text("Advanced")
+                .trailing_icon(widget::icon::from_name(match self.advanced {
+                    true => "go-up-symbolic",
+                    false => "go-down-symbolic",
+                }))
+                .on_press(Ok(AppMessage::OperationPartitionFormatToggleAdvanced)),
+        );
+        if self.advanced {
+            controls = controls.push(self.advanced_section());
+        }
+
         widget::dialog()
             .title("Format Partition")
             .body("Create a filesystem for the selected partition, this erases all data on the volume! Please back up data before you format.")
-            .control(
-                widget::settings::section()
-                    .add(widget::settings::item(
-                        "Volume Name",
-                        widget::text_input("", &self.name)
-                            .on_input(|input| Ok(AppMessage::OperationPartitionFormatNameUpdate(input)))
-                    ))
-                    .add(widget::settings::item(
-                        "Full Erase (Slow)",
-                        widget::toggler(self.erase).on_toggle(|toggle| Ok(AppMessage::OperationPartitionFormatToggleErase(toggle)))
-                    ))
-                    .add(widget::settings::item(
-                        "Filesystem Type",
-                        widget::dropdown(&["Linux (Ext4)", "Windows (NTFS)", "Universal (FAT)"], self.type_index, |index| Ok(AppMessage::OperationPartitionFormatSelectFS(index)))
-                    ))
+            .control(controls)
+            .primary_action(confirm)
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
             )
-            .primary_action(widget::button::destructive("Confirm").on_press(Ok(AppMessage::ConfirmOperation)))
-            .secondary_action(widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)))
             .into()
     }
 }
@@ -0,0 +1,89 @@
+use cosmic::{prelude::*, widget};
+
+use crate::app::{error::Error, message::AppMessage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RaidCommand {
+    Start,
+    Stop,
+    /// Scrub the array, reading every stripe and counting mismatches
+    Check,
+    RemoveMember(usize),
+}
+
+pub struct RaidCommandDialog {
+    command: RaidCommand,
+}
+
+impl RaidCommandDialog {
+    pub fn new(command: RaidCommand) -> Self {
+        Self { command }
+    }
+}
+
+impl super::OperationDialog for RaidCommandDialog {
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        if let AppMessage::PerformRaidOperation(raid) = message {
+            let command = self.command;
+            let member = match command {
+                RaidCommand::RemoveMember(index) => raid
+                    .members
+                    .get(index)
+                    .map(|member| member.device.path.clone()),
+                _ => None,
+            };
+            tasks.push(cosmic::task::future(async move {
+                let options = udisks2::standard_options(false);
+                match command {
+                    RaidCommand::Start => raid.raid.start(options).await?,
+                    RaidCommand::Stop => raid.raid.stop(options).await?,
+                    RaidCommand::Check => raid.raid.request_sync_action("check", options).await?,
+                    RaidCommand::RemoveMember(_) => {
+                        let Some(member) = member else {
+                            return Err(Error::new("The member no longer exists", true));
+                        };
+                        let mut options = options;
+                        options.insert("wipe", true.into());
+                        raid.raid.remove_device(&member, options).await?
+                    }
+                }
+                Ok(AppMessage::OperationFinish)
+            }));
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        let (title, body, action) = match self.command {
+            RaidCommand::Start => (
+                "Start RAID Array",
+                "Assemble the array from its members so it can be used.",
+                widget::button::suggested("Start"),
+            ),
+            RaidCommand::Stop => (
+                "Stop RAID Array",
+                "The array can not be used until it is started again, make sure no file system on it is mounted.",
+                widget::button::destructive("Stop"),
+            ),
+            RaidCommand::Check => (
+                "Check Redundancy",
+                "Read the whole array and compare the redundant data, this can take many hours while the array stays usable.",
+                widget::button::suggested("Check"),
+            ),
+            RaidCommand::RemoveMember(_) => (
+                "Remove Member",
+                "The member is wiped and removed from the array, the array may become degraded!",
+                widget::button::destructive("Remove"),
+            ),
+        };
+        widget::dialog()
+            .title(title)
+            .body(body)
+            .primary_action(action.on_press(Ok(AppMessage::ConfirmOperation)))
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
@@ -0,0 +1,167 @@
+use cosmic::{prelude::*, widget};
+
+use crate::app::{
+    device::{self, Device},
+    error::Error,
+    message::AppMessage,
+};
+
+/// Levels offered for new arrays with the number of members they need.
+const LEVELS: [(&str, usize); 6] = [
+    ("raid0", 2),
+    ("raid1", 2),
+    ("raid4", 3),
+    ("raid5", 3),
+    ("raid6", 4),
+    ("raid10", 2),
+];
+
+pub struct RaidCreate {
+    client: Option<udisks2::Client>,
+    candidates: Vec<Device>,
+    selected: Vec<bool>,
+    level: Option<usize>,
+    name: String,
+}
+
+impl RaidCreate {
+    pub fn new() -> Self {
+        Self {
+            client: None,
+            candidates: Vec::new(),
+            selected: Vec::new(),
+            level: Some(1),
+            name: "".to_string(),
+        }
+    }
+
+    fn can_create(&self) -> bool {
+        let members = self.selected.iter().filter(|selected| **selected).count();
+        self.level
+            .is_some_and(|level| members >= LEVELS[level].1 && !self.name.is_empty())
+    }
+
+    fn perform(&self) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let Some(client) = self.client.clone() else {
+            return cosmic::Task::none();
+        };
+        let blocks: Vec<_> = self
+            .candidates
+            .iter()
+            .zip(self.selected.iter())
+            .filter(|(_, selected)| **selected)
+            .map(|(candidate, _)| candidate.path.clone())
+            .collect();
+        let level = LEVELS[self.level.unwrap_or(1)].0;
+        // Mirrors have no stripes, mdadm rejects a chunk size for them
+        let chunk = match level {
+            "raid1" => 0,
+            _ => 512 * 1024,
+        };
+        let name = self.name.clone();
+        cosmic::task::future(async move {
+            let blocks: Vec<_> = blocks.into_iter().map(|path| path.into_inner()).collect();
+            let raid_path = client
+                .manager()
+                .mdraid_create(
+                    &blocks,
+                    level,
+                    &name,
+                    chunk,
+                    udisks2::standard_options(false),
+                )
+                .await?;
+            Ok(AppMessage::RaidCreated(raid_path))
+        })
+    }
+}
+
+impl super::OperationDialog for RaidCreate {
+    fn init(&mut self, client: udisks2::Client) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        self.client = Some(client.clone());
+        cosmic::task::future(async move {
+            Ok(AppMessage::OperationRaidCandidates(
+                device::unused(&client).await?,
+            ))
+        })
+    }
+
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        match message {
+            AppMessage::OperationRaidCandidates(candidates) => {
+                self.selected = vec![false; candidates.len()];
+                self.candidates = candidates;
+            }
+            AppMessage::OperationRaidToggleMember(index, toggle) => {
+                if let Some(selected) = self.selected.get_mut(index) {
+                    *selected = toggle;
+                }
+            }
+            AppMessage::OperationRaidSelectLevel(index) => self.level = Some(index),
+            AppMessage::OperationRaidNameUpdate(name) => self.name = name,
+            // Arrays are not bound to the selected drive or array
+            AppMessage::PerformOperation(_) | AppMessage::PerformRaidOperation(_) => {
+                tasks.push(self.perform())
+            }
+            _ => {}
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        use widget::settings;
+
+        let members = self
+            .candidates
+            .iter()
+            .zip(self.selected.iter())
+            .enumerate()
+            .fold(
+                settings::section().title("Members"),
+                |section, (index, (candidate, selected))| {
+                    section.add(settings::item(
+                        format!("{} ({})", candidate.device, candidate.size),
+                        widget::toggler(*selected).on_toggle(move |toggle| {
+                            Ok(AppMessage::OperationRaidToggleMember(index, toggle))
+                        }),
+                    ))
+                },
+            );
+
+        let mut create = widget::button::destructive("Create");
+        if self.can_create() {
+            create = create.on_press(Ok(AppMessage::ConfirmOperation));
+        }
+
+        widget::dialog()
+            .title("Create RAID Array")
+            .body("All data on the selected members will be lost!")
+            .control(
+                widget::column()
+                    .push(
+                        settings::section()
+                            .add(settings::item(
+                                "Name",
+                                widget::text_input("", &self.name).on_input(|input| {
+                                    Ok(AppMessage::OperationRaidNameUpdate(input))
+                                }),
+                            ))
+                            .add(settings::item(
+                                "Level",
+                                widget::dropdown(
+                                    &["RAID 0", "RAID 1", "RAID 4", "RAID 5", "RAID 6", "RAID 10"],
+                                    self.level,
+                                    |index| Ok(AppMessage::OperationRaidSelectLevel(index)),
+                                ),
+                            )),
+                    )
+                    .push(members),
+            )
+            .primary_action(create)
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
@@ -0,0 +1,83 @@
+use cosmic::{prelude::*, widget};
+
+use crate::app::{
+    device::{self, Device},
+    error::Error,
+    message::AppMessage,
+};
+
+pub struct RaidAddSpare {
+    candidates: Vec<Device>,
+    names: Vec<String>,
+    candidate: Option<usize>,
+}
+
+impl RaidAddSpare {
+    pub fn new() -> Self {
+        Self {
+            candidates: Vec::new(),
+            names: Vec::new(),
+            candidate: None,
+        }
+    }
+}
+
+impl super::OperationDialog for RaidAddSpare {
+    fn init(&mut self, client: udisks2::Client) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        cosmic::task::future(async move {
+            Ok(AppMessage::OperationRaidCandidates(
+                device::unused(&client).await?,
+            ))
+        })
+    }
+
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        match message {
+            AppMessage::OperationRaidCandidates(candidates) => {
+                self.names = candidates
+                    .iter()
+                    .map(|candidate| format!("{} ({})", candidate.device, candidate.size))
+                    .collect();
+                self.candidates = candidates;
+            }
+            AppMessage::OperationRaidSelectSpare(index) => self.candidate = Some(index),
+            AppMessage::PerformRaidOperation(raid) => {
+                if let Some(candidate) = self.candidate.and_then(|index| self.candidates.get(index))
+                {
+                    let path = candidate.path.clone();
+                    tasks.push(cosmic::task::future(async move {
+                        raid.raid
+                            .add_device(&path, udisks2::standard_options(false))
+                            .await?;
+                        Ok(AppMessage::OperationFinish)
+                    }));
+                }
+            }
+            _ => {}
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        let mut add = widget::button::destructive("Add");
+        if self.candidate.is_some() {
+            add = add.on_press(Ok(AppMessage::ConfirmOperation));
+        }
+
+        widget::dialog()
+            .title("Add Spare")
+            .body("The spare takes over when a member fails, a degraded array starts rebuilding onto it immediately. All data on it will be lost!")
+            .control(widget::settings::section().add(widget::settings::item(
+                "Device",
+                widget::dropdown(&self.names, self.candidate, |index| {
+                    Ok(AppMessage::OperationRaidSelectSpare(index))
+                }),
+            )))
+            .primary_action(add)
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
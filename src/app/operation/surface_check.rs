@@ -0,0 +1,70 @@
+use std::fs::File;
+use std::os::fd::OwnedFd;
+
+use cosmic::{prelude::*, widget};
+
+use crate::app::surface::SurfaceCheck;
+use crate::app::{error::Error, message::AppMessage, progress};
+
+pub struct SurfaceCheckDialog;
+
+impl SurfaceCheckDialog {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl super::OperationDialog for SurfaceCheckDialog {
+    fn update(&mut self, message: AppMessage) -> cosmic::app::Task<Result<AppMessage, Error>> {
+        let mut tasks = Vec::new();
+        if let AppMessage::PerformOperation(drive) = message {
+            let key = drive.results_key();
+            let capacity = drive.capacity;
+            if let Some(path) = drive.image_file {
+                tasks.push(progress::run(move |mut reporter| async move {
+                    let check = reporter
+                        .blocking(move |reporter| {
+                            SurfaceCheck::run(&mut File::open(&path)?, capacity, reporter)
+                        })
+                        .await?;
+                    let saved = check.save(&key);
+                    reporter.message(AppMessage::SurfaceChecked(check)).await;
+                    saved
+                }));
+            } else if let Some(block) = drive.block {
+                tasks.push(progress::run(move |mut reporter| async move {
+                    // Direct IO, so the page cache does not hide read errors or slow sectors
+                    let mut open_options = udisks2::standard_options(false);
+                    open_options.insert("writable", false.into());
+                    let mut device =
+                        File::from(OwnedFd::from(block.open_for_benchmark(open_options).await?));
+                    let check = reporter
+                        .blocking(move |reporter| {
+                            SurfaceCheck::run(&mut device, capacity, reporter)
+                        })
+                        .await?;
+                    // The result is shown either way, drives without a serial can not keep it
+                    let saved = check.save(&key);
+                    reporter.message(AppMessage::SurfaceChecked(check)).await;
+                    saved
+                }));
+            }
+        }
+        cosmic::app::Task::batch(tasks)
+    }
+
+    fn dialog(&self) -> Element<Result<AppMessage, Error>> {
+        widget::dialog()
+            .title("Check Surface")
+            .body(
+                "Reads the whole drive to find sectors that can not be read or only slowly. This can take hours for large drives, cancelling keeps the result of what was read so far.",
+            )
+            .primary_action(
+                widget::button::suggested("Start Check").on_press(Ok(AppMessage::ConfirmOperation)),
+            )
+            .secondary_action(
+                widget::button::standard("Cancel").on_press(Ok(AppMessage::CancelOperation)),
+            )
+            .into()
+    }
+}
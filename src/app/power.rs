@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use udisks2::{
+    ata::AtaProxy,
+    drive::DriveProxy,
+    zbus::zvariant::{OwnedValue, Value},
+};
+
+use super::error::Error;
+
+/// Standby timeouts in the ATA encoding: 1 to 240 count 5 seconds, 241 to 251 count 30 minutes.
+pub const STANDBY_TIMEOUTS: [(i32, &str); 9] = [
+    (0, "Never"),
+    (12, "1 Minute"),
+    (60, "5 Minutes"),
+    (120, "10 Minutes"),
+    (180, "15 Minutes"),
+    (240, "20 Minutes"),
+    (241, "30 Minutes"),
+    (242, "1 Hour"),
+    (244, "2 Hours"),
+];
+
+/// Advanced Power Management levels, 128 and above never spin the drive down.
+pub const APM_LEVELS: [(i32, &str); 5] = [
+    (255, "Disabled"),
+    (254, "Maximum Performance"),
+    (128, "Balanced, without Standby"),
+    (127, "Balanced, with Standby"),
+    (1, "Minimum Power Usage"),
+];
+
+/// Automatic Acoustic Management levels.
+pub const AAM_LEVELS: [(i32, &str); 2] = [(254, "Fast (Louder)"), (128, "Quiet (Slower)")];
+
+/// Power state as returned by `CHECK POWER MODE`, which does not wake the drive up.
+pub fn power_state_for_display(state: u8) -> &'static str {
+    match state {
+        0x00 | 0x01 => "Standby",
+        0x40 | 0x41 => "Spun Down (NV Cache)",
+        0x80..=0x83 => "Idle",
+        0xff => "Active",
+        _ => "Unknown",
+    }
+}
+
+fn config_i32(config: &HashMap<String, OwnedValue>, key: &str) -> Option<i32> {
+    match config.get(key).map(|value| &**value) {
+        Some(Value::I32(value)) => Some(*value),
+        _ => None,
+    }
+}
+
+fn config_bool(config: &HashMap<String, OwnedValue>, key: &str) -> Option<bool> {
+    match config.get(key).map(|value| &**value) {
+        Some(Value::Bool(value)) => Some(*value),
+        _ => None,
+    }
+}
+
+/// Power settings of an ATA drive, stored by udisks and applied whenever the drive appears.
+///
+/// Settings that are `None` are left at the drive's own default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PowerSettings {
+    pub standby_supported: bool,
+    pub apm_supported: bool,
+    pub aam_supported: bool,
+    pub write_cache_supported: bool,
+    pub standby: Option<i32>,
+    pub apm: Option<i32>,
+    pub aam: Option<i32>,
+    pub write_cache: Option<bool>,
+}
+
+impl PowerSettings {
+    pub async fn load(drive: &DriveProxy<'static>, ata: &AtaProxy<'static>) -> Result<Self, Error> {
+        let config = drive.configuration().await?;
+        Ok(Self {
+            standby_supported: ata.pm_supported().await?,
+            apm_supported: ata.apm_supported().await?,
+            aam_supported: ata.aam_supported().await?,
+            write_cache_supported: ata.write_cache_supported().await?,
+            standby: config_i32(&config, "ata-pm-standby"),
+            apm: config_i32(&config, "ata-apm-level"),
+            aam: config_i32(&config, "ata-aam-level"),
+            write_cache: config_bool(&config, "ata-write-cache-enabled"),
+        })
+    }
+
+    pub fn any_supported(&self) -> bool {
+        self.standby_supported
+            || self.apm_supported
+            || self.aam_supported
+            || self.write_cache_supported
+    }
+
+    /// Stores the settings with udisks, which applies them right away.
+    pub async fn apply(&self, drive: &DriveProxy<'static>) -> Result<(), Error> {
+        let mut config: HashMap<&str, Value<'_>> = HashMap::new();
+        if let Some(standby) = self.standby.filter(|_| self.standby_supported) {
+            config.insert("ata-pm-standby", standby.into());
+        }
+        if let Some(apm) = self.apm.filter(|_| self.apm_supported) {
+            config.insert("ata-apm-level", apm.into());
+        }
+        if let Some(aam) = self.aam.filter(|_| self.aam_supported) {
+            config.insert("ata-aam-level", aam.into());
+        }
+        if let Some(write_cache) = self.write_cache.filter(|_| self.write_cache_supported) {
+            config.insert("ata-write-cache-enabled", write_cache.into());
+        }
+        drive
+            .set_configuration(config, udisks2::standard_options(false))
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+use std::future::Future;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::{Duration, Instant};
+
+use cosmic::iced::futures::{channel::mpsc, channel::oneshot, SinkExt};
+use cosmic::{iced, prelude::*, widget};
+
+use super::{error::Error, message::AppMessage};
+
+/// How often a running transfer reports back to the app.
+const REPORT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// State of a long running operation, shown in place of the "Please Wait" dialog.
+#[derive(Clone, Debug)]
+pub struct Progress {
+    pub title: String,
+    pub done: u64,
+    pub total: u64,
+    started: Instant,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Progress {
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Bytes per second since the current stage started.
+    pub fn rate(&self) -> u64 {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            (self.done as f64 / elapsed) as u64
+        } else {
+            0
+        }
+    }
+
+    pub fn dialog(&self, client: Option<&udisks2::Client>) -> Element<Result<AppMessage, Error>> {
+        let size = |size: u64| match client {
+            Some(client) => client.size_for_display(size, true, false),
+            None => format!("{size} B"),
+        };
+        let cancelled = self.cancel.load(Ordering::Relaxed);
+
+        let mut cancel = widget::button::standard("Cancel");
+        if !cancelled {
+            cancel = cancel.on_press(Ok(AppMessage::CancelPending));
+        }
+
+        widget::dialog()
+            .title(self.title.clone())
+            .body(if cancelled {
+                "Cancelling...".to_string()
+            } else {
+                format!(
+                    "{} of {}, {}/s",
+                    size(self.done),
+                    size(self.total),
+                    size(self.rate())
+                )
+            })
+            .control(iced::widget::progress_bar(
+                0.0..=1.0,
+                if self.total > 0 {
+                    (self.done as f64 / self.total as f64) as f32
+                } else {
+                    0.0
+                },
+            ))
+            .secondary_action(cancel)
+            .into()
+    }
+}
+
+/// Handed to long operations to report progress and check for cancellation.
+#[derive(Clone)]
+pub struct Reporter {
+    progress: Progress,
+    sender: mpsc::Sender<Result<AppMessage, Error>>,
+    last: Instant,
+}
+
+impl Reporter {
+    fn new(sender: mpsc::Sender<Result<AppMessage, Error>>) -> Self {
+        Self {
+            progress: Progress {
+                title: "Please Wait...".to_string(),
+                done: 0,
+                total: 0,
+                started: Instant::now(),
+                cancel: Arc::new(AtomicBool::new(false)),
+            },
+            sender,
+            last: Instant::now(),
+        }
+    }
+
+    /// A reporter whose updates go nowhere, for testing blocking work.
+    #[cfg(test)]
+    pub fn detached() -> Self {
+        Self::new(mpsc::channel(1).0)
+    }
+
+    /// Starts a new stage of the operation, e.g. verifying after writing.
+    pub fn stage(&mut self, title: impl Into<String>, total: u64) {
+        self.progress.title = title.into();
+        self.progress.total = total;
+        self.progress.done = 0;
+        self.progress.started = Instant::now();
+        self.send();
+    }
+
+    pub fn update(&mut self, done: u64) {
+        self.progress.done = done;
+        if self.last.elapsed() >= REPORT_INTERVAL {
+            self.send();
+        }
+    }
+
+    pub fn cancelled(&self) -> bool {
+        self.progress.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Fails with a recoverable error once the user cancelled the operation.
+    pub fn check_cancelled(&self) -> Result<(), Error> {
+        if self.cancelled() {
+            Err(Error::new("The operation was cancelled", true))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Runs blocking IO on its own thread so the app stays responsive.
+    pub async fn blocking<T, W>(&mut self, work: W) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        W: FnOnce(&mut Reporter) -> Result<T, Error> + Send + 'static,
+    {
+        let mut reporter = self.clone();
+        let (result_tx, result_rx) = oneshot::channel();
+        std::thread::spawn(move || {
+            let result = work(&mut reporter);
+            let _ = result_tx.send((result, reporter.progress));
+        });
+        match result_rx.await {
+            Ok((result, progress)) => {
+                self.progress = progress;
+                self.send();
+                result
+            }
+            Err(_) => Err(Error::new(
+                "The operation stopped unexpectedly, this is a bug, please report it!",
+                true,
+            )),
+        }
+    }
+
+    /// Hands a message to the app ahead of the final `OperationFinish`, e.g. to remove a drive
+    /// that is gone before the active one is reloaded.
+    pub async fn message(&mut self, message: AppMessage) {
+        let _ = self.sender.send(Ok(message)).await;
+    }
+
+    fn send(&mut self) {
+        self.last = Instant::now();
+        // Progress is best effort, dropping an update when the app is busy is fine
+        let _ = self
+            .sender
+            .try_send(Ok(AppMessage::OperationProgress(self.progress.clone())));
+    }
+}
+
+/// Runs a long operation, streaming its progress to the app.
+///
+/// The operation talks to udisks asynchronously and hands its blocking IO to
+/// [`Reporter::blocking`]. It is finished in any case so the app does not stay pending after
+/// an error.
+pub fn run<F, Fut>(work: F) -> cosmic::app::Task<Result<AppMessage, Error>>
+where
+    F: FnOnce(Reporter) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), Error>> + Send + 'static,
+{
+    cosmic::task::stream(iced::stream::channel(4, move |mut output| async move {
+        let reporter = Reporter::new(output.clone());
+        if let Err(err) = work(reporter).await {
+            let _ = output.send(Err(err)).await;
+        }
+        let _ = output.send(Ok(AppMessage::OperationFinish)).await;
+    }))
+}
@@ -0,0 +1,312 @@
+use std::collections::HashMap;
+
+use cosmic::prelude::*;
+use cosmic::widget::nav_bar::Id;
+use cosmic::{iced, theme, widget};
+
+use udisks2::{mdraid::MDRaidProxy, zbus::zvariant::OwnedObjectPath, Client};
+
+use super::device::Device;
+use super::operation::{raid_command::RaidCommand, Operation};
+use super::{error::Error, message::AppMessage};
+
+pub const MDRAID_INTERFACE: &str = "org.freedesktop.UDisks2.MDRaid";
+
+/// Lists the object paths of all RAID arrays known to mdadm, running or not.
+pub async fn arrays(client: Client) -> Result<AppMessage, Error> {
+    let objects = client.object_manager().get_managed_objects().await?;
+    Ok(AppMessage::ReadRaidsDone(
+        objects
+            .into_iter()
+            .filter(|(_, interfaces)| {
+                interfaces
+                    .keys()
+                    .any(|interface| interface.as_str() == MDRAID_INTERFACE)
+            })
+            .map(|(path, _)| path)
+            .collect(),
+    ))
+}
+
+pub fn level_for_display(level: &str) -> String {
+    match level.strip_prefix("raid") {
+        Some(number) => format!("RAID {number}"),
+        None => match level {
+            "linear" => "Linear".to_string(),
+            level => level.to_string(),
+        },
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RaidMember {
+    pub device: Device,
+    pub slot: Option<i32>,
+    pub state: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct Raid {
+    pub raid: MDRaidProxy<'static>,
+    pub raid_path: OwnedObjectPath,
+
+    pub name: String,
+    pub device: Option<String>,
+    pub level: String,
+    pub redundant: bool,
+    pub running: bool,
+    pub size: String,
+    pub num_devices: u32,
+    pub degraded: u32,
+    pub sync: Option<String>,
+    pub sync_completed: f64,
+    pub uuid: String,
+
+    pub members: Vec<RaidMember>,
+}
+
+#[derive(Eq, PartialEq, Clone, Copy)]
+enum RaidAction {
+    Create,
+    Command(RaidCommand),
+    AddSpare,
+}
+
+impl widget::menu::Action for RaidAction {
+    type Message = Result<AppMessage, Error>;
+
+    fn message(&self) -> Self::Message {
+        match self {
+            Self::Create => Ok(AppMessage::OpenOperationDialog(Operation::RaidCreate)),
+            Self::Command(command) => Ok(AppMessage::OpenOperationDialog(Operation::RaidCommand(
+                *command,
+            ))),
+            Self::AddSpare => Ok(AppMessage::OpenOperationDialog(Operation::RaidAddSpare)),
+        }
+    }
+}
+
+impl Raid {
+    pub async fn load(
+        client: Client,
+        id: Id,
+        raid_path: OwnedObjectPath,
+    ) -> Result<AppMessage, Error> {
+        let raid = client.object(raid_path.clone()).unwrap().mdraid().await?;
+
+        // The array block device only exists while it is running
+        let mut device = None;
+        for block_path in client
+            .manager()
+            .get_block_devices(udisks2::standard_options(false))
+            .await?
+        {
+            let block = client.object(block_path.clone()).unwrap().block().await?;
+            if block.mdraid().await? == raid_path {
+                device = Some(Device::load(&client, block_path, &block).await?.device);
+                break;
+            }
+        }
+
+        let mut members = Vec::new();
+        for (block_path, slot, state, _, _) in raid.active_devices().await? {
+            let block = client.object(block_path.clone()).unwrap().block().await?;
+            members.push(RaidMember {
+                device: Device::load(&client, block_path, &block).await?,
+                slot: (slot >= 0).then_some(slot),
+                state: state.join(", "),
+            });
+        }
+        members.sort_by_key(|member| member.slot.unwrap_or(i32::MAX));
+
+        let level = raid.level().await?;
+        let sync_action = raid.sync_action().await?;
+        let sync_completed = raid.sync_completed().await?;
+
+        Ok(AppMessage::RaidRead(
+            id,
+            Raid {
+                // Names are prefixed with the host they were created on
+                name: match raid.name().await?.split_once(':') {
+                    Some((_, name)) => name.to_string(),
+                    None => raid.name().await?,
+                },
+                device,
+                redundant: !matches!(level.as_str(), "raid0" | "linear"),
+                level: level_for_display(&level),
+                running: raid.running().await?,
+                size: client.size_for_display(raid.size().await?, true, false),
+                num_devices: raid.num_devices().await?,
+                degraded: raid.degraded().await?,
+                sync: match sync_action.as_str() {
+                    "" | "idle" => None,
+                    action => Some(format!(
+                        "{} {:.1}%, {}/s",
+                        action,
+                        sync_completed * 100.0,
+                        client.size_for_display(raid.sync_rate().await?, true, false)
+                    )),
+                },
+                sync_completed,
+                uuid: raid.uuid().await?,
+                members,
+                raid,
+                raid_path,
+            },
+        ))
+    }
+
+    pub fn state(&self) -> &'static str {
+        match (self.running, self.degraded) {
+            (false, _) => "Stopped",
+            (true, 0) => "Running",
+            (true, _) => "Degraded",
+        }
+    }
+
+    pub fn menu_bar(&self) -> Element<Result<AppMessage, Error>> {
+        use widget::menu;
+        let mut items = vec![menu::Item::Button(
+            "Create RAID Array",
+            None,
+            RaidAction::Create,
+        )];
+        items.push(menu::Item::Divider);
+        if self.running {
+            items.push(menu::Item::Button(
+                "Stop",
+                None,
+                RaidAction::Command(RaidCommand::Stop),
+            ));
+            if self.redundant {
+                items.push(menu::Item::Button(
+                    "Check Redundancy",
+                    None,
+                    RaidAction::Command(RaidCommand::Check),
+                ));
+                items.push(menu::Item::Button("Add Spare", None, RaidAction::AddSpare));
+            }
+        } else {
+            items.push(menu::Item::Button(
+                "Start",
+                None,
+                RaidAction::Command(RaidCommand::Start),
+            ));
+        }
+
+        menu::bar(vec![
+            menu::Tree::with_children(menu::root("Array"), menu::items(&HashMap::new(), items)),
+            menu::Tree::with_children(
+                menu::root("Members"),
+                menu::items(
+                    &HashMap::new(),
+                    self.members
+                        .iter()
+                        .enumerate()
+                        .map(|(index, member)| {
+                            menu::Item::Folder(
+                                member.device.device.clone(),
+                                vec![menu::Item::Button(
+                                    "Remove".to_string(),
+                                    None,
+                                    RaidAction::Command(RaidCommand::RemoveMember(index)),
+                                )],
+                            )
+                        })
+                        .collect(),
+                ),
+            ),
+        ])
+        .apply(Element::from)
+    }
+
+    pub fn view(&self) -> Element<Result<AppMessage, Error>> {
+        let theme = theme::active();
+        let cosmic = theme.cosmic();
+
+        let mut info = widget::settings::section()
+            .add(widget::settings::item(
+                "Level",
+                widget::text::heading(&self.level),
+            ))
+            .add(widget::settings::item(
+                "State",
+                widget::text::heading(self.state()),
+            ))
+            .add(widget::settings::item(
+                "Size",
+                widget::text::body(&self.size),
+            ))
+            .add(widget::settings::item(
+                "Devices",
+                widget::text::body(self.num_devices.to_string()),
+            ))
+            .add(widget::settings::item(
+                "Degraded Devices",
+                widget::text::body(self.degraded.to_string()),
+            ));
+        if let Some(device) = &self.device {
+            info = info.add(widget::settings::item("Device", widget::text::body(device)));
+        }
+        if let Some(sync) = &self.sync {
+            info = info.add(widget::settings::item(
+                "Sync",
+                widget::column()
+                    .spacing(cosmic.space_xxs())
+                    .push(widget::text::body(sync))
+                    .push(iced::widget::progress_bar(
+                        0.0..=1.0,
+                        self.sync_completed as f32,
+                    )),
+            ));
+        }
+        info = info.add(widget::settings::item(
+            "UUID",
+            widget::text::caption(&self.uuid),
+        ));
+
+        widget::column()
+            .spacing(cosmic.space_s())
+            .push(
+                widget::column()
+                    .spacing(cosmic.space_xs())
+                    .push(widget::text::title3(&self.name))
+                    .push(info),
+            )
+            .push(
+                widget::column()
+                    .push(widget::text::title3("Members"))
+                    .push(iced::widget::horizontal_rule(1)),
+            )
+            .push(widget::scrollable(
+                widget::column::with_children(
+                    self.members
+                        .iter()
+                        .map(|member| {
+                            widget::settings::section()
+                                .title(&member.device.device)
+                                .add(widget::settings::item(
+                                    "State",
+                                    widget::text::heading(&member.state),
+                                ))
+                                .add(widget::settings::item(
+                                    "Slot",
+                                    widget::text::body(match member.slot {
+                                        Some(slot) => slot.to_string(),
+                                        None => "None".to_string(),
+                                    }),
+                                ))
+                                .add(widget::settings::item(
+                                    "Size",
+                                    widget::text::body(&member.device.size),
+                                ))
+                                .into()
+                        })
+                        .collect(),
+                )
+                .padding([0, cosmic.space_xs(), 0, 0])
+                .spacing(cosmic.space_m()),
+            ))
+            .into()
+    }
+}
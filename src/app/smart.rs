@@ -0,0 +1,491 @@
+use cosmic::{iced, prelude::*, theme, widget};
+
+use udisks2::{ata::AtaProxy, zbus::zvariant::OwnedObjectPath, Client};
+
+use super::{error::Error, message::AppMessage};
+
+/// Attribute flag marking an attribute that predicts imminent failure.
+const FLAG_PREFAIL: u16 = 0x0001;
+
+/// Formats a duration the way drive tools usually present it.
+pub fn duration_for_display(seconds: u64) -> String {
+    match seconds {
+        0..60 => format!("{seconds} seconds"),
+        60..3600 => format!("{} minutes", seconds / 60),
+        3600..172800 => format!("{} hours", seconds / 3600),
+        _ => format!("{} days ({} hours)", seconds / 86400, seconds / 3600),
+    }
+}
+
+/// Temperatures are reported in Kelvin, zero when the drive has no sensor.
+pub fn temperature_for_display(kelvin: f64) -> String {
+    if kelvin > 0.0 {
+        format!("{:.0} °C", kelvin - 273.15)
+    } else {
+        "Unknown".to_string()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelftestKind {
+    Short,
+    Extended,
+    Conveyance,
+}
+
+impl SelftestKind {
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Short => "short",
+            Self::Extended => "extended",
+            Self::Conveyance => "conveyance",
+        }
+    }
+}
+
+fn selftest_status_for_display(status: &str) -> &'static str {
+    match status {
+        "success" => "Completed Successfully",
+        "aborted" => "Aborted",
+        "interrupted" => "Interrupted by a Reset",
+        "fatal" => "Did Not Complete",
+        "error_electrical" => "Failed, Electrical Error",
+        "error_servo" => "Failed, Servo Error",
+        "error_read" => "Failed, Read Error",
+        "error_handling" => "Failed, Handling Damage",
+        "error_unknown" => "Failed",
+        "inprogress" => "In Progress",
+        _ => "Unknown",
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Assessment {
+    Good,
+    /// Bad sectors or attributes that crossed their threshold in the past
+    Warning,
+    Failing,
+    Disabled,
+}
+
+impl Assessment {
+    /// Rates the drive from what it reports, counts below zero are unknown.
+    fn new(
+        enabled: bool,
+        failing: bool,
+        bad_sectors: i64,
+        attributes_failing: i32,
+        attributes_failed_in_the_past: i32,
+        attributes: &[Attribute],
+    ) -> Self {
+        if !enabled {
+            Self::Disabled
+        } else if failing || attributes.iter().any(|attr| attr.prefail && attr.failing()) {
+            Self::Failing
+        } else if bad_sectors > 0 || attributes_failing > 0 || attributes_failed_in_the_past > 0 {
+            Self::Warning
+        } else {
+            Self::Good
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Good => "Disk is OK",
+            Self::Warning => "Disk is OK, but shows signs of wear",
+            Self::Failing => "Disk is likely to fail soon",
+            Self::Disabled => "SMART is disabled",
+        }
+    }
+
+    /// Icon shown on the nav entry in place of the drive icon, if any.
+    pub fn icon_name(&self) -> Option<&'static str> {
+        match self {
+            Self::Good | Self::Disabled => None,
+            Self::Warning => Some("dialog-warning-symbolic"),
+            Self::Failing => Some("dialog-error-symbolic"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Attribute {
+    pub id: u8,
+    pub name: String,
+    pub prefail: bool,
+    pub value: i32,
+    pub worst: i32,
+    pub threshold: i32,
+    pub pretty: i64,
+    pub pretty_unit: i32,
+}
+
+impl Attribute {
+    /// Normalized values count down, a value at or below the threshold means failure.
+    /// Values of zero or a threshold of zero carry no meaning.
+    fn below_threshold(&self, value: i32) -> bool {
+        self.threshold > 0 && value > 0 && value <= self.threshold
+    }
+
+    pub fn failing(&self) -> bool {
+        self.below_threshold(self.value)
+    }
+
+    pub fn failed_in_the_past(&self) -> bool {
+        !self.failing() && self.below_threshold(self.worst)
+    }
+
+    pub fn assessment(&self) -> &'static str {
+        match (self.failing(), self.failed_in_the_past()) {
+            (true, _) if self.prefail => "Failing",
+            (true, _) => "Failing (Old-Age)",
+            (_, true) => "Failed in the Past",
+            _ => "OK",
+        }
+    }
+
+    pub fn value_for_display(&self) -> String {
+        match self.pretty_unit {
+            1 => self.pretty.to_string(),
+            2 => {
+                let milliseconds = self.pretty.max(0) as u64;
+                if milliseconds < 1000 {
+                    format!("{milliseconds} ms")
+                } else {
+                    duration_for_display(milliseconds / 1000)
+                }
+            }
+            3 => format!("{} sectors", self.pretty),
+            4 => temperature_for_display(self.pretty as f64 / 1000.0),
+            _ => "N/A".to_string(),
+        }
+    }
+}
+
+/// SMART data of an ATA drive.
+#[derive(Clone, Debug)]
+pub struct Smart {
+    pub proxy: AtaProxy<'static>,
+    pub enabled: bool,
+    pub failing: bool,
+    /// Unix time of the last update, zero when the drive was never read
+    pub updated: u64,
+    pub temperature: f64,
+    pub power_on_seconds: u64,
+    pub bad_sectors: i64,
+    pub attributes_failing: i32,
+    pub attributes_failed_in_the_past: i32,
+    pub attributes: Vec<Attribute>,
+    /// Result of the last self-test the drive logged, or `inprogress`
+    pub selftest_status: String,
+    /// -1 when no self-test is running
+    pub selftest_percent_remaining: i32,
+}
+
+impl Smart {
+    /// Loads SMART data, `None` for drives that are not ATA or do not support SMART.
+    pub async fn load(client: &Client, drive_path: OwnedObjectPath) -> Result<Option<Self>, Error> {
+        let Ok(proxy) = client.object(drive_path).unwrap().drive_ata().await else {
+            return Ok(None);
+        };
+        if !proxy.smart_supported().await.unwrap_or(false) {
+            return Ok(None);
+        }
+
+        // Values the drive fails to report are shown as unknown instead of hiding the drive
+        let enabled = proxy.smart_enabled().await.unwrap_or(false);
+        let updated = proxy.smart_updated().await.unwrap_or(0);
+        // Asking a drive that was never read for attributes fails
+        let attributes = match enabled && updated > 0 {
+            true => proxy
+                .smart_get_attributes(udisks2::standard_options(false))
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(
+                    |(id, name, flags, value, worst, threshold, pretty, pretty_unit, _)| {
+                        Attribute {
+                            id,
+                            name,
+                            prefail: flags & FLAG_PREFAIL != 0,
+                            value,
+                            worst,
+                            threshold,
+                            pretty,
+                            pretty_unit,
+                        }
+                    },
+                )
+                .collect(),
+            false => Vec::new(),
+        };
+
+        Ok(Some(Self {
+            enabled,
+            failing: proxy.smart_failing().await.unwrap_or(false),
+            updated,
+            temperature: proxy.smart_temperature().await.unwrap_or(0.0),
+            power_on_seconds: proxy.smart_power_on_seconds().await.unwrap_or(0),
+            bad_sectors: proxy.smart_num_bad_sectors().await.unwrap_or(-1),
+            attributes_failing: proxy.smart_num_attributes_failing().await.unwrap_or(-1),
+            attributes_failed_in_the_past: proxy
+                .smart_num_attributes_failed_in_the_past()
+                .await
+                .unwrap_or(-1),
+            attributes,
+            selftest_status: proxy.smart_selftest_status().await.unwrap_or_default(),
+            selftest_percent_remaining: proxy
+                .smart_selftest_percent_remaining()
+                .await
+                .unwrap_or(-1),
+            proxy,
+        }))
+    }
+
+    pub fn selftest_running(&self) -> bool {
+        self.selftest_status == "inprogress"
+    }
+
+    pub async fn start_selftest(&self, kind: SelftestKind) -> Result<(), Error> {
+        self.proxy
+            .smart_selftest_start(kind.id(), udisks2::standard_options(false))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn abort_selftest(&self) -> Result<(), Error> {
+        self.proxy
+            .smart_selftest_abort(udisks2::standard_options(false))
+            .await?;
+        Ok(())
+    }
+
+    /// Replaces the data read from the drive with a blob saved by libatasmart, e.g. with
+    /// `skdump --save`. The drive itself is not touched.
+    pub async fn load_blob(&self, blob: Vec<u8>) -> Result<(), Error> {
+        let mut options = udisks2::standard_options(false);
+        options.insert("atasmart_blob", blob.into());
+        self.proxy.smart_update(options).await?;
+        Ok(())
+    }
+
+    pub fn assessment(&self) -> Assessment {
+        Assessment::new(
+            self.enabled,
+            self.failing,
+            self.bad_sectors,
+            self.attributes_failing,
+            self.attributes_failed_in_the_past,
+            &self.attributes,
+        )
+    }
+
+    pub fn view(&self) -> Element<Result<AppMessage, Error>> {
+        let theme = theme::active();
+        let cosmic = theme.cosmic();
+
+        let mut section = widget::settings::section()
+            .title("Health")
+            .add(widget::settings::item(
+                "Assessment",
+                widget::text::heading(self.assessment().name()),
+            ));
+        if !self.enabled {
+            return section.into();
+        }
+        section = section
+            .add(widget::settings::item(
+                "Temperature",
+                widget::text::body(temperature_for_display(self.temperature)),
+            ))
+            .add(widget::settings::item(
+                "Powered On",
+                widget::text::body(match self.power_on_seconds {
+                    0 => "Unknown".to_string(),
+                    seconds => duration_for_display(seconds),
+                }),
+            ))
+            .add(widget::settings::item(
+                "Self-Test",
+                match self.selftest_running() {
+                    true => {
+                        let done = (100 - self.selftest_percent_remaining).clamp(0, 100);
+                        widget::column()
+                            .spacing(cosmic.space_xxs())
+                            .push(widget::text::body(format!("{done}% Done")))
+                            .push(iced::widget::progress_bar(0.0..=100.0, done as f32))
+                            .apply(Element::from)
+                    }
+                    false => widget::text::body(match self.selftest_status.as_str() {
+                        "" => "Never Run",
+                        status => selftest_status_for_display(status),
+                    })
+                    .apply(Element::from),
+                },
+            ))
+            .add(widget::settings::item(
+                "Bad Sectors",
+                widget::text::body(match self.bad_sectors {
+                    0 => "None".to_string(),
+                    count if count < 0 => "Unknown".to_string(),
+                    count => count.to_string(),
+                }),
+            ))
+            .add(widget::settings::item(
+                "Failing Attributes",
+                widget::text::body(
+                    match (self.attributes_failing, self.attributes_failed_in_the_past) {
+                        (now, past) if now < 0 || past < 0 => "Unknown".to_string(),
+                        (now, past) => format!("{now} now, {past} in the past"),
+                    },
+                ),
+            ));
+
+        let column = |text: String, portion: u16| {
+            widget::text::caption(text)
+                .width(iced::Length::FillPortion(portion))
+                .apply(Element::from)
+        };
+        let header = widget::row::with_children(vec![
+            column("ID".to_string(), 1),
+            column("Attribute".to_string(), 5),
+            column("Value".to_string(), 3),
+            column("Normalized".to_string(), 2),
+            column("Worst".to_string(), 2),
+            column("Threshold".to_string(), 2),
+            column("Type".to_string(), 2),
+            column("Assessment".to_string(), 3),
+        ])
+        .spacing(cosmic.space_xxs());
+        let attributes = self.attributes.iter().fold(
+            widget::column().spacing(cosmic.space_xxs()).push(header),
+            |attributes, attr| {
+                attributes.push(
+                    widget::row::with_children(vec![
+                        column(attr.id.to_string(), 1),
+                        column(attr.name.clone(), 5),
+                        column(attr.value_for_display(), 3),
+                        column(attr.value.to_string(), 2),
+                        column(attr.worst.to_string(), 2),
+                        column(attr.threshold.to_string(), 2),
+                        column(
+                            if attr.prefail { "Pre-Fail" } else { "Old-Age" }.to_string(),
+                            2,
+                        ),
+                        column(attr.assessment().to_string(), 3),
+                    ])
+                    .spacing(cosmic.space_xxs()),
+                )
+            },
+        );
+
+        widget::column()
+            .spacing(cosmic.space_xs())
+            .push(section)
+            .push(
+                widget::settings::section().title("SMART Attributes").add(
+                    match self.attributes.is_empty() {
+                        true => widget::text::body("The drive did not report any attributes")
+                            .apply(Element::from),
+                        false => attributes.apply(Element::from),
+                    },
+                ),
+            )
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An attribute from its normalized value, worst value, threshold and prefail flag.
+    fn attribute((value, worst, threshold, prefail): (i32, i32, i32, bool)) -> Attribute {
+        Attribute {
+            id: 5,
+            name: "reallocated-sector-count".to_string(),
+            prefail,
+            value,
+            worst,
+            threshold,
+            pretty: 0,
+            pretty_unit: 0,
+        }
+    }
+
+    fn pretty(pretty: i64, pretty_unit: i32) -> String {
+        Attribute {
+            pretty,
+            pretty_unit,
+            ..attribute((100, 100, 10, false))
+        }
+        .value_for_display()
+    }
+
+    #[test]
+    fn attribute_thresholds() {
+        // value, worst, threshold, prefail -> failing, failed in the past, assessment
+        let cases = [
+            ((100, 100, 10, true), false, false, "OK"),
+            ((10, 10, 10, true), true, false, "Failing"),
+            ((5, 5, 10, false), true, false, "Failing (Old-Age)"),
+            ((100, 8, 10, true), false, true, "Failed in the Past"),
+            ((11, 10, 10, false), false, true, "Failed in the Past"),
+            // A threshold or value of zero means nothing
+            ((1, 1, 0, true), false, false, "OK"),
+            ((0, 0, 10, true), false, false, "OK"),
+            ((100, 0, 10, true), false, false, "OK"),
+        ];
+        for (values, failing, failed_in_the_past, assessment) in cases {
+            let attr = attribute(values);
+            assert_eq!(attr.failing(), failing, "{values:?}");
+            assert_eq!(attr.failed_in_the_past(), failed_in_the_past, "{values:?}");
+            assert_eq!(attr.assessment(), assessment, "{values:?}");
+        }
+    }
+
+    #[test]
+    fn attribute_values() {
+        let cases = [
+            ((42, 0), "N/A"),
+            ((42, 1), "42"),
+            ((500, 2), "500 ms"),
+            ((90_000, 2), "1 minutes"),
+            ((7_200_000, 2), "2 hours"),
+            ((-5, 2), "0 ms"),
+            ((8, 3), "8 sectors"),
+            ((313_150, 4), "40 °C"),
+            ((0, 4), "Unknown"),
+            ((42, 5), "N/A"),
+        ];
+        for ((value, unit), display) in cases {
+            assert_eq!(pretty(value, unit), display, "{value} {unit}");
+        }
+    }
+
+    #[test]
+    fn assessments() {
+        let good = [attribute((100, 100, 10, true))];
+        let old_age = [attribute((5, 5, 10, false))];
+        let prefail = [attribute((5, 5, 10, true))];
+        // enabled, failing, bad sectors, failing now, failed in the past, attributes
+        let cases = [
+            (true, false, 0, 0, 0, &good[..], Assessment::Good),
+            (false, true, 10, 1, 1, &prefail[..], Assessment::Disabled),
+            (true, true, 0, 0, 0, &good[..], Assessment::Failing),
+            (true, false, 0, 0, 0, &prefail[..], Assessment::Failing),
+            (true, false, 0, 0, 0, &old_age[..], Assessment::Good),
+            (true, false, 3, 0, 0, &good[..], Assessment::Warning),
+            (true, false, 0, 1, 0, &good[..], Assessment::Warning),
+            (true, false, 0, 0, 2, &[][..], Assessment::Warning),
+            // Counts the drive could not report
+            (true, false, -1, -1, -1, &[][..], Assessment::Good),
+        ];
+        for (enabled, failing, bad_sectors, now, past, attributes, assessment) in cases {
+            assert_eq!(
+                Assessment::new(enabled, failing, bad_sectors, now, past, attributes),
+                assessment
+            );
+        }
+    }
+}
@@ -0,0 +1,590 @@
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use cosmic::{iced, prelude::*, widget};
+
+use super::benchmark::{date_for_display, ALIGN};
+use super::drive::{Block, Drive};
+use super::{data_path, error::Error, message::AppMessage, progress::Reporter};
+use crate::widget::surface_map::{Cell, COLUMNS, ROWS};
+use crate::widget::SurfaceMap;
+
+const CHUNK: u64 = 1 << 20;
+/// `BLKSSZGET`, asks a block device for its logical block size.
+const BLKSSZGET: u64 = 0x1268;
+/// A healthy drive reads a chunk in a few milliseconds, sectors needing retries take far longer.
+const SLOW_CHUNK: Duration = Duration::from_millis(150);
+
+/// The smallest unit a device reads, 512 bytes for image files.
+fn logical_block_size(file: &File) -> u64 {
+    let mut size: libc::c_int = 0;
+    // SAFETY: the request writes a single int, files that are no block device fail with ENOTTY
+    let status = unsafe {
+        libc::ioctl(
+            file.as_raw_fd(),
+            BLKSSZGET as _,
+            &mut size as *mut libc::c_int,
+        )
+    };
+    match status {
+        0 if size > 0 => size as u64,
+        _ => 512,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RangeKind {
+    /// The drive returned an error
+    Bad,
+    Slow,
+}
+
+impl RangeKind {
+    fn id(&self) -> &'static str {
+        match self {
+            Self::Bad => "bad",
+            Self::Slow => "slow",
+        }
+    }
+
+    fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "bad" => Some(Self::Bad),
+            "slow" => Some(Self::Slow),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Bad => "Read Error",
+            Self::Slow => "Slow",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Range {
+    pub kind: RangeKind,
+    pub offset: u64,
+    pub length: u64,
+}
+
+impl Range {
+    /// Names of the partitions the range overlaps, empty when it is in unallocated space.
+    pub fn partitions<'a>(&self, partitions: &'a [Block]) -> Vec<&'a str> {
+        partitions
+            .iter()
+            .filter(|block| {
+                block.offset < self.offset + self.length && self.offset < block.offset + block.size
+            })
+            .filter_map(|block| block.partition.as_ref())
+            .map(|partition| partition.name.as_str())
+            .collect()
+    }
+
+    fn partitions_for_display(&self, partitions: &[Block]) -> String {
+        match self.partitions(partitions).as_slice() {
+            [] => "Unallocated Space".to_string(),
+            names => names.join(", "),
+        }
+    }
+
+    /// Ranges may be as small as a single sector.
+    fn length_for_display(&self) -> String {
+        if self.length < 1024 {
+            format!("{} bytes", self.length)
+        } else if self.length < CHUNK {
+            format!("{:.1} KiB", self.length as f64 / 1024.0)
+        } else {
+            format!("{:.1} MiB", self.length as f64 / CHUNK as f64)
+        }
+    }
+}
+
+/// Result of reading a whole device, the latest one is kept per drive.
+#[derive(Clone, Debug, Default)]
+pub struct SurfaceCheck {
+    /// Seconds since the Unix epoch
+    pub time: u64,
+    pub capacity: u64,
+    /// Bytes read before the check finished or was cancelled
+    pub checked: u64,
+    pub ranges: Vec<Range>,
+    map: SurfaceMap,
+}
+
+impl SurfaceCheck {
+    /// Reads the device chunk by chunk, a failing chunk does not stop the check.
+    ///
+    /// Cancelling keeps the result of what was read so far.
+    pub fn run(file: &mut File, capacity: u64, reporter: &mut Reporter) -> Result<Self, Error> {
+        let mut check = Self {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs()),
+            capacity,
+            ..Default::default()
+        };
+        let sector_size = logical_block_size(file);
+        let mut storage = vec![0u8; (CHUNK + ALIGN) as usize];
+        let start = storage.as_ptr().align_offset(ALIGN as usize);
+        let buffer = &mut storage[start..start + CHUNK as usize];
+        reporter.stage("Checking Surface", capacity);
+        while check.checked < capacity && !reporter.cancelled() {
+            let offset = check.checked;
+            let length = CHUNK.min(capacity - offset);
+            let started = Instant::now();
+            match file
+                .seek(SeekFrom::Start(offset))
+                .and_then(|_| file.read_exact(&mut buffer[..length as usize]))
+            {
+                Err(_) => {
+                    let checked = check.reread(file, buffer, offset, length, sector_size, reporter);
+                    check.checked += checked;
+                }
+                Ok(()) => {
+                    if started.elapsed() > SLOW_CHUNK {
+                        check.add(RangeKind::Slow, offset, length);
+                    }
+                    check.checked += length;
+                }
+            }
+            reporter.update(check.checked);
+        }
+        check.map = check.map();
+        Ok(check)
+    }
+
+    /// Reads a chunk that failed sector by sector, so only the sectors that can not be read
+    /// are marked bad. Returns how much of the chunk was checked before a cancel.
+    fn reread(
+        &mut self,
+        file: &mut File,
+        buffer: &mut [u8],
+        offset: u64,
+        length: u64,
+        sector_size: u64,
+        reporter: &Reporter,
+    ) -> u64 {
+        let end = offset + length;
+        let mut sector = offset;
+        while sector < end && !reporter.cancelled() {
+            let sector_length = sector_size.min(end - sector);
+            if file
+                .seek(SeekFrom::Start(sector))
+                .and_then(|_| file.read_exact(&mut buffer[..sector_length as usize]))
+                .is_err()
+            {
+                self.add(RangeKind::Bad, sector, sector_length);
+            }
+            sector += sector_length;
+        }
+        sector - offset
+    }
+
+    /// Adds a range, merged with the previous one if they are adjacent and alike.
+    fn add(&mut self, kind: RangeKind, offset: u64, length: u64) {
+        if let Some(last) = self.ranges.last_mut() {
+            if last.kind == kind && last.offset + last.length == offset {
+                last.length += length;
+                return;
+            }
+        }
+        self.ranges.push(Range {
+            kind,
+            offset,
+            length,
+        });
+    }
+
+    fn map(&self) -> SurfaceMap {
+        let cells = COLUMNS * ROWS;
+        let cell_range = |cell: usize| {
+            let start = self.capacity * cell as u64 / cells as u64;
+            (start, self.capacity * (cell as u64 + 1) / cells as u64)
+        };
+        SurfaceMap {
+            cells: (0..cells)
+                .map(|cell| {
+                    let (start, end) = cell_range(cell);
+                    let overlapping = |kind| {
+                        self.ranges.iter().any(|range| {
+                            range.kind == kind
+                                && range.offset < end
+                                && start < range.offset + range.length
+                        })
+                    };
+                    if overlapping(RangeKind::Bad) {
+                        Cell::Bad
+                    } else if overlapping(RangeKind::Slow) {
+                        Cell::Slow
+                    } else if self.checked >= end && end > start {
+                        Cell::Good
+                    } else {
+                        Cell::Unchecked
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    pub fn bad_bytes(&self) -> u64 {
+        self.ranges
+            .iter()
+            .filter(|range| range.kind == RangeKind::Bad)
+            .map(|range| range.length)
+            .sum()
+    }
+
+    pub fn load(key: &str) -> Option<Self> {
+        Self::load_from(&data_path("surface", key)?)
+    }
+
+    fn load_from(path: &Path) -> Option<Self> {
+        let result = std::fs::read_to_string(path).ok()?;
+        let mut lines = result.lines();
+        let mut header = lines.next()?.split('\t');
+        let mut check = Self {
+            time: header.next()?.parse().ok()?,
+            capacity: header.next()?.parse().ok()?,
+            checked: header.next()?.parse().ok()?,
+            ..Default::default()
+        };
+        for line in lines {
+            let mut fields = line.split('\t');
+            let (Some(kind), Some(Ok(offset)), Some(Ok(length))) = (
+                fields.next().and_then(RangeKind::from_id),
+                fields.next().map(str::parse::<u64>),
+                fields.next().map(str::parse::<u64>),
+            ) else {
+                continue;
+            };
+            check.ranges.push(Range {
+                kind,
+                offset,
+                length,
+            });
+        }
+        check.map = check.map();
+        Some(check)
+    }
+
+    /// Replaces the stored result of the drive, only the latest check is of interest.
+    pub fn save(&self, key: &str) -> Result<(), Error> {
+        let Some(path) = data_path("surface", key) else {
+            return Err(Error::new(
+                "The drive has no serial number to store the result under",
+                true,
+            ));
+        };
+        self.save_to(&path)
+    }
+
+    fn save_to(&self, path: &Path) -> Result<(), Error> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut result = format!("{}\t{}\t{}\n", self.time, self.capacity, self.checked);
+        for range in &self.ranges {
+            let _ = writeln!(
+                result,
+                "{}\t{}\t{}",
+                range.kind.id(),
+                range.offset,
+                range.length
+            );
+        }
+        std::fs::write(path, result)?;
+        Ok(())
+    }
+
+    /// Plain text report to hand in with a warranty claim.
+    pub fn report(&self, drive: &Drive) -> String {
+        let mut report = String::new();
+        let _ = writeln!(report, "Surface Check Report");
+        let _ = writeln!(report);
+        let _ = writeln!(report, "Model: {}", drive.model);
+        let _ = writeln!(report, "Serial Number: {}", drive.serial);
+        let _ = writeln!(report, "Firmware: {}", drive.revision);
+        let _ = writeln!(report, "Capacity: {} bytes", self.capacity);
+        let _ = writeln!(report, "Checked: {}", date_for_display(self.time));
+        let _ = writeln!(
+            report,
+            "Read: {} of {} bytes{}",
+            self.checked,
+            self.capacity,
+            match self.checked < self.capacity {
+                true => " (cancelled)",
+                false => "",
+            }
+        );
+        let _ = writeln!(report, "Unreadable: {} bytes", self.bad_bytes());
+        let _ = writeln!(report);
+        if self.ranges.is_empty() {
+            let _ = writeln!(report, "No read errors or slow sectors were found.");
+        }
+        for range in &self.ranges {
+            let _ = writeln!(
+                report,
+                "{}: bytes {} to {} ({} bytes, 512 byte sectors {} to {}), {}",
+                range.kind.name(),
+                range.offset,
+                range.offset + range.length - 1,
+                range.length,
+                range.offset / 512,
+                (range.offset + range.length - 1) / 512,
+                range.partitions_for_display(&drive.partitions)
+            );
+        }
+        report
+    }
+
+    pub fn view<'a>(&'a self, partitions: &'a [Block]) -> Element<'a, Result<AppMessage, Error>> {
+        let mut section = widget::settings::section()
+            .title("Surface Check")
+            .add(widget::settings::item(
+                "Last Run",
+                widget::text::body(date_for_display(self.time)),
+            ))
+            .add(widget::settings::item(
+                "Result",
+                widget::text::body(
+                    match (self.ranges.is_empty(), self.checked < self.capacity) {
+                        (true, false) => "No Problems Found".to_string(),
+                        (true, true) => "No Problems Found, Cancelled".to_string(),
+                        (false, _) => format!("{} Problem Areas", self.ranges.len()),
+                    },
+                ),
+            ))
+            .add(widget::settings::flex_item(
+                "Surface",
+                widget::canvas(&self.map)
+                    .width(iced::Length::Fill)
+                    .height(iced::Length::Fixed(160.0)),
+            ));
+        for range in &self.ranges {
+            section = section.add(widget::settings::item(
+                format!(
+                    "{} at {} MiB, {}",
+                    range.kind.name(),
+                    range.offset >> 20,
+                    range.length_for_display()
+                ),
+                widget::text::body(range.partitions_for_display(partitions)),
+            ));
+        }
+        section
+            .add(widget::settings::item(
+                "Report",
+                widget::button::standard("Export Report...")
+                    .on_press(Ok(AppMessage::SurfaceExport)),
+            ))
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::app::drive::Partition;
+
+    /// A temporary file, removed again when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            Self(
+                std::env::temp_dir()
+                    .join(format!("andromeda-surface-{}-{name}", std::process::id())),
+            )
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn range(kind: RangeKind, offset: u64, length: u64) -> Range {
+        Range {
+            kind,
+            offset,
+            length,
+        }
+    }
+
+    fn block(name: &str, offset: u64, size: u64) -> Block {
+        Block {
+            offset,
+            offset_for_display: String::new(),
+            size,
+            size_for_display: String::new(),
+            partition: Some(Partition {
+                block: None,
+                part: None,
+                fs: None,
+                btrfs: None,
+                btrfs_error: None,
+                tools: None,
+                name: name.to_string(),
+                partition_id: String::new(),
+                size: String::new(),
+                offset: String::new(),
+                r#type: String::new(),
+                block_size: String::new(),
+                uuid: String::new(),
+            }),
+        }
+    }
+
+    #[test]
+    fn adjacent_ranges_merge() {
+        let mut check = SurfaceCheck::default();
+        check.add(RangeKind::Bad, 0, 512);
+        check.add(RangeKind::Bad, 512, 512);
+        // A gap, then a different kind right after
+        check.add(RangeKind::Bad, 2048, 512);
+        check.add(RangeKind::Slow, 2560, CHUNK);
+        check.add(RangeKind::Bad, 2560 + CHUNK, 512);
+        assert_eq!(
+            check.ranges,
+            [
+                range(RangeKind::Bad, 0, 1024),
+                range(RangeKind::Bad, 2048, 512),
+                range(RangeKind::Slow, 2560, CHUNK),
+                range(RangeKind::Bad, 2560 + CHUNK, 512),
+            ]
+        );
+        assert_eq!(check.bad_bytes(), 1024 + 512 + 512);
+    }
+
+    #[test]
+    fn cell_map() {
+        let cells = (COLUMNS * ROWS) as u64;
+        let mut check = SurfaceCheck {
+            capacity: cells * CHUNK,
+            checked: cells * CHUNK / 2,
+            ..Default::default()
+        };
+        // A single bad sector marks its whole cell, bad wins over slow
+        check.add(RangeKind::Slow, CHUNK, 2 * CHUNK);
+        check.add(RangeKind::Bad, 2 * CHUNK + 512, 512);
+        let map = check.map();
+        assert_eq!(map.cells.len(), COLUMNS * ROWS);
+        assert_eq!(map.cells[0], Cell::Good);
+        assert_eq!(map.cells[1], Cell::Slow);
+        assert_eq!(map.cells[2], Cell::Bad);
+        assert_eq!(map.cells[3], Cell::Good);
+        assert_eq!(map.cells[cells as usize / 2 - 1], Cell::Good);
+        assert_eq!(map.cells[cells as usize / 2], Cell::Unchecked);
+        assert_eq!(map.cells[cells as usize - 1], Cell::Unchecked);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let file = TempFile::new("saved");
+        let mut check = SurfaceCheck {
+            time: 1_700_000_000,
+            capacity: 64 * CHUNK,
+            checked: 40 * CHUNK,
+            ..Default::default()
+        };
+        check.add(RangeKind::Slow, CHUNK, CHUNK);
+        check.add(RangeKind::Bad, 3 * CHUNK, 512);
+        check.save_to(&file.0).unwrap();
+
+        let loaded = SurfaceCheck::load_from(&file.0).unwrap();
+        assert_eq!(loaded.time, check.time);
+        assert_eq!(loaded.capacity, check.capacity);
+        assert_eq!(loaded.checked, check.checked);
+        assert_eq!(loaded.ranges, check.ranges);
+        assert_eq!(loaded.map.cells, check.map().cells);
+    }
+
+    #[test]
+    fn broken_files_are_skipped() {
+        let file = TempFile::new("broken");
+        std::fs::write(&file.0, "not a header\n").unwrap();
+        assert!(SurfaceCheck::load_from(&file.0).is_none());
+
+        std::fs::write(
+            &file.0,
+            "1\t4096\t4096\nbad\t0\t512\nworn\t512\t512\nslow\t1024\n",
+        )
+        .unwrap();
+        let loaded = SurfaceCheck::load_from(&file.0).unwrap();
+        assert_eq!(loaded.ranges, [range(RangeKind::Bad, 0, 512)]);
+
+        assert!(SurfaceCheck::load_from(&file.0.with_extension("missing")).is_none());
+    }
+
+    #[test]
+    fn unreadable_sectors_are_bad() {
+        // Reading past the end of the file fails like a bad sector would
+        let file = TempFile::new("short");
+        std::fs::write(&file.0, vec![0u8; (CHUNK + 1024) as usize]).unwrap();
+        let check = SurfaceCheck::run(
+            &mut File::open(&file.0).unwrap(),
+            2 * CHUNK,
+            &mut Reporter::detached(),
+        )
+        .unwrap();
+        assert_eq!(check.checked, 2 * CHUNK);
+        let bad: Vec<_> = check
+            .ranges
+            .iter()
+            .filter(|range| range.kind == RangeKind::Bad)
+            .collect();
+        assert_eq!(bad, [&range(RangeKind::Bad, CHUNK + 1024, CHUNK - 1024)]);
+    }
+
+    #[test]
+    fn partition_overlap() {
+        let partitions = [
+            block("EFI", CHUNK, CHUNK),
+            block("Root", 2 * CHUNK, 8 * CHUNK),
+            Block {
+                partition: None,
+                ..block("", 10 * CHUNK, CHUNK)
+            },
+        ];
+        let names = |offset, length| range(RangeKind::Bad, offset, length).partitions(&partitions);
+        assert_eq!(names(0, 512), Vec::<&str>::new());
+        assert_eq!(names(CHUNK - 512, 512), Vec::<&str>::new());
+        assert_eq!(names(CHUNK, 512), ["EFI"]);
+        assert_eq!(names(2 * CHUNK - 512, 1024), ["EFI", "Root"]);
+        assert_eq!(names(10 * CHUNK, 512), Vec::<&str>::new());
+        assert_eq!(
+            range(RangeKind::Bad, 0, 512).partitions_for_display(&partitions),
+            "Unallocated Space"
+        );
+        assert_eq!(
+            range(RangeKind::Bad, 2 * CHUNK - 512, 1024).partitions_for_display(&partitions),
+            "EFI, Root"
+        );
+    }
+
+    #[test]
+    fn lengths() {
+        assert_eq!(
+            range(RangeKind::Bad, 0, 512).length_for_display(),
+            "512 bytes"
+        );
+        assert_eq!(
+            range(RangeKind::Bad, 0, 4096).length_for_display(),
+            "4.0 KiB"
+        );
+        assert_eq!(
+            range(RangeKind::Slow, 0, 3 * CHUNK / 2).length_for_display(),
+            "1.5 MiB"
+        );
+    }
+}
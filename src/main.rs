@@ -2,5 +2,12 @@ mod app;
 mod widget;
 
 fn main() -> Result<(), cosmic::iced::Error> {
-    cosmic::app::run::<app::App>(cosmic::app::Settings::default(), ())
+    // A device or image file to open right away, e.g. handed over by a file manager
+    let flags = app::Flags {
+        path: std::env::args_os().nth(1).map(|path| {
+            let path = std::path::PathBuf::from(path);
+            std::fs::canonicalize(&path).unwrap_or(path)
+        }),
+    };
+    cosmic::app::run::<app::App>(cosmic::app::Settings::default(), flags)
 }
@@ -0,0 +1,88 @@
+use cosmic::iced::{Color, Point};
+use cosmic::prelude::*;
+use cosmic::widget::canvas::{self, Stroke};
+
+/// Values drawn evenly spaced over the width of a graph, scaled to `max`.
+#[derive(Clone, Debug)]
+pub struct Series {
+    pub color: Color,
+    pub values: Vec<f32>,
+    pub max: f32,
+    /// Draws single dots instead of a line, for values without an order
+    pub points: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Graph {
+    pub series: Vec<Series>,
+}
+
+impl canvas::Program<Result<crate::app::message::AppMessage, crate::app::error::Error>, Theme>
+    for Graph
+{
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: cosmic::iced::Rectangle,
+        _cursor: cosmic::iced_core::mouse::Cursor,
+    ) -> Vec<cosmic::widget::canvas::Geometry<Renderer>> {
+        let cosmic = theme.cosmic();
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+        let (width, height) = (frame.width(), frame.height());
+
+        frame.fill_rectangle(
+            Point::ORIGIN,
+            frame.size(),
+            Color::from(cosmic.primary_component_color()).scale_alpha(0.3),
+        );
+        // Quarters of the maximum as guides
+        for quarter in 1..4 {
+            let y = height * quarter as f32 / 4.0;
+            frame.stroke(
+                &canvas::Path::line(Point::new(0.0, y), Point::new(width, y)),
+                Stroke::default()
+                    .with_color(cosmic.primary_component_color().into())
+                    .with_width(1.0),
+            );
+        }
+
+        for series in &self.series {
+            if series.values.is_empty() || series.max <= 0.0 {
+                continue;
+            }
+            let step = width / series.values.len() as f32;
+            let point = |index: usize, value: f32| {
+                Point::new(
+                    step * (index as f32 + 0.5),
+                    height - (value / series.max).clamp(0.0, 1.0) * height,
+                )
+            };
+            if series.points {
+                for (index, value) in series.values.iter().enumerate() {
+                    frame.fill(
+                        &canvas::Path::circle(point(index, *value), 2.0),
+                        series.color,
+                    );
+                }
+                continue;
+            }
+            let mut line = canvas::path::Builder::new();
+            for (index, value) in series.values.iter().enumerate() {
+                match index {
+                    0 => line.move_to(point(index, *value)),
+                    _ => line.line_to(point(index, *value)),
+                }
+            }
+            frame.stroke(
+                &line.build(),
+                Stroke::default().with_color(series.color).with_width(2.0),
+            );
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
@@ -1,2 +1,6 @@
+pub mod graph;
 pub mod ring;
+pub mod surface_map;
+pub use graph::Graph;
 pub use ring::Ring;
+pub use surface_map::SurfaceMap;
@@ -9,6 +9,31 @@ pub struct RingSection {
     pub index: usize,
 }
 
+/// Colors cycled through for the sections of a ring.
+const SECTION_COLORS: [Color; 6] = [
+    Color::from_rgb(0.39, 0.62, 0.90),
+    Color::from_rgb(0.58, 0.80, 0.42),
+    Color::from_rgb(0.96, 0.69, 0.31),
+    Color::from_rgb(0.82, 0.45, 0.78),
+    Color::from_rgb(0.36, 0.78, 0.76),
+    Color::from_rgb(0.93, 0.45, 0.45),
+];
+
+pub fn section_color(index: usize) -> Color {
+    SECTION_COLORS[index % SECTION_COLORS.len()]
+}
+
+impl RingSection {
+    /// Unallocated space, drawn as a gap over the background circle.
+    pub fn free(size: usize) -> Self {
+        Self {
+            color: Color::TRANSPARENT,
+            size,
+            index: usize::MAX,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Ring {
     pub sections: Vec<RingSection>,
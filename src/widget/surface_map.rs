@@ -0,0 +1,67 @@
+use cosmic::iced::{Color, Point, Size};
+use cosmic::prelude::*;
+use cosmic::widget::canvas;
+
+use super::ring::section_color;
+
+pub const COLUMNS: usize = 64;
+pub const ROWS: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cell {
+    /// Not read, the check was cancelled before reaching it
+    Unchecked,
+    Good,
+    Slow,
+    Bad,
+}
+
+impl Cell {
+    pub fn color(&self) -> Option<Color> {
+        match self {
+            Self::Unchecked => None,
+            Self::Good => Some(section_color(1)),
+            Self::Slow => Some(section_color(2)),
+            Self::Bad => Some(section_color(5)),
+        }
+    }
+}
+
+/// The device drawn as a grid of `COLUMNS` by `ROWS` cells, from left to right and top to bottom.
+#[derive(Clone, Debug, Default)]
+pub struct SurfaceMap {
+    pub cells: Vec<Cell>,
+}
+
+impl canvas::Program<Result<crate::app::message::AppMessage, crate::app::error::Error>, Theme>
+    for SurfaceMap
+{
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: cosmic::iced::Rectangle,
+        _cursor: cosmic::iced_core::mouse::Cursor,
+    ) -> Vec<cosmic::widget::canvas::Geometry<Renderer>> {
+        let cosmic = theme.cosmic();
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+
+        let cell = (frame.width() / COLUMNS as f32).min(frame.height() / ROWS as f32);
+        let gap = (cell * 0.15).max(1.0);
+        for (index, state) in self.cells.iter().enumerate().take(COLUMNS * ROWS) {
+            let (row, column) = (index / COLUMNS, index % COLUMNS);
+            frame.fill_rectangle(
+                Point::new(column as f32 * cell, row as f32 * cell),
+                Size::new(cell - gap, cell - gap),
+                state
+                    .color()
+                    .unwrap_or(cosmic.primary_component_color().into()),
+            );
+        }
+
+        vec![frame.into_geometry()]
+    }
+}